@@ -0,0 +1,492 @@
+//! enjoy gerrit list：查询自己在 Gerrit 上打开的改动
+//! ssh 地址的远程仓库用 ssh <host> gerrit query，http 地址用 curl 请求 REST 接口 /a/changes/，
+//! 需要在 ~/.netrc 中配置 Gerrit 的 HTTP 密码
+
+use crate::error::GerritError;
+use crate::format::{display_width, pad_column};
+use crate::gerrit::Server;
+use crate::i18n::tr;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::process::{Command, Stdio};
+
+/// 查询的条件
+const QUERY: &str = "status:open owner:self";
+
+/// 一个打开的改动
+#[derive(Debug)]
+pub(crate) struct Change {
+    pub(crate) number: u64,
+    pub(crate) subject: String,
+    pub(crate) branch: String,
+    pub(crate) status: String,
+    /// 每个评审标签的结果，如 Code-Review → +2；有负分时取最低分，否则取最高分
+    pub(crate) labels: BTreeMap<String, i32>,
+}
+
+/// 执行外部命令得到的结果
+pub(crate) struct Fetched {
+    pub(crate) code: Option<i32>,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+}
+
+/// 访问服务器：执行 ssh 或 curl 并取得输出，测试时可以替换为固定的输出
+pub(crate) trait Fetch {
+    fn fetch(&mut self, program: &str, args: &[String]) -> io::Result<Fetched>;
+}
+
+/// 真正执行命令
+pub(crate) struct CommandFetch;
+
+impl Fetch for CommandFetch {
+    fn fetch(&mut self, program: &str, args: &[String]) -> io::Result<Fetched> {
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()?;
+        Ok(Fetched {
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// 查询 server 上自己打开的改动
+pub(crate) fn list(server: &Server, fetch: &mut dyn Fetch) -> Result<Vec<Change>, GerritError> {
+    let (program, args) = query_command(server);
+    let fetched = fetch.fetch(program, &args).map_err(|err| {
+        GerritError::Query(if err.kind() == io::ErrorKind::NotFound {
+            tr!(
+                "找不到 {} 命令，请安装它并确认它在 PATH 中",
+                "{} not found, install it and check that it is in PATH",
+                program
+            )
+        } else {
+            tr!("无法执行 {}: {}", "cannot run {}: {}", program, err)
+        })
+    })?;
+    let stderr = fetched.stderr.trim();
+    match (server, fetched.code) {
+        (_, Some(0)) => {}
+        (Server::Ssh { host, .. }, _) => {
+            return Err(GerritError::Query(tr!(
+                "无法通过 ssh 查询 {}: {}\n请确认 ssh 公钥已添加到 Gerrit 的设置中，并且远程仓库地址中的端口正确",
+                "cannot query {} over ssh: {}\nmake sure your ssh public key is added in Gerrit's settings and the port in the remote URL is right",
+                host,
+                stderr
+            )));
+        }
+        // curl -f 在 HTTP 状态为 400 以上时以 22 退出
+        (Server::Http { base }, Some(22)) => {
+            return Err(GerritError::Query(tr!(
+                "{} 拒绝了查询: {}\n查询自己的改动需要登录: 请在 ~/.netrc 中为该主机配置 Gerrit 的 HTTP 密码 \
+                 (Gerrit 设置 → HTTP Credentials)，或改用 ssh 地址的远程仓库",
+                "{} refused the query: {}\nlisting your own changes needs a login: add Gerrit's HTTP password for this host \
+                 to ~/.netrc (Gerrit settings → HTTP Credentials), or use a remote with an ssh URL",
+                base,
+                stderr
+            )));
+        }
+        (Server::Http { base }, _) => {
+            return Err(GerritError::Query(tr!(
+                "无法访问 {}: {}",
+                "cannot reach {}: {}",
+                base,
+                stderr
+            )));
+        }
+    }
+    let parsed = match server {
+        Server::Ssh { .. } => parse_ssh(&fetched.stdout),
+        Server::Http { .. } => parse_rest(&fetched.stdout),
+    };
+    parsed.map_err(|message| {
+        GerritError::Query(tr!(
+            "无法解析服务器的回应: {}",
+            "cannot parse the server's response: {}",
+            message
+        ))
+    })
+}
+
+/// 查询用的命令和参数
+fn query_command(server: &Server) -> (&'static str, Vec<String>) {
+    match server {
+        Server::Ssh { host, port } => {
+            let mut args = Vec::new();
+            if let Some(port) = port {
+                args.extend(["-p".to_string(), port.clone()]);
+            }
+            args.push(host.clone());
+            args.extend(
+                [
+                    "gerrit",
+                    "query",
+                    "--format=JSON",
+                    "--current-patch-set",
+                    QUERY,
+                ]
+                .map(str::to_string),
+            );
+            ("ssh", args)
+        }
+        Server::Http { base } => (
+            "curl",
+            vec![
+                "-fsS".to_string(),
+                "--netrc-optional".to_string(),
+                format!(
+                    "{}/a/changes/?q={}&o=DETAILED_LABELS",
+                    base,
+                    QUERY.replace(' ', "+")
+                ),
+            ],
+        ),
+    }
+}
+
+/// 旧版本的 gerrit query 把数字写成字符串
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Number {
+    Int(i64),
+    Text(String),
+}
+
+impl Number {
+    fn value(&self) -> Option<i64> {
+        match self {
+            Number::Int(n) => Some(*n),
+            Number::Text(text) => text.trim().parse().ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SshChange {
+    number: Number,
+    subject: String,
+    branch: String,
+    status: String,
+    current_patch_set: Option<SshPatchSet>,
+}
+
+#[derive(Deserialize)]
+struct SshPatchSet {
+    #[serde(default)]
+    approvals: Vec<SshApproval>,
+}
+
+#[derive(Deserialize)]
+struct SshApproval {
+    #[serde(rename = "type")]
+    label: String,
+    value: Number,
+}
+
+/// gerrit query --format=JSON 的输出：每行一个改动，最后一行是 "type": "stats" 的统计
+fn parse_ssh(output: &str) -> Result<Vec<Change>, String> {
+    let mut changes = Vec::new();
+    for line in output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        let value: serde_json::Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+        match value.get("type").and_then(|kind| kind.as_str()) {
+            Some("stats") => continue,
+            Some("error") => {
+                let message = value.get("message").and_then(|m| m.as_str());
+                return Err(message.unwrap_or(line).to_string());
+            }
+            _ => {}
+        }
+        let change: SshChange = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        let votes = change
+            .current_patch_set
+            .map(|patch_set| patch_set.approvals)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|approval| Some((approval.label, approval.value.value()? as i32)));
+        changes.push(Change {
+            number: change
+                .number
+                .value()
+                .and_then(|n| u64::try_from(n).ok())
+                .ok_or_else(|| tr!("无效的改动编号", "invalid change number"))?,
+            subject: change.subject,
+            branch: change.branch,
+            status: change.status,
+            labels: summarize(votes),
+        });
+    }
+    Ok(changes)
+}
+
+#[derive(Deserialize)]
+struct RestChange {
+    #[serde(rename = "_number")]
+    number: u64,
+    subject: String,
+    branch: String,
+    status: String,
+    #[serde(default)]
+    labels: BTreeMap<String, RestLabel>,
+}
+
+#[derive(Deserialize)]
+struct RestLabel {
+    #[serde(default)]
+    all: Vec<RestVote>,
+}
+
+#[derive(Deserialize)]
+struct RestVote {
+    #[serde(default)]
+    value: i32,
+}
+
+/// REST 接口的回应：以 )]}' 开头（防止 XSSI），之后是改动的数组
+fn parse_rest(output: &str) -> Result<Vec<Change>, String> {
+    let json = output.trim_start().strip_prefix(")]}'").unwrap_or(output);
+    let changes: Vec<RestChange> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    Ok(changes
+        .into_iter()
+        .map(|change| {
+            let votes = change.labels.into_iter().flat_map(|(label, votes)| {
+                votes
+                    .all
+                    .into_iter()
+                    .map(move |vote| (label.clone(), vote.value))
+            });
+            Change {
+                number: change.number,
+                subject: change.subject,
+                branch: change.branch,
+                status: change.status,
+                labels: summarize(votes),
+            }
+        })
+        .collect())
+}
+
+/// 合并每个标签的所有投票：有负分时取最低分，否则取最高分；只有 0 分的标签不列出
+fn summarize(votes: impl Iterator<Item = (String, i32)>) -> BTreeMap<String, i32> {
+    let mut labels: BTreeMap<String, i32> = BTreeMap::new();
+    for (label, value) in votes.filter(|(_, value)| *value != 0) {
+        labels
+            .entry(label)
+            .and_modify(|current| {
+                *current = match (*current < 0, value < 0) {
+                    (true, true) => (*current).min(value),
+                    (true, false) => *current,
+                    (false, true) => value,
+                    (false, false) => (*current).max(value),
+                }
+            })
+            .or_insert(value);
+    }
+    labels
+}
+
+/// 评审结果的简写，如 CR+2 V-1，没有投票时为 -
+fn review(labels: &BTreeMap<String, i32>) -> String {
+    if labels.is_empty() {
+        return "-".to_string();
+    }
+    labels
+        .iter()
+        .map(|(label, value)| {
+            let name = match label.as_str() {
+                "Code-Review" => "CR",
+                "Verified" => "V",
+                other => other,
+            };
+            format!("{}{:+}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 对齐的表格：编号、分支、状态、评审、标题；标题在最后，不需要对齐
+pub(crate) fn render_table(changes: &[Change]) -> String {
+    if changes.is_empty() {
+        return tr!("没有打开的改动", "no open changes");
+    }
+    let header = [
+        tr!("编号", "change"),
+        tr!("分支", "branch"),
+        tr!("状态", "status"),
+        tr!("评审", "review"),
+        tr!("标题", "subject"),
+    ];
+    let rows: Vec<[String; 5]> = changes
+        .iter()
+        .map(|change| {
+            [
+                change.number.to_string(),
+                change.branch.clone(),
+                change.status.clone(),
+                review(&change.labels),
+                change.subject.clone(),
+            ]
+        })
+        .collect();
+    let mut widths = [0; 4];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|row| {
+            let mut line: String = widths
+                .iter()
+                .zip(row)
+                .map(|(width, cell)| pad_column(cell, width + 2))
+                .collect();
+            line.push_str(&row[4]);
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 返回固定的输出，并记下执行的命令
+    struct FakeFetch {
+        result: Option<Fetched>,
+        commands: Vec<(String, Vec<String>)>,
+    }
+
+    impl FakeFetch {
+        fn new(code: i32, stdout: &str, stderr: &str) -> Self {
+            FakeFetch {
+                result: Some(Fetched {
+                    code: Some(code),
+                    stdout: stdout.to_string(),
+                    stderr: stderr.to_string(),
+                }),
+                commands: Vec::new(),
+            }
+        }
+    }
+
+    impl Fetch for FakeFetch {
+        fn fetch(&mut self, program: &str, args: &[String]) -> io::Result<Fetched> {
+            self.commands.push((program.to_string(), args.to_vec()));
+            self.result
+                .take()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    fn ssh() -> Server {
+        Server::Ssh {
+            host: "alice@review.example.com".to_string(),
+            port: Some("29418".to_string()),
+        }
+    }
+
+    fn http() -> Server {
+        Server::Http {
+            base: "https://review.example.com".to_string(),
+        }
+    }
+
+    const SSH_OUTPUT: &str = r#"
+{"number":1234,"subject":"uart: fix baud rate","branch":"develop","status":"NEW","currentPatchSet":{"approvals":[{"type":"Code-Review","value":"2"},{"type":"Code-Review","value":"-1"},{"type":"Verified","value":"1"}]}}
+{"number":"1240","subject":"spi: add driver","branch":"main","status":"NEW"}
+{"type":"stats","rowCount":2}
+"#;
+
+    #[test]
+    fn ssh_query() {
+        let mut fetch = FakeFetch::new(0, SSH_OUTPUT, "");
+        let changes = list(&ssh(), &mut fetch).unwrap();
+        assert_eq!(
+            fetch.commands,
+            [(
+                "ssh".to_string(),
+                [
+                    "-p",
+                    "29418",
+                    "alice@review.example.com",
+                    "gerrit",
+                    "query",
+                    "--format=JSON",
+                    "--current-patch-set",
+                    QUERY
+                ]
+                .map(str::to_string)
+                .to_vec()
+            )]
+        );
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].number, 1234);
+        // 有负分时取最低分
+        assert_eq!(review(&changes[0].labels), "CR-1 V+1");
+        assert_eq!(changes[1].number, 1240);
+        assert_eq!(review(&changes[1].labels), "-");
+        let table = render_table(&changes);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "1234  develop  NEW   CR-1 V+1  uart: fix baud rate"
+        );
+        assert_eq!(lines[2], "1240  main     NEW   -         spi: add driver");
+        assert!(
+            parse_ssh(r#"{"type":"error","message":"not authorized"}"#)
+                .unwrap_err()
+                .contains("not authorized")
+        );
+    }
+
+    #[test]
+    fn rest_query() {
+        let output = r#")]}'
+[{"_number":42,"subject":"Add SPI","branch":"main","status":"NEW",
+  "labels":{"Code-Review":{"all":[{"value":1},{"value":2},{}]},"Verified":{"all":[{"value":0}]}}}]"#;
+        let mut fetch = FakeFetch::new(0, output, "");
+        let changes = list(&http(), &mut fetch).unwrap();
+        assert_eq!(fetch.commands[0].0, "curl");
+        assert_eq!(
+            fetch.commands[0].1.last().unwrap(),
+            "https://review.example.com/a/changes/?q=status:open+owner:self&o=DETAILED_LABELS"
+        );
+        assert_eq!(changes[0].number, 42);
+        // 只有 0 分的标签不列出
+        assert_eq!(review(&changes[0].labels), "CR+2");
+        assert_eq!(render_table(&[]), "没有打开的改动");
+    }
+
+    #[test]
+    fn query_errors() {
+        let message = |server: &Server, fetch: &mut FakeFetch| match list(server, fetch) {
+            Err(GerritError::Query(message)) => message,
+            other => panic!("{:?}", other.map(|changes| changes.len())),
+        };
+        let mut fetch = FakeFetch::new(255, "", "Permission denied (publickey).");
+        assert!(message(&ssh(), &mut fetch).contains("ssh 公钥"));
+        let mut fetch = FakeFetch::new(22, "", "The requested URL returned error: 401");
+        assert!(message(&http(), &mut fetch).contains("~/.netrc"));
+        let mut fetch = FakeFetch::new(6, "", "Could not resolve host");
+        assert!(message(&http(), &mut fetch).starts_with("无法访问"));
+        let mut fetch = FakeFetch::new(0, "<html>", "");
+        assert!(message(&http(), &mut fetch).starts_with("无法解析服务器的回应"));
+        let mut fetch = FakeFetch {
+            result: None,
+            commands: Vec::new(),
+        };
+        assert!(message(&ssh(), &mut fetch).starts_with("找不到 ssh 命令"));
+    }
+}
@@ -0,0 +1,3107 @@
+//! 命令行参数的定义，以及计算、交互模式和各个子命令的执行
+
+use crate::changes;
+use crate::completions;
+use crate::config::{Config, config_path};
+use crate::debug::{self, debug};
+use crate::error::{EnjoyError, EvalError, GerritError};
+use crate::expr::{
+    ANS, CONSTANTS, DEFAULT_MAX_DEPTH, DEFAULT_MAX_TOKENS, Dimension, Dimensioned, EvalContext,
+    EvalOptions, ExprToken, FUNCTIONS, Operator, Saved, Unevaluated, Value, check_limits,
+    evaluate_statements, lookup_function, parse_number, read_tokens_from_json,
+    sign_extend_literals, tokenize_args, top_level_positions,
+};
+use crate::format::{
+    Base, ByteOrder, FieldSpec, FormattedResult, Style, binary_row, display_width, duration_line,
+    get_padded_binary, group_hex_digits, human_size_line, index_row, le_byte_sequence,
+    minimal_byte_count, pad_column, parse_field_spec, print_ascii, print_byte_order,
+    print_conversion, print_fields, print_float_info, print_ieee754_fields, print_ipv4,
+    print_result, print_set_bits, print_timestamp, split_into_groups, to_radix,
+};
+use crate::gerrit;
+use crate::i18n::{Lang, Message, lang, set_lang, tr};
+use crate::line_editor;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use serde::Serialize;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::time::Instant;
+
+/// 这是一个简单的命令行工具
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+#[command(group(clap::ArgGroup::new("expr").args(["calc", "tokens_from_json"])))]
+#[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+#[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+pub(crate) struct Args {
+    /// 调试输出，写到标准错误：-d 列出表达式元素及其序号，gerrit 给出执行的命令和环境；
+    /// -dd 另外跟踪求值的每一步运算和括号；-ddd 另外给出耗时和原始的命令行参数
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    debug: u8,
+
+    /// 计算表达式 (已弃用，请改用 enjoy calc)，可以重复给出，后面的表达式可以用 ans 引用前一个结果；
+    /// 表达式的写法见 enjoy calc --help
+    #[arg(
+        short,
+        long,
+        num_args = 1.., // 接收至少一个参数
+    )]
+    calc: Vec<String>, // 原样保存参数，计算前再拆分为表达式元素；多次出现时按出现次数分组
+
+    /// 对 n 取 START..END（不含 END）中的每个值计算表达式，按表格输出: enjoy calc --table 0..16 'bit[n]'
+    /// 不给出表达式时直接列出范围内的数值，最多 100 万行
+    #[arg(
+        long,
+        value_name = "START..END",
+        value_parser = parse_range,
+        conflicts_with_all = ["tokens_from_json", "assert", "check", "json", "quiet"],
+        global = true,
+    )]
+    table: Option<(i128, i128)>,
+
+    /// --table 的步长，默认为 1
+    #[arg(long, value_name = "N", requires = "table", value_parser = parse_step, global = true)]
+    step: Option<i128>,
+
+    /// 从 JSON 文件读取 token 数组并计算 (--tokens-from-json tokens.json)
+    #[arg(long, value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath, global = true)]
+    tokens_from_json: Option<PathBuf>,
+
+    /// 从标准输入逐行读取表达式并计算，每行一个表达式，按 --output、--quiet 或 --json 输出结果
+    /// 跳过空行和以 # 开头的注释；出错的行向标准错误输出带行号的错误并继续，任何一行出错时最后以非零状态退出 (解析错误为 2，计算错误为 3)
+    /// 与 --shell 一起使用时变量名带上行号 (RESULT3_DEC)
+    #[arg(long, default_value_t = false, conflicts_with_all = ["expr", "table", "diff", "assert"], global = true)]
+    stdin: bool,
+
+    /// 按顺序计算文件中的表达式，每行一个，跳过空行和以 # 开头的注释 (--file worksheet.enjoy)
+    /// 前面的行赋值的变量可以在后面的行中使用；每个结果前面给出行号和原文，
+    /// 出错时给出 文件:行号: 错误，默认停止计算并以非零状态退出
+    #[arg(
+        long,
+        value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath,
+        conflicts_with_all = ["expr", "table", "diff", "assert", "stdin"],
+        global = true,
+    )]
+    file: Option<PathBuf>,
+
+    /// 与 --file 一起使用时，遇到出错的行继续计算后面的行，最后仍以非零状态退出
+    #[arg(long, default_value_t = false, requires = "file", global = true)]
+    keep_going: bool,
+
+    /// 从标准输入逐行读取数值并聚合，按 --output、--quiet 或 --json 输出结果: grep ... | awk '{print $3}' | enjoy --reduce sum
+    /// 每行一个数字字面量（可以写作 0x、0b、4k 等），跳过空行；avg 同时给出整数商和余数；
+    /// 无法解析的行给出行号并以状态 2 退出，和超出 128 位时以状态 3 退出
+    #[arg(
+        long,
+        value_name = "OP",
+        value_enum,
+        conflicts_with_all = ["expr", "table", "diff", "assert", "check", "stdin", "file"],
+        global = true,
+    )]
+    reduce: Option<Reduce>,
+
+    /// 与 --reduce 一起使用时跳过无法解析的行，最后在标准错误中给出跳过的行数
+    #[arg(long, default_value_t = false, requires = "reduce", global = true)]
+    skip_bad: bool,
+
+    /// 交互模式：逐行读取并计算表达式，ans 和变量在各行之间保留，输入 :help 查看命令，Ctrl-D 退出
+    #[arg(
+        short,
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["expr", "table", "diff", "assert", "check", "stdin", "file", "reduce"],
+        global = true,
+    )]
+    interactive: bool,
+
+    /// 交互模式下不读写历史记录文件，历史记录只在本次会话中保留；标准输入不是终端时总是不读写
+    #[arg(long, default_value_t = false, requires = "interactive", global = true)]
+    no_history: bool,
+
+    /// 历史记录文件最多保留的条数
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1000,
+        requires = "interactive",
+        global = true
+    )]
+    history_size: usize,
+
+    /// 按 64 位寄存器计算，溢出时按补码回绕，而不是报错
+    #[arg(long, default_value_t = false, global = true)]
+    wrap: bool,
+
+    /// 按寄存器位宽计算，溢出时限制在最小值和最大值之间，并提示发生了饱和 (--width 16 --saturate --calc 30000 + 10000)
+    /// 除零仍然报错
+    #[arg(long, default_value_t = false, conflicts_with_all = ["wrap", "modulus"], global = true)]
+    saturate: bool,
+
+    /// 按 8/16/32/64 位寄存器显示结果，十六进制和二进制补零到该位宽 (--width 32 --calc 1)，结果放不下时给出警告
+    /// 与 --wrap 或 --saturate 一起使用时，每一步都截断或限制到该位宽 (--width 8 --wrap --calc 200 + 100)，回绕模式下字面量按位模式理解
+    #[arg(long, value_name = "BITS", value_parser = parse_register_bits, global = true)]
+    width: Option<u32>,
+
+    /// 按无符号整数计算，结果按 u64 显示，默认为有符号 (i64)
+    /// 中间结果在 128 位内计算，超出 64 位的结果会单独提示
+    #[arg(short, long, default_value_t = false, global = true)]
+    unsigned: bool,
+
+    /// 按有符号整数计算和解释结果（默认），与 --unsigned 相对
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with = "unsigned",
+        global = true
+    )]
+    signed: bool,
+
+    /// 取模运算，每一步运算的结果都对 N 取余并规约到 0..N-1 (--mod 0x10000)，N 的范围为 1..=u64::MAX
+    /// 乘法和乘方按模计算，中间结果不会溢出；/ % 和位运算按普通整数计算后再取余
+    #[arg(
+        long = "mod",
+        value_name = "N",
+        value_parser = parse_modulus,
+        conflicts_with = "wrap",
+        global = true,
+    )]
+    modulus: Option<i128>,
+
+    /// 列出表达式中可以使用的命名常量 (--list-constants)
+    #[arg(long, default_value_t = false, global = true)]
+    list_constants: bool,
+
+    /// 按 f64 浮点数计算 (--float)，表达式中出现小数（如 3.3）时会自动启用
+    /// 浮点模式只支持 + - x / % **，整数会自动转换为浮点数
+    #[arg(long, default_value_t = false, conflicts_with_all = ["wrap", "saturate", "unsigned", "modulus"], global = true)]
+    float: bool,
+
+    /// 表达式允许的最大 token 数量
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_TOKENS, global = true)]
+    max_tokens: usize,
+
+    /// 表达式允许的最大括号嵌套深度
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_DEPTH, global = true)]
+    max_depth: usize,
+
+    /// 断言计算结果等于期望值，匹配时静默退出，否则以非零状态退出 (--assert 42)
+    /// 需要给出表达式 (--calc、--tokens-from-json 或 calc 子命令)
+    #[arg(long, value_parser = parse_number_arg, global = true)]
+    assert: Option<i128>,
+
+    /// 只检查表达式的结构（括号、操作数、函数参数等）而不计算，没有问题时不输出，否则以非零状态退出
+    #[arg(
+        long,
+        conflicts_with = "assert",
+        default_value_t = false,
+        global = true
+    )]
+    check: bool,
+
+    /// 把结果当作字节数，按二进制单位 (KiB MiB GiB TiB) 和 SI 单位 (KB MB GB) 给出大小 (--human)
+    #[arg(long, default_value_t = false, global = true)]
+    human: bool,
+
+    /// 把结果的低 32 或 64 位按 IEEE 754 浮点数解释，列出符号、指数和尾数 (--calc 0x3F800000 --float-bits 32)
+    #[arg(long, value_name = "BITS", value_parser = parse_float_bits, global = true)]
+    float_bits: Option<u32>,
+
+    /// 在结果之后按小端序和大端序列出各个字节 (--endian)，字节数由 --width 决定，否则取能放下结果的最小的 2 的幂
+    /// 指定字节序时只列出该字节序 (--endian be)，也用于选择 --emit bytes 的字节序（默认为小端序）
+    #[arg(long, value_name = "ORDER", value_enum, num_args = 0..=1, global = true)]
+    endian: Option<Option<ByteOrder>>,
+
+    /// 在结果之后按大端序和小端序把各个字节显示为 ASCII 字符，不可打印的字节写作 \xNN (--ascii)
+    #[arg(long, default_value_t = false, global = true)]
+    ascii: bool,
+
+    /// 把结果当作 Unix 时间戳，给出 UTC (ISO 8601) 和本地时间 (--time)，按数量级判断是秒还是毫秒
+    #[arg(long, default_value_t = false, global = true)]
+    time: bool,
+
+    /// 把结果的低 32 位显示为 IPv4 地址，是连续的掩码时同时给出前缀长度 (--ipv4 --calc 0xFFFFFF00)
+    #[arg(long, default_value_t = false, global = true)]
+    ipv4: bool,
+
+    /// 在结果之后从高到低列出为 1 的位 (--bits)，与 --quiet 一起使用时只输出逗号分隔的位索引
+    #[arg(long, default_value_t = false, global = true)]
+    bits: bool,
+
+    /// 把结果按位段拆开，每个位段一行给出名称、位范围和值 (--fields "31:28=MODE,27:16=DIV,15:0=ADDR")
+    /// 位段必须在 0..=63 范围内，只写一位时可省略低位 (5=EN)，重叠的位段会给出警告
+    #[arg(long, value_name = "SPEC", value_parser = parse_field_spec, global = true)]
+    fields: Option<FieldSpec>,
+
+    /// 寄存器描述文件 (JSON)，与 --decode 一起使用，格式见 regmaps/stm32f1_rcc.json
+    #[arg(long, value_name = "FILE",
+        value_hint = clap::ValueHint::FilePath, requires = "decode", global = true)]
+    regmap: Option<PathBuf>,
+
+    /// 按寄存器描述文件中的寄存器解码结果，取值有名称时一并给出 (--regmap stm32f1_rcc.json --decode RCC_CFGR)
+    #[arg(long, value_name = "REGISTER", requires = "regmap", global = true)]
+    decode: Option<String>,
+
+    /// 与 --fields 或 --decode 一起使用时，把没有命名的位作为 RESERVED 一并列出
+    #[arg(long, default_value_t = false, requires = "layout", global = true)]
+    reserved: bool,
+
+    /// 逐位比较两个值，两者都可以是表达式 (--diff 0xDEADBEEF 0xDEADBEAF)
+    /// 相同时以 0 退出，不同时以 1 退出，计算出错时以 2 退出；与 --quiet 一起使用时只设置退出状态
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["A", "B"],
+        conflicts_with_all = ["expr", "table", "assert", "check", "json"],
+        global = true,
+    )]
+    diff: Option<Vec<String>>,
+
+    /// 整数结果输出哪些进制，用逗号分隔，按给出的顺序输出 (--output hex,dec)，可选 dec hex bin oct
+    #[arg(
+        long,
+        value_name = "BASES",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "dec,hex,oct,bin",
+        global = true
+    )]
+    output: Vec<Base>,
+
+    /// 额外输出一行任意进制 (2..=36) 的结果，数字为 0-9a-z，负数带负号 (--radix 36)
+    /// 与 --quiet 一起使用时只输出该进制的结果
+    #[arg(long, value_name = "N", value_parser = parse_radix, global = true)]
+    radix: Option<u32>,
+
+    /// 位宽正好为 W 位的十六进制和二进制字面量按补码理解 (--in-signed 16 --calc 0xFFFC + 10 得 6)
+    /// 位宽按书写的数字个数计算（含前导零），较窄的字面量仍为正数，超出 W 位的字面量是错误；十进制字面量不受影响
+    #[arg(long, value_name = "W", value_parser = parse_register_bits, global = true)]
+    in_signed: Option<u32>,
+
+    /// 只输出结果的值，便于在 shell 中使用: SIZE=$(enjoy -q calc 4 x 1024 x 1024)
+    /// 可以用 = 指定进制 (-q=hex 或 --quiet=hex)，默认为十进制；出错时只向标准错误输出信息，并以非零状态退出
+    #[arg(
+        short,
+        long,
+        value_name = "BASE",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "dec",
+        conflicts_with = "json",
+        global = true,
+    )]
+    quiet: Option<Base>,
+
+    /// 计算成功后把结果复制到系统剪贴板，默认为带 0x 前缀的十六进制 (--copy=dec)
+    /// 依次尝试 pbcopy、wl-copy、xclip、xsel 和 clip.exe，确认信息输出到标准错误
+    #[arg(
+        long,
+        value_name = "BASE",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "hex",
+        global = true,
+    )]
+    copy: Option<Base>,
+
+    /// --quiet 模式下十六进制、八进制和二进制结果带上 0x、0o、0b 前缀
+    #[arg(long, default_value_t = false, requires = "quiet", global = true)]
+    prefix: bool,
+
+    /// 以 JSON 格式输出结果，便于其他程序解析；出错时输出 {"message": ..., "token": 出错元素的下标} 并以非零状态退出
+    /// 多个表达式时每行输出一个 JSON 对象，此时标准输出中没有其他内容
+    #[arg(long, default_value_t = false, conflicts_with_all = ["assert", "check"], global = true)]
+    json: bool,
+
+    /// 以 shell 变量赋值的形式输出结果，可以直接 eval: eval "$(enjoy --shell --calc 4 x 1024)"
+    /// 整数结果输出 RESULT_DEC、RESULT_HEX、RESULT_OCT、RESULT_BIN，浮点数结果输出 RESULT_DEC 和 RESULT_IEEE754；
+    /// 多个表达式时变量名带上序号 (RESULT1_DEC)，出错时只向标准错误输出信息，并以非零状态退出
+    #[arg(long, default_value_t = false, conflicts_with_all = ["json", "quiet", "assert", "check", "table", "diff"], global = true)]
+    shell: bool,
+
+    /// 把结果输出为可以直接粘贴的常量定义 (--emit c:FOO_MASK 或 --emit rust:FOO_MASK)
+    /// 非负数按无符号类型输出十六进制，负数按有符号类型输出十进制；类型的位宽取自 --width，
+    /// 没有给出时取能放下结果的最小位宽
+    /// --emit bytes 按 C 和 Rust 的写法输出字节数组，负数取补码，字节序由 --endian 选择
+    #[arg(
+        long,
+        value_name = "LANG:NAME",
+        value_parser = parse_emit,
+        conflicts_with_all = ["json", "quiet", "shell", "assert", "check", "table", "diff"],
+        global = true,
+    )]
+    emit: Option<Emit>,
+
+    /// --shell 输出的变量名前缀 (--shell-prefix ADDR_)
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        default_value = "RESULT_",
+        value_parser = parse_shell_prefix,
+        requires = "shell",
+        global = true,
+    )]
+    shell_prefix: String,
+
+    /// 二进制输出中相邻分组的 1 交替使用不同的颜色，便于阅读 (--zebra)
+    #[arg(
+        long,
+        alias = "color-bit-groups",
+        default_value_t = false,
+        global = true
+    )]
+    zebra: bool,
+
+    /// 二进制输出每组的位数，可以是 4、8 或 16，位索引标在每组最低位的下方 (--group-bits 8)
+    #[arg(long, value_name = "N", default_value_t = 4, value_parser = parse_group_bits, global = true)]
+    group_bits: usize,
+
+    /// 十进制结果加千位分隔符 (1,610,612,736)，十六进制结果每 4 位加下划线 (0x5F5E_1000)，--quiet 和 --json 不受影响
+    #[arg(long, default_value_t = false, global = true)]
+    sep: bool,
+
+    /// 不输出 ANSI 颜色；默认只在标准输出为终端且未设置 NO_COLOR 环境变量时输出颜色
+    /// 二进制输出中为 1 的位高亮显示，为 0 的位使用暗色
+    #[arg(long, default_value_t = false, global = true)]
+    no_color: bool,
+
+    /// 输出和错误信息的语言 (--lang en-US)，默认按 LC_ALL、LC_MESSAGES、LANG 环境变量选择，都不是中文或英文时为中文
+    /// 只影响计算结果和错误信息，--help 的说明仍为中文
+    #[arg(long, value_name = "LANG", value_enum, global = true)]
+    lang: Option<Lang>,
+
+    /// 不读取配置文件 (位置见 enjoy config path)，只使用命令行参数和内置的默认值
+    /// 配置文件可以给出 output、width、group_bits、color 的默认值和不带参数运行时计算的 default_calc，
+    /// 在 [constants] 中定义表达式中可用的常量，并定义 enjoy gerrit 的配置 ([gerrit.profiles.<名称>])
+    #[arg(long, default_value_t = false, global = true)]
+    no_config: bool,
+
+    /// 配置文件 [constants] 中的常量，计算前放入上下文
+    #[arg(skip)]
+    constants: Vec<(String, i128)>,
+
+    /// 配置文件中的 default_calc，没有给出表达式时计算
+    #[arg(skip)]
+    default_calc: Option<String>,
+
+    /// 是否启用 Gerrit 功能 (已弃用，请改用 enjoy gerrit)
+    #[arg(short, long, default_value_t = false, conflicts_with = "json")]
+    gerrit: bool,
+
+    /// 与 --gerrit 一起使用，评审的目标分支 (已弃用，请改用 enjoy gerrit --branch)
+    #[arg(
+        long,
+        value_name = "NAME",
+        default_value = gerrit::DEFAULT_BRANCH,
+        value_parser = gerrit::check_branch,
+        requires = "gerrit"
+    )]
+    branch: String,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+/// 表达式的写法，显示在 enjoy calc --help 的末尾
+const EXPR_HELP: &str = "\
+表达式的写法:
+
+操作符
+  a + b - c / d % e '**' f '<<' g '&' h ^ '~' i '|' j，& | ~ ** << >> 等 shell 特殊字符需要加引号
+  负数可直接写作 -5 或 -0x10；乘号写作 x 或 '*': enjoy calc '(len + 7) / 8 * 8'
+  括号可以写作 [ ] 或 ( )，两者等价，( ) 同样需要加引号: enjoy calc '(' 1 + 2 ')' x 3
+  也可以把整个表达式放进一个参数: enjoy calc '1+2x[3+4]'
+  比较操作符 == != < <= > >= 优先级最低，结果为 1 或 0，顶层比较为假时以 1 退出:
+    enjoy calc '0x1000 x 4 == 0x4000'
+
+数字
+  十进制、0x 十六进制、0b 二进制、0o 八进制，数字之间可以用 _ 分隔: 0xFFFF_FFFF
+  汇编风格的 0FFh、FFh、'$FF' (十六进制) 和 1010b (二进制): enjoy calc 0FFh + 1010b
+  其他进制写作 <进制>#<数字>，进制为 2 到 36，数字 0-9 a-z 不区分大小写: enjoy calc '36#Z1G + 0x10'
+  可以直接粘贴 C 代码中带 U、UL、ULL 等后缀的常量和带千位分隔符的十进制数:
+    enjoy calc 0x00200000UL + 1,048,576
+  单引号括起的 ASCII 字符按编码计算，多个字符按大端序拼接（如 'ABCD'）: enjoy calc \"'A' x 0x100 + 'B'\"
+  可以使用 KB、MB、PAGE_SIZE 等命名常量 (--list-constants 查看): enjoy calc 4 x MB
+
+单位
+  十进制数可以带容量后缀 k M G T P E（不区分大小写），按 2 的幂计算: enjoy calc 512M / 4k
+  结果为整数的科学计数法按十进制整数计算，指数不能为负，结果不能超出 64 位有符号整数:
+  enjoy calc 1e6 / 25e3，1.5e1 为 15；1e-3 不是 1E 减 3，后者需要写作 '1E - 3'
+  频率后缀 Hz kHz MHz GHz 按 10 的幂计算（不区分大小写）: 72MHz 为 72000000，而 72M 为 72 x 2^20
+  带时间单位 ns us ms s min hr 的十进制数（可以是小数）按纳秒计算，结果是时间时另外给出便于阅读的写法:
+    enjoy calc 2s + 350ms + 20us
+  时间只能与时间相加减和比较，可以乘以或除以普通的数，两个时间之比是普通的数 (90s / 1min 为 1)；
+  小时写作 hr，因为 10h 是十六进制数
+
+函数（参数写在括号中并用逗号分隔）
+  abs min max gcd lcm: enjoy calc 'gcd[48, 180]'
+  bit[n] 即 1 << n，mask[n] 即低 n 位全为 1 的掩码 (mask[64] 为 u64::MAX): enjoy calc 'mask[12]'
+  setbit[v, n...] clrbit[v, n...] tglbit[v, n...] 置位、清除或翻转第 n 位 (0..63)，可以一次给出多个位
+  align_up[v, a] 和 align_down[v, a] 把 v 向上或向下对齐到 a（必须是 2 的幂）
+  period_ns[f] 给出频率 f (Hz) 的周期，cycles[t, f] 给出 t 纳秒在频率 f 下的时钟周期数，
+  两者都四舍五入到整数 (.5 向上舍入，如 period_ns[3MHz] 为 333): enjoy calc 'cycles[1500, 48MHz]'
+  rotl[v, n, w] 和 rotr[v, n, w] 在 w 位（8/16/32/64）内循环移位；rbit[v, w] 反转低 w 位
+  byte[v, n] 取第 n 个字节 (0..7)，nibble[v, n] 取第 n 个半字节 (0..15)，第 0 个为最低位
+  bswap16 bswap32 bswap64 按对应位宽翻转字节序，revnibbles[v] 反转十六进制数字 (0x1234 为 0x4321，给出 --width 时按该位宽)
+  sext[v, w] 把低 w 位按符号扩展，zext[v, w] 只保留低 w 位 (w 为 1..64)
+  crc32[v, n] (IEEE) 和 crc16[v, n] (CCITT-FALSE) 计算 v 的低 n 个字节（1..8）的校验值，
+  字节按小端序排列，最低字节在前: enjoy calc 'crc32[0xDEADBEEF, 4]'
+  mulwide[a, b] 计算两个 64 位数的完整 128 位乘积，并按高低 64 位给出
+  interleave[x, y] 按位交错得到 Morton (Z-order) 码，x 占偶数位，deinterleave_x deinterleave_y 是其逆变换
+  tobcd[v] 和 frombcd[v] 在十进制数和压缩 BCD 码之间转换；gray[v] 和 ungray[v] 在二进制数和格雷码之间转换
+  位查询函数 popcount parity clz ctz 按 64 位计算（负数按补码），log2 向下取整
+
+位段
+  紧跟在数值后面的 [hi:lo] 提取第 hi 到 lo 位: enjoy calc '0xDEADBEEF[27:24]'
+  位的范围与计算的位宽相同: 默认为 0..=127，--wrap 或 --saturate 时为寄存器的位宽 (默认 0..=63，--width 32 时 0..=31)
+
+多个表达式和变量
+  用 ; 分隔多条语句，name = expr 给变量赋值，输出最后一条语句的结果:
+    enjoy calc 'base = 0x40000000 ; base + 0x2000'
+  ans 为前一个表达式的结果；取模运算: enjoy --mod 0x10000 calc 0xFFF0 + 0x40";
+
+/// 子命令；不给出子命令时沿用顶层的 --calc、--gerrit 等参数
+#[derive(clap::Subcommand, Debug)]
+enum Commands {
+    /// 计算表达式 (enjoy calc 0x1000 + 4 x 1024)，写法见 enjoy calc --help
+    Calc(CalcArgs),
+    /// 进制转换，给出每个数值的各种表示 (enjoy conv 0x1F 0b1010)
+    Conv(ConvArgs),
+    /// 把当前分支推送到 Gerrit 评审 (git push origin HEAD:refs/for/develop)，--remote、--branch 选择远程仓库和目标分支，--reviewer、--cc 指定评审人和抄送，--topic 指定主题，--hashtag 添加标签，--wip、--private 等设置改动的状态，-o 附加其他推送选项，--dry-run 只输出命令，推送前需要确认 (-y 跳过)；默认值可以写在 git config 的 enjoy.gerrit.* 中；enjoy gerrit list 列出自己打开的改动
+    Gerrit(Box<GerritArgs>),
+    /// 向标准输出写出 shell 补全脚本 (source <(enjoy completions bash))
+    Completions(CompletionsArgs),
+    /// 配置文件相关的命令 (enjoy config path)
+    Config(ConfigArgs),
+}
+
+#[derive(clap::Args, Debug)]
+// 全局参数引用的参数组在每个子命令中也要存在
+#[command(group(clap::ArgGroup::new("expr").args(["expression", "tokens_from_json"])))]
+#[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+#[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+#[command(after_long_help = EXPR_HELP)]
+struct CalcArgs {
+    /// 要计算的表达式，可以分成多个参数，也可以放进一个参数: enjoy calc '(len + 7) / 8 * 8'；
+    /// 写法见下文 (enjoy calc --help)
+    #[arg(value_name = "EXPR", required = true, num_args = 1..)]
+    expression: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+// 全局参数引用的参数组在每个子命令中也要存在
+#[command(group(clap::ArgGroup::new("expr").args(["values", "tokens_from_json"])))]
+#[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+#[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+struct ConvArgs {
+    /// 要转换的数值，可以是任何数字字面量（0x1F、0b1010、0FFh、36#Z1G、4k、'A' 等），不能是表达式；每个数值单独输出
+    #[arg(value_name = "VALUE", required = true, num_args = 1..)]
+    values: Vec<String>,
+
+    /// 只输出一种进制，便于在脚本中使用 (--to hex)，不带 0x 等前缀
+    #[arg(long, value_name = "BASE", value_enum, conflicts_with_all = ["quiet", "json", "shell", "emit"])]
+    to: Option<Base>,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(clap::ArgGroup::new("expr")))]
+#[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+#[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+#[command(args_conflicts_with_subcommands = true)]
+struct GerritArgs {
+    #[command(subcommand)]
+    action: Option<GerritAction>,
+
+    /// 使用配置文件中 [gerrit.profiles.<NAME>] 定义的配置，命令行参数逐项优先；
+    /// 没有给出时使用 [gerrit] 的 default_profile
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// 推送到的远程仓库 (--remote gerrit)，推送前用 git remote get-url 确认它存在；
+    /// 默认为 git config 的 enjoy.gerrit.remote，没有设置时为 origin
+    #[arg(long, value_name = "NAME", value_parser = gerrit::check_remote)]
+    remote: Option<String>,
+
+    /// 评审的目标分支，推送到 refs/for/<NAME> (--branch master)，不能含有空白和 %；
+    /// 默认为 git config 的 enjoy.gerrit.branch，其次为当前分支的上游分支，都没有时为 develop
+    #[arg(long, value_name = "NAME", value_parser = gerrit::check_branch)]
+    branch: Option<String>,
+
+    /// 不由 --branch 生成，原样推送到 HEAD:<SPEC> (--refspec refs/for/refs/heads/lts/5.10)，
+    /// --reviewer 等推送选项仍然接在它之后
+    #[arg(long, value_name = "SPEC", value_parser = gerrit::check_refspec, conflicts_with = "branch")]
+    refspec: Option<String>,
+
+    /// 评审人，可以重复给出 (--reviewer alice --reviewer bob)；
+    /// 不给出时使用 git config 的 enjoy.gerrit.reviewers (逗号分隔)，也没有时不指定评审人
+    #[arg(long = "reviewer", value_name = "USER", value_parser = gerrit::check_reviewer)]
+    reviewers: Vec<String>,
+
+    /// 抄送，可以重复给出 (--cc lead)；同时是评审人时只作为评审人
+    #[arg(long, value_name = "USER", value_parser = gerrit::check_cc)]
+    cc: Vec<String>,
+
+    /// 把改动归入 Gerrit 的主题 (--topic uart-fix)，不能含有空白和 ,；
+    /// git config 的 enjoy.gerrit.topicPrefix 会加在它之前
+    #[arg(long, value_name = "NAME", value_parser = gerrit::check_topic)]
+    topic: Option<String>,
+
+    /// 标签，可以重复给出 (--hashtag bugfix --hashtag uart)，不能含有空白、, 和 %
+    #[arg(long = "hashtag", value_name = "TAG", value_parser = gerrit::check_hashtag)]
+    hashtags: Vec<String>,
+
+    /// 标记为进行中 (wip)：CI 照常运行，但不通知评审人，可以与 --reviewer 一起使用
+    #[arg(long, conflicts_with = "ready")]
+    wip: bool,
+
+    /// 取消进行中的标记 (ready)，通知评审人
+    #[arg(long)]
+    ready: bool,
+
+    /// 标记为私有 (private)，只有自己和评审人可以看到
+    #[arg(long, conflicts_with = "remove_private")]
+    private: bool,
+
+    /// 取消私有的标记 (remove-private)
+    #[arg(long)]
+    remove_private: bool,
+
+    /// 原样附加的推送选项，可以重复给出 (-o notify=NONE -o l=Verified+1)，
+    /// 按给出的顺序接在其他选项之后；message=(m=) 的值会被编码，可以含有空白
+    #[arg(short = 'o', long = "push-option", value_name = "KEY=VALUE", value_parser = gerrit::check_push_option)]
+    push_options: Vec<String>,
+
+    /// 只输出要执行的 git 命令，不执行任何命令
+    #[arg(long, conflicts_with = "server_dry_run")]
+    dry_run: bool,
+
+    /// 执行 git push --dry-run：连接服务器检查推送，但不真正推送
+    #[arg(long)]
+    server_dry_run: bool,
+
+    /// 不询问，直接推送，用于脚本；标准输入不是终端时必须给出
+    #[arg(short, long)]
+    yes: bool,
+
+    /// HEAD 没有 Change-Id 时不询问，直接从 Gerrit 下载并安装 commit-msg 钩子
+    #[arg(long)]
+    install_hook: bool,
+
+    /// HEAD 没有 Change-Id 时仍然推送
+    #[arg(long)]
+    force: bool,
+
+    /// 不检查提交信息（标题长度、第二行为空、git config 的 enjoy.gerrit.subjectPattern）
+    #[arg(long)]
+    no_verify: bool,
+
+    /// 输出合并命令行、git config 和默认值之后的设置及其来源，不推送
+    #[arg(long)]
+    show_config: bool,
+}
+
+impl GerritArgs {
+    /// 命令行的值优先，其余来自合并好的设置
+    fn push_options(&self, settings: gerrit::Settings) -> gerrit::PushOptions {
+        let reviewers = settings.reviewers.value;
+        let mut cc: Vec<String> = Vec::new();
+        for user in &self.cc {
+            if reviewers.contains(user) {
+                eprintln!(
+                    "{}",
+                    tr!(
+                        "注意: {} 已经是评审人，不再抄送",
+                        "note: {} is already a reviewer, not adding as cc",
+                        user
+                    )
+                );
+            } else if !cc.contains(user) {
+                cc.push(user.clone());
+            }
+        }
+        let prefix = settings.topic_prefix.value;
+        gerrit::PushOptions {
+            remote: settings.remote.value,
+            branch: settings.branch.value,
+            target: self.refspec.clone(),
+            reviewers,
+            cc,
+            topic: self
+                .topic
+                .as_ref()
+                .map(|topic| format!("{}{}", prefix, topic)),
+            hashtags: self.hashtags.clone(),
+            wip: self.wip,
+            ready: self.ready,
+            private: self.private,
+            remove_private: self.remove_private,
+            extra_options: self.push_options.clone(),
+            dry_run: self.dry_run,
+            server_dry_run: self.server_dry_run,
+            yes: self.yes,
+            install_hook: self.install_hook,
+            force: self.force,
+            no_verify: self.no_verify,
+            subject_pattern: settings.subject_pattern.value,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(clap::ArgGroup::new("expr")))]
+#[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+#[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+struct CompletionsArgs {
+    /// 目标 shell（参数名不能与全局的 --shell 相同）
+    #[arg(value_enum, value_name = "SHELL")]
+    target: clap_complete::Shell,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(group(clap::ArgGroup::new("expr")))]
+#[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+#[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+struct ConfigArgs {
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigAction {
+    /// 输出配置文件的位置，文件不存在时也会输出
+    #[command(group(clap::ArgGroup::new("expr")))]
+    #[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+    #[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+    Path,
+}
+
+/// --reduce 的聚合方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Reduce {
+    Sum,
+    Min,
+    Max,
+    /// 平均值，输出整数商，另外给出余数
+    Avg,
+    Count,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum GerritAction {
+    /// 列出自己在 Gerrit 上打开的改动：编号、分支、状态、评审结果和标题
+    #[command(group(clap::ArgGroup::new("expr")))]
+    #[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+    #[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+    List(GerritListArgs),
+    /// 列出配置文件中定义的 gerrit 配置及其设置
+    #[command(group(clap::ArgGroup::new("expr")))]
+    #[command(group(clap::ArgGroup::new("register").args(["wrap", "saturate"])))]
+    #[command(group(clap::ArgGroup::new("layout").args(["fields", "decode"])))]
+    Profiles,
+}
+
+#[derive(clap::Args, Debug)]
+struct GerritListArgs {
+    /// 使用配置文件中定义的配置中的远程仓库
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// 从哪个远程仓库的地址得到 Gerrit 服务器，默认与推送时相同
+    #[arg(long, value_name = "NAME", value_parser = gerrit::check_remote)]
+    remote: Option<String>,
+}
+
+/// 各个子命令的执行结果，Err 为进程的退出状态
+pub(crate) type RunResult = Result<(), i32>;
+
+impl Args {
+    /// 标准输出只有供其他程序使用的结果 (--quiet、--json、--shell、--emit)，不输出表达式标题等说明
+    fn machine_output(&self) -> bool {
+        self.quiet.is_some() || self.json || self.shell || self.emit.is_some()
+    }
+}
+
+impl EvalOptions {
+    /// 整数求值选项，来自命令行参数
+    fn from_args(args: &Args) -> Self {
+        EvalOptions {
+            wrap: args.wrap,
+            unsigned: args.unsigned,
+            saturate: args.saturate,
+            width: args.width,
+            modulus: args.modulus,
+        }
+    }
+}
+
+impl Style {
+    /// 标准输出必须是终端，且没有使用 --no-color 或设置 NO_COLOR 环境变量，才输出 ANSI 颜色
+    fn from_args(args: &Args) -> Self {
+        Style {
+            color: !args.no_color
+                && std::env::var_os("NO_COLOR").is_none()
+                && std::io::stdout().is_terminal(),
+            zebra: args.zebra,
+            group_bits: args.group_bits,
+            separators: args.sep,
+        }
+    }
+}
+
+/// 解析选项中的数字字面量，错误信息交给 clap 输出
+fn parse_number_arg(s: &str) -> Result<i128, String> {
+    parse_number(s).map_err(|e| e.to_string())
+}
+
+/// 解析 --mod 的模数，按 u128 计算乘积，模数不能超过 u64::MAX
+fn parse_modulus(s: &str) -> Result<i128, String> {
+    let modulus = parse_number_arg(s)?;
+    if (1..=u64::MAX as i128).contains(&modulus) {
+        Ok(modulus)
+    } else {
+        Err(tr!(
+            "模数必须在 1..=0x{:X} 范围内: {}",
+            "modulus must be in 1..=0x{:X}: {}",
+            u64::MAX,
+            modulus
+        ))
+    }
+}
+
+/// 解析 --width 的寄存器位宽，只能是 8/16/32/64
+pub(crate) fn parse_register_bits(s: &str) -> Result<u32, String> {
+    match s.trim().parse() {
+        Ok(bits @ (8 | 16 | 32 | 64)) => Ok(bits),
+        _ => Err(tr!(
+            "位宽只能是 8、16、32 或 64: {}",
+            "width must be 8, 16, 32 or 64: {}",
+            s
+        )),
+    }
+}
+
+/// 解析 --group-bits，只能是 4、8 或 16
+pub(crate) fn parse_group_bits(s: &str) -> Result<usize, String> {
+    match s.trim().parse() {
+        Ok(bits @ (4 | 8 | 16)) => Ok(bits),
+        _ => Err(tr!(
+            "分组位数只能是 4、8 或 16: {}",
+            "group size must be 4, 8 or 16 bits: {}",
+            s
+        )),
+    }
+}
+
+/// 解析 --radix，只能是 2 到 36
+fn parse_radix(s: &str) -> Result<u32, String> {
+    match s.trim().parse() {
+        Ok(radix @ 2..=36) => Ok(radix),
+        _ => Err(tr!(
+            "进制只能是 2 到 36: {}",
+            "radix must be between 2 and 36: {}",
+            s
+        )),
+    }
+}
+
+/// --json 模式下的浮点数结果
+#[derive(Serialize)]
+struct JsonFloat {
+    decimal: f64,
+    ieee754: String,
+}
+
+/// --json 模式下的错误信息：message 不含位置和回显的表达式，便于其他程序读取；
+/// token 为出错元素的下标（从 0 开始），line 为 --stdin 或 --file 中出错的行号
+#[derive(Serialize)]
+struct JsonError {
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+}
+
+impl JsonError {
+    fn new(err: &EnjoyError, line: Option<usize>) -> Self {
+        JsonError {
+            message: err.message(),
+            token: err.token(),
+            line,
+        }
+    }
+}
+
+/// --shell 变量名的前缀，多个表达式时在末尾的下划线之前加上序号 (RESULT_ -> RESULT1_)
+fn shell_prefix(prefix: &str, index: Option<usize>) -> String {
+    match index {
+        Some(index) => match prefix.strip_suffix('_') {
+            Some(stem) => format!("{}{}_", stem, index),
+            None => format!("{}{}", prefix, index),
+        },
+        None => prefix.to_string(),
+    }
+}
+
+/// 按 POSIX sh 的规则给值加引号，只含安全字符时原样输出
+fn shell_quote(value: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_.+:,/@%".contains(c);
+    if !value.is_empty() && value.chars().all(safe) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// 每行输出一个 KEY=value，可以直接在 shell 中 eval
+fn print_shell(prefix: &str, values: &[(&str, String)]) {
+    for (key, value) in values {
+        println!("{}{}={}", prefix, key, shell_quote(value));
+    }
+}
+
+/// --emit 生成的代码片段
+#[derive(Debug, Clone)]
+enum Emit {
+    /// C 语言的 #define NAME 0x...u
+    C(String),
+    /// Rust 的 pub const NAME: u32 = 0x...;
+    Rust(String),
+    /// 字节数组，依次输出 C 和 Rust 两种写法
+    Bytes,
+}
+
+/// C 和 Rust 的关键字，不能用作 --emit 的名称
+const RESERVED_NAMES: [&str; 62] = [
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+    "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+    "restrict", "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef",
+    "union", "unsigned", "void", "volatile", "while", "as", "async", "await", "crate", "dyn",
+    "false", "fn", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "self", "Self", "super", "trait", "true", "type", "unsafe", "use", "where", "gen", "_",
+];
+
+/// 解析 --emit c:NAME、rust:NAME 或 bytes，NAME 必须是合法的标识符
+fn parse_emit(s: &str) -> Result<Emit, String> {
+    if s.eq_ignore_ascii_case("bytes") {
+        return Ok(Emit::Bytes);
+    }
+    let (language, name) = s.split_once(':').ok_or_else(|| {
+        tr!(
+            "应写作 c:NAME、rust:NAME 或 bytes: {}",
+            "expected c:NAME, rust:NAME or bytes: {}",
+            s
+        )
+    })?;
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(tr!(
+            "{} 不是合法的标识符，只能包含字母、数字和下划线，且不能以数字开头",
+            "{} is not a valid identifier: use only letters, digits and underscores, and do not start with a digit",
+            name
+        ));
+    }
+    if RESERVED_NAMES.contains(&name) {
+        return Err(tr!(
+            "{} 是关键字，不能用作名称",
+            "{} is a keyword and cannot be used as a name",
+            name
+        ));
+    }
+    match language.to_ascii_lowercase().as_str() {
+        "c" => Ok(Emit::C(name.to_string())),
+        "rust" | "rs" => Ok(Emit::Rust(name.to_string())),
+        _ => Err(tr!(
+            "不支持的语言 {}，可选 c 或 rust",
+            "unsupported language {}, choose c or rust",
+            language
+        )),
+    }
+}
+
+/// --emit 使用的整数位宽：给出 --width 时使用该位宽，否则取能放下结果的最小的 8/16/32/64/128 位
+/// 负数按有符号类型计算
+fn emit_bits(value: i128, width: Option<u32>) -> Result<u32, String> {
+    let fits = |bits: u32| {
+        if value < 0 {
+            bits == 128 || value >= -(1i128 << (bits - 1))
+        } else {
+            bits == 128 || value < 1i128 << bits
+        }
+    };
+    match width {
+        Some(bits) if fits(bits) => Ok(bits),
+        Some(bits) => Err(tr!(
+            "结果 {} 超出 {} 位",
+            "result {} does not fit in {} bits",
+            value,
+            bits
+        )),
+        None => Ok([8, 16, 32, 64, 128]
+            .into_iter()
+            .find(|&bits| fits(bits))
+            .unwrap()),
+    }
+}
+
+/// 生成 --emit 的代码片段：非负数按无符号类型输出补零到位宽的十六进制，负数按有符号类型输出十进制
+/// C 中 32 位以内使用 u 后缀，64 位使用 ull 后缀，负数用括号括起来，最小值写作 (-MAX - 1) 避免溢出
+/// bytes 依次输出 C 的数组初始化和 Rust 的数组字面量
+fn emit_snippet(value: i128, args: &Args) -> Result<String, String> {
+    let bits = emit_bits(value, args.width)?;
+    let digits = bits as usize / 4;
+    match args.emit.as_ref().expect("只在 --emit 时调用") {
+        Emit::C(_) if bits > 64 => Err(tr!(
+            "C 没有 128 位的整数常量，结果 {} 超出 64 位",
+            "C has no 128-bit integer constants, result {} does not fit in 64 bits",
+            value
+        )),
+        Emit::C(name) if value >= 0 => {
+            let suffix = if bits > 32 { "ull" } else { "u" };
+            Ok(format!("#define {} 0x{:0digits$X}{}", name, value, suffix))
+        }
+        Emit::C(name) => {
+            let suffix = if bits > 32 { "LL" } else { "" };
+            if value == -(1i128 << (bits.max(32) - 1)) {
+                Ok(format!("#define {} ({}{} - 1)", name, value + 1, suffix))
+            } else {
+                Ok(format!("#define {} ({}{})", name, value, suffix))
+            }
+        }
+        Emit::Rust(name) if value >= 0 => Ok(format!(
+            "pub const {}: u{} = 0x{};",
+            name,
+            bits,
+            group_hex_digits(&format!("{:0digits$X}", value))
+        )),
+        Emit::Rust(name) => Ok(format!("pub const {}: i{} = {};", name, bits, value)),
+        Emit::Bytes => {
+            // 没有指定字节序或只写了 --endian 时按小端序
+            let order = args.endian.flatten().unwrap_or(ByteOrder::Le);
+            let bytes = emit_byte_sequence(value, args.width, order)?;
+            Ok(format!(
+                "{}\n{}",
+                c_byte_array(&bytes),
+                rust_byte_array(&bytes)
+            ))
+        }
+    }
+}
+
+/// 解析 --shell-prefix，必须能作为 shell 变量名的开头
+fn parse_shell_prefix(s: &str) -> Result<String, String> {
+    let mut chars = s.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(s.to_string())
+    } else {
+        Err(tr!(
+            "变量名前缀只能包含字母、数字和下划线，且不能以数字开头: {}",
+            "variable name prefix must contain only letters, digits and underscores, and not start with a digit: {}",
+            s
+        ))
+    }
+}
+
+/// 向标准错误输出一条错误信息
+fn print_error(message: impl std::fmt::Display) {
+    eprintln!("{}", tr!("错误: {}", "error: {}", message));
+}
+
+/// 一行输出一个 JSON 对象，多个表达式时每个表达式一行
+fn print_json(value: &impl Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string(value).expect("序列化 JSON 输出失败")
+    );
+}
+
+/// 剪贴板后端：把文本交给一个剪贴板程序，测试时可以替换为固定的结果
+trait CopyBackend {
+    /// 程序名，用于确认信息和错误信息
+    fn name(&self) -> &str;
+    /// 写入剪贴板，程序不存在或不能写入时返回 false
+    fn copy(&self, text: &str) -> bool;
+}
+
+/// 执行剪贴板命令，通过标准输入传入文本
+struct CommandBackend(&'static [&'static str]);
+
+impl CopyBackend for CommandBackend {
+    fn name(&self) -> &str {
+        self.0[0]
+    }
+
+    fn copy(&self, text: &str) -> bool {
+        use std::io::Write;
+        let Ok(mut child) = Command::new(self.0[0])
+            .args(&self.0[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return false;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(text.as_bytes()).is_ok());
+        // 命令存在但无法连接显示服务时以非零状态退出
+        child.wait().is_ok_and(|status| status.success()) && written
+    }
+}
+
+/// 依次尝试的剪贴板命令：macOS、Wayland、X11、Windows (含 WSL)
+const CLIPBOARD_BACKENDS: [CommandBackend; 5] = [
+    CommandBackend(&["pbcopy"]),
+    CommandBackend(&["wl-copy"]),
+    CommandBackend(&["xclip", "-selection", "clipboard"]),
+    CommandBackend(&["xsel", "--clipboard", "--input"]),
+    CommandBackend(&["clip.exe"]),
+];
+
+/// 依次尝试各个后端把文本写入剪贴板，成功时返回使用的程序名；
+/// 都不可用时（如通过 SSH 登录）返回错误，列出所有尝试过的程序
+fn copy_to_clipboard(text: &str, backends: &[&dyn CopyBackend]) -> Result<String, String> {
+    match backends.iter().find(|backend| backend.copy(text)) {
+        Some(backend) => Ok(backend.name().to_string()),
+        None => Err(tr!(
+            "没有可用的剪贴板，需要以下命令之一: {}",
+            "no clipboard available, one of these commands is needed: {}",
+            backends
+                .iter()
+                .map(|backend| backend.name())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// --copy：计算成功后把结果复制到剪贴板，确认信息和错误都输出到标准错误，不影响标准输出
+fn copy_result(text: &str) {
+    let backends = CLIPBOARD_BACKENDS
+        .each_ref()
+        .map(|backend| backend as &dyn CopyBackend);
+    match copy_to_clipboard(text, &backends) {
+        Ok(command) => eprintln!(
+            "{}",
+            tr!(
+                "已复制到剪贴板 ({}): {}",
+                "copied to clipboard ({}): {}",
+                command,
+                text
+            )
+        ),
+        Err(err) => print_error(tr!(
+            "无法复制到剪贴板: {}",
+            "cannot copy to clipboard: {}",
+            err
+        )),
+    }
+}
+
+/// 解析 --float-bits，只能是 32 或 64
+fn parse_float_bits(s: &str) -> Result<u32, String> {
+    match s.trim().parse() {
+        Ok(bits @ (32 | 64)) => Ok(bits),
+        _ => Err(tr!(
+            "浮点数位宽只能是 32 或 64: {}",
+            "float width must be 32 or 64: {}",
+            s
+        )),
+    }
+}
+
+/// --emit bytes 的字节：负数取补码，字节数由 --width 决定，否则取能放下结果的最少字节数（0 为一个字节）
+fn emit_byte_sequence(
+    value: i128,
+    width: Option<u32>,
+    order: ByteOrder,
+) -> Result<Vec<u8>, String> {
+    let nbytes = match width {
+        Some(bits) => emit_bits(value, Some(bits))? as usize / 8,
+        None => minimal_byte_count(value),
+    };
+    let mut bytes = le_byte_sequence(value, nbytes);
+    if order == ByteOrder::Be {
+        bytes.reverse();
+    }
+    Ok(bytes)
+}
+
+/// 逗号分隔的字节列表，如 0x78, 0x56, 0x34, 0x12
+fn byte_list(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("0x{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// C 的数组初始化: {0x78, 0x56, 0x34, 0x12}
+fn c_byte_array(bytes: &[u8]) -> String {
+    format!("{{{}}}", byte_list(bytes))
+}
+
+/// Rust 的数组字面量: [0x78, 0x56, 0x34, 0x12]
+fn rust_byte_array(bytes: &[u8]) -> String {
+    format!("[{}]", byte_list(bytes))
+}
+
+/// 一个表达式的计算结果，用于决定进程的退出状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Success,
+    /// 计算出错或断言失败，带有进程的退出状态
+    Failure(i32),
+    /// 最后一条语句的顶层是比较，且结果为假
+    False,
+}
+
+/// 最后一条语句的第一个元素的位置，结果就是这条语句的值
+fn last_statement(tokens: &[ExprToken]) -> usize {
+    top_level_positions(tokens, 0, tokens.len(), |t| {
+        matches!(t, ExprToken::Semicolon)
+    })
+    .last()
+    .map_or(0, |semicolon| semicolon + 1)
+}
+
+/// 最后一条语句的顶层（不在括号中）是否有比较操作符，此时结果就是比较的真假
+fn ends_with_comparison(tokens: &[ExprToken]) -> bool {
+    let start = last_statement(tokens);
+    !top_level_positions(
+        tokens,
+        start,
+        tokens.len(),
+        |t| matches!(t, ExprToken::Operator(op) if op.is_comparison()),
+    )
+    .is_empty()
+}
+
+/// 交互模式中表达式是否还没有写完：有没有闭合的括号，或以操作符、=、逗号或 : 结尾
+/// 多出的右括号之类的错误不算没写完，交给求值时报告
+fn needs_continuation(tokens: &[ExprToken]) -> bool {
+    let mut depth = 0i32;
+    for token in tokens {
+        if token.open_bracket().is_some() {
+            depth += 1;
+        } else if token.close_bracket().is_some() {
+            depth -= 1;
+            if depth < 0 {
+                return false;
+            }
+        }
+    }
+    depth > 0
+        || matches!(
+            tokens.last(),
+            Some(ExprToken::Operator(_) | ExprToken::Assign | ExprToken::Comma | ExprToken::Colon)
+        )
+}
+
+/// 最后一条语句整个是一次函数调用（如 parity[x]）时返回函数名
+fn top_level_call(tokens: &[ExprToken]) -> Option<&str> {
+    let start = last_statement(tokens);
+    let ExprToken::Ident(name) = tokens.get(start)? else {
+        return None;
+    };
+    tokens.get(start + 1)?.open_bracket()?;
+    // 参数列表的右括号必须是最后一个元素
+    let mut depth = 0;
+    for (k, token) in tokens.iter().enumerate().skip(start + 1) {
+        if token.open_bracket().is_some() {
+            depth += 1;
+        } else if token.close_bracket().is_some() {
+            depth -= 1;
+            if depth == 0 {
+                return (k + 1 == tokens.len()).then_some(name.as_str());
+            }
+        }
+    }
+    None
+}
+
+/// 拆分表达式元素，按 --in-signed 扩展字面量，并检查元素个数和嵌套深度
+/// 出错时也返回已经拆分出的原文，用于在错误提示中指出出错的位置
+fn parse_tokens(
+    expression: &[String],
+    args: &Args,
+) -> (Result<Vec<ExprToken>, EnjoyError>, Vec<String>) {
+    let (mut tokens, sources) = match tokenize_args(expression) {
+        Ok(tokenized) => tokenized,
+        Err(err) => return (Err(EnjoyError::parse(err)), Vec::new()),
+    };
+    let checked = sign_extend_literals(&mut tokens, &sources, args.in_signed)
+        .and_then(|()| check_limits(&tokens, args.max_tokens, args.max_depth));
+    (checked.map(|()| tokens), sources)
+}
+
+/// 计算表达式，-ddd 时给出耗时
+fn timed<T>(evaluate: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let result = evaluate();
+    debug!(
+        debug::TIMING,
+        "求值耗时 {:?}",
+        "evaluation took {:?}",
+        started.elapsed()
+    );
+    result
+}
+
+/// --check：检查表达式的结构、限制、变量和量纲，不进行计算，因此不会有除零或溢出错误
+fn check_expression(
+    tokens: Result<Vec<ExprToken>, EnjoyError>,
+    args: &Args,
+    context: &mut EvalContext,
+) -> Result<Dimensioned<Unevaluated>, EnjoyError> {
+    let tokens = tokens?;
+    check_limits(&tokens, args.max_tokens, args.max_depth)?;
+    Ok(evaluate_statements::<Dimensioned<Unevaluated>>(
+        &tokens,
+        &EvalOptions::default(),
+        context,
+        &mut Vec::new(),
+    )?)
+}
+
+/// 是否按浮点数计算：--float，或出现小数（或引用了浮点数结果）时自动启用；
+/// --wrap、--saturate、--unsigned 和 --mod 只对整数有意义，此时仍按整数报错
+fn float_mode(tokens: &[ExprToken], args: &Args, context: &EvalContext) -> bool {
+    let uses_float = |token: &ExprToken| match token {
+        ExprToken::Float(_) => true,
+        ExprToken::Ident(name) => {
+            let saved = if name == ANS {
+                context.ans
+            } else {
+                context.variables.get(name).copied()
+            };
+            matches!(saved, Some(Saved::Float(_)))
+        }
+        _ => false,
+    };
+    args.float
+        || (!args.wrap
+            && !args.saturate
+            && !args.unsigned
+            && args.modulus.is_none()
+            && tokens.iter().any(uses_float))
+}
+
+/// 计算并输出一个表达式，成功时把结果记为 context 中的 ans
+/// index 为多个表达式时的序号（从 1 开始），用于区分 --shell 输出的变量名
+fn run_expression(
+    tokens: Result<(Vec<ExprToken>, Vec<String>), Message>,
+    args: &Args,
+    context: &mut EvalContext,
+    index: Option<usize>,
+) -> Outcome {
+    // 拆分元素时的错误都是解析错误；原文单独保存，用于在错误提示中指出出错的位置
+    let (tokens, sources) = match tokens {
+        Ok((mut tokens, sources)) => {
+            let extended = sign_extend_literals(&mut tokens, &sources, args.in_signed);
+            (extended.map(|()| tokens), sources)
+        }
+        Err(err) => (Err(EnjoyError::parse(err)), Vec::new()),
+    };
+    if let Ok(tokens) = &tokens {
+        for (i, (token, source)) in tokens.iter().zip(&sources).enumerate() {
+            debug!(
+                debug::TOKENS,
+                "元素 {}: {:?} ('{}')",
+                "token {}: {:?} ('{}')",
+                i + 1,
+                token,
+                source
+            );
+        }
+    }
+    let shell_prefix = shell_prefix(&args.shell_prefix, index);
+    // --stdin 和 --file 时 index 为行号，错误信息带上行号
+    let located = |err: &EnjoyError| {
+        let err = err.render(&sources);
+        match (&args.file, index) {
+            (Some(path), Some(line)) => format!("{}:{}: {}", path.display(), line, err),
+            (None, Some(line)) if args.stdin => tr!("第 {} 行: {}", "line {}: {}", line, err),
+            _ => err,
+        }
+    };
+    let json_error = |err: &EnjoyError| {
+        let line = index.filter(|_| args.file.is_some() || args.stdin);
+        JsonError::new(err, line)
+    };
+    // 顶层是比较时用退出状态表示真假，便于在 shell 条件中使用
+    let comparison = tokens
+        .as_ref()
+        .is_ok_and(|tokens| ends_with_comparison(tokens));
+    // 整个表达式是一次函数调用时，按该函数补充输出（如 parity 的奇偶性、rbit 的位宽）
+    let top_call = tokens
+        .as_ref()
+        .ok()
+        .and_then(|tokens| top_level_call(tokens))
+        .and_then(lookup_function);
+    let outcome = |is_false: bool| {
+        if comparison && is_false {
+            Outcome::False
+        } else {
+            Outcome::Success
+        }
+    };
+
+    if args.check {
+        let result = timed(|| check_expression(tokens, args, context));
+        return match result {
+            Ok(result) => {
+                context.ans = Some(result.save());
+                Outcome::Success
+            }
+            Err(err) => {
+                print_error(located(&err));
+                Outcome::Failure(err.exit_code())
+            }
+        };
+    }
+
+    let float = tokens
+        .as_ref()
+        .map_or(args.float, |tokens| float_mode(tokens, args, context));
+
+    if float {
+        let result = timed(|| {
+            let tokens = tokens?;
+            check_limits(&tokens, args.max_tokens, args.max_depth)?;
+            Ok(evaluate_statements::<Dimensioned<f64>>(
+                &tokens,
+                &EvalOptions::default(),
+                context,
+                &mut Vec::new(),
+            )?)
+        });
+        if let Ok(result) = &result {
+            context.ans = Some(result.save());
+        }
+        let result = result.map(|result| result.value);
+        if let Ok(result) = &result {
+            // 浮点数结果只按十进制复制
+            if args.copy.is_some() {
+                copy_result(&result.to_string());
+            }
+        }
+
+        match (result, args.assert) {
+            (Ok(result), Some(expected)) if result == expected as f64 => Outcome::Success,
+            (Ok(result), Some(expected)) => {
+                eprintln!(
+                    "{}",
+                    tr!(
+                        "断言失败: 期望 {}, 实际 {}",
+                        "assertion failed: expected {}, got {}",
+                        expected,
+                        result
+                    )
+                );
+                Outcome::Failure(1)
+            }
+            // 浮点数结果只按十进制输出
+            (Ok(result), None) if args.quiet.is_some() => {
+                println!("{}", result);
+                outcome(result == 0.0)
+            }
+            (Ok(_), None) if args.emit.is_some() => {
+                print_error(tr!(
+                    "--emit 只支持整数结果",
+                    "--emit only supports integer results"
+                ));
+                Outcome::Failure(1)
+            }
+            (Ok(result), None) if args.shell => {
+                print_shell(
+                    &shell_prefix,
+                    &[
+                        ("DEC", result.to_string()),
+                        ("IEEE754", format!("0x{:016X}", result.to_bits())),
+                    ],
+                );
+                outcome(result == 0.0)
+            }
+            (Ok(result), None) if args.json => {
+                print_json(&JsonFloat {
+                    decimal: result,
+                    ieee754: format!("0x{:016X}", result.to_bits()),
+                });
+                outcome(result == 0.0)
+            }
+            (Ok(result), None) => {
+                print_float_info(result);
+                outcome(result == 0.0)
+            }
+            (Err(err), None) if args.json => {
+                print_json(&json_error(&err));
+                Outcome::Failure(err.exit_code())
+            }
+            (Err(err), _) => {
+                print_error(located(&err));
+                Outcome::Failure(err.exit_code())
+            }
+        }
+    } else {
+        let options = EvalOptions::from_args(args);
+        let mut notes = Vec::new();
+        let result = timed(|| {
+            let tokens = tokens?;
+            check_limits(&tokens, args.max_tokens, args.max_depth)?;
+            Ok(evaluate_statements::<Dimensioned<i128>>(
+                &tokens, &options, context, &mut notes,
+            )?)
+        });
+        if let Ok(result) = &result {
+            context.ans = Some(result.save());
+        }
+        let is_duration = result
+            .as_ref()
+            .is_ok_and(|result| result.dimension == Dimension::Time);
+        let result = result.map(|result| result.value);
+        if let Ok(result) = &result {
+            let result = *result;
+            if let Some(base) = args.copy {
+                copy_result(
+                    &FormattedResult::new(result, &options, top_call, &notes).quiet(base, true),
+                );
+            }
+        }
+
+        // --assert 模式下只比较结果，不打印常规输出
+        if let Some(expected) = args.assert {
+            match result {
+                Ok(result) if result == expected => Outcome::Success,
+                Ok(result) => {
+                    eprintln!(
+                        "{}",
+                        tr!(
+                            "断言失败: 期望 {} (0x{:X}), 实际 {} (0x{:X})",
+                            "assertion failed: expected {} (0x{:X}), got {} (0x{:X})",
+                            expected,
+                            expected,
+                            result,
+                            result
+                        )
+                    );
+                    Outcome::Failure(1)
+                }
+                Err(err) => {
+                    print_error(located(&err));
+                    Outcome::Failure(err.exit_code())
+                }
+            }
+        } else {
+            match result {
+                Ok(result) if args.quiet.is_some() => {
+                    let formatted = FormattedResult::new(result, &options, top_call, &notes);
+                    if let Some(radix) = args.radix {
+                        println!("{}", to_radix(result, radix));
+                    } else if args.bits {
+                        // 只输出从高到低的位索引，如 31,10；结果为 0 时为空行
+                        let bits: Vec<String> = formatted
+                            .bits_set
+                            .iter()
+                            .rev()
+                            .map(usize::to_string)
+                            .collect();
+                        println!("{}", bits.join(","));
+                    } else {
+                        println!("{}", formatted.quiet(args.quiet.unwrap(), args.prefix));
+                    }
+                    outcome(result == 0)
+                }
+                Ok(result) if args.emit.is_some() => match emit_snippet(result, args) {
+                    Ok(snippet) => {
+                        println!("{}", snippet);
+                        outcome(result == 0)
+                    }
+                    Err(err) => {
+                        print_error(err);
+                        Outcome::Failure(1)
+                    }
+                },
+                Ok(result) if args.shell => {
+                    let formatted = FormattedResult::new(result, &options, top_call, &notes);
+                    print_shell(
+                        &shell_prefix,
+                        &[
+                            ("DEC", formatted.decimal.to_string()),
+                            ("HEX", formatted.hex.clone()),
+                            ("OCT", formatted.octal.clone()),
+                            ("BIN", format!("0b{}", formatted.binary)),
+                        ],
+                    );
+                    outcome(result == 0)
+                }
+                Ok(result) if args.json => {
+                    print_json(&FormattedResult::new(result, &options, top_call, &notes));
+                    outcome(result == 0)
+                }
+                Ok(result) => {
+                    print_result(
+                        result,
+                        &args.output,
+                        &options,
+                        Style::from_args(args),
+                        top_call,
+                        &notes,
+                    );
+                    if let Some(radix) = args.radix {
+                        println!(
+                            "{}",
+                            tr!("{} 进制: {}", "base {}: {}", radix, to_radix(result, radix))
+                        );
+                    }
+                    if is_duration {
+                        println!("{}", duration_line(result));
+                    }
+                    if args.human {
+                        println!("{}", human_size_line(result));
+                    }
+                    if let Some(bits) = args.float_bits {
+                        print_ieee754_fields(result as u64, bits);
+                    }
+                    if let Some(order) = args.endian {
+                        print_byte_order(result, args.width, order);
+                    }
+                    if args.ascii {
+                        print_ascii(result, args.width);
+                    }
+                    if args.time {
+                        print_timestamp(result);
+                    }
+                    if args.ipv4 {
+                        print_ipv4(result);
+                    }
+                    if args.bits {
+                        print_set_bits(
+                            &FormattedResult::new(result, &options, top_call, &notes).bits_set,
+                        );
+                    }
+                    if let Some(spec) = &args.fields {
+                        print_fields(result, spec, args.reserved, Style::from_args(args));
+                    }
+                    outcome(result == 0)
+                }
+                Err(err) if args.json => {
+                    print_json(&json_error(&err));
+                    Outcome::Failure(err.exit_code())
+                }
+                Err(err) => {
+                    print_error(located(&err));
+                    Outcome::Failure(err.exit_code())
+                }
+            }
+        }
+    }
+}
+
+/// --table 最多输出的行数
+const MAX_TABLE_ROWS: i128 = 1_000_000;
+
+/// 解析 --table 的范围 START..END，不含 END
+fn parse_range(s: &str) -> Result<(i128, i128), String> {
+    let (start, end) = s.split_once("..").ok_or_else(|| {
+        tr!(
+            "范围应写作 START..END: {}",
+            "range must be written as START..END: {}",
+            s
+        )
+    })?;
+    let (start, end) = (parse_number_arg(start)?, parse_number_arg(end)?);
+    if start >= end {
+        return Err(tr!(
+            "范围 {}..{} 为空，END 必须大于 START",
+            "range {}..{} is empty, END must be greater than START",
+            start,
+            end
+        ));
+    }
+    Ok((start, end))
+}
+
+/// 解析 --step，步长必须为正数
+fn parse_step(s: &str) -> Result<i128, String> {
+    match parse_number_arg(s)? {
+        step if step > 0 => Ok(step),
+        step => Err(tr!("步长必须为正数: {}", "step must be positive: {}", step)),
+    }
+}
+
+/// 对范围内的每个 n 计算表达式（没有表达式时即 n 本身），按对齐的列输出十进制、十六进制和二进制
+/// 有行出错时以第一个出错的行的退出状态退出
+fn run_table(
+    (start, end): (i128, i128),
+    step: i128,
+    expression: Option<&[String]>,
+    args: &Args,
+    context: &mut EvalContext,
+) -> RunResult {
+    let rows = (end - start + step - 1) / step;
+    if rows > MAX_TABLE_ROWS {
+        print_error(tr!(
+            "--table 共 {} 行，超出上限 {}，请缩小范围或增大步长",
+            "--table would print {} rows, more than the limit of {}; narrow the range or increase the step",
+            rows,
+            MAX_TABLE_ROWS
+        ));
+        return Err(1);
+    }
+    let tokens = match expression.map(|expression| parse_tokens(expression, args)) {
+        Some((Ok(tokens), sources)) => Some((tokens, sources)),
+        Some((Err(err), sources)) => {
+            print_error(err.render(&sources));
+            return Err(err.exit_code());
+        }
+        None => None,
+    };
+
+    let options = EvalOptions::from_args(args);
+    let results: Vec<(i128, Result<FormattedResult, EnjoyError>)> = (0..rows)
+        .map(|row| {
+            let n = start + row * step;
+            let result = match &tokens {
+                Some((tokens, _)) => {
+                    context.variables.insert("n".to_string(), Saved::Int(n));
+                    evaluate_statements::<Dimensioned<i128>>(
+                        tokens,
+                        &options,
+                        context,
+                        &mut Vec::new(),
+                    )
+                    .map(|result| result.value)
+                    .map_err(EnjoyError::from)
+                }
+                None => options
+                    .check_literal(n)
+                    .map_err(|message| EnjoyError::Eval {
+                        error: EvalError::Other(message),
+                        token: None,
+                    }),
+            };
+            (
+                n,
+                result.map(|value| FormattedResult::new(value, &options, None, &[])),
+            )
+        })
+        .collect();
+
+    // 十六进制和二进制补零到最长的值，便于逐列对照
+    let formatted = || {
+        results
+            .iter()
+            .filter_map(|(_, result)| result.as_ref().ok())
+    };
+    let hex_digits = formatted().map(|r| r.hex.len() - 2).max().unwrap_or(0);
+    let bin_digits = formatted()
+        .map(|r| r.binary.len())
+        .max()
+        .unwrap_or(0)
+        .div_ceil(args.group_bits)
+        * args.group_bits;
+    let cells: Vec<(String, Result<[String; 3], &EnjoyError>)> = results
+        .iter()
+        .map(|(n, result)| {
+            let cells = result.as_ref().map(|r| {
+                let binary = format!("{:0>width$}", r.binary, width = bin_digits);
+                [
+                    r.decimal.to_string(),
+                    format!("0x{:0>width$}", &r.hex[2..], width = hex_digits),
+                    split_into_groups(&binary, args.group_bits).join(" "),
+                ]
+            });
+            (n.to_string(), cells)
+        })
+        .collect();
+
+    let header = [
+        tr!("十进制", "Decimal"),
+        tr!("十六进制", "Hex"),
+        tr!("二进制", "Binary"),
+    ];
+    let width = |column: usize, title: &str| {
+        cells
+            .iter()
+            .filter_map(|(_, cells)| cells.as_ref().ok())
+            .map(|cells| cells[column].len())
+            .chain([display_width(title)])
+            .max()
+            .unwrap_or(0)
+    };
+    let n_width = cells.iter().map(|(n, _)| n.len()).max().unwrap_or(1);
+    let widths = [width(0, &header[0]), width(1, &header[1])];
+    let mut line = String::new();
+    if tokens.is_some() {
+        line += &format!("{}  ", pad_column("n", n_width));
+    }
+    line += &format!(
+        "{}  {}  {}",
+        pad_column(&header[0], widths[0]),
+        pad_column(&header[1], widths[1]),
+        header[2]
+    );
+    println!("{}", line);
+
+    let sources = tokens.as_ref().map_or(&[][..], |(_, sources)| sources);
+    let mut status = Ok(());
+    for (n, cells) in &cells {
+        let mut line = String::new();
+        if tokens.is_some() {
+            line += &format!("{}  ", pad_column(n, n_width));
+        }
+        match cells {
+            Ok([decimal, hex, binary]) => {
+                line += &format!(
+                    "{}  {}  {}",
+                    pad_column(decimal, widths[0]),
+                    pad_column(hex, widths[1]),
+                    binary
+                )
+            }
+            // 错误信息只取第一行，原文和出错位置在表格中没有意义
+            Err(err) => {
+                status = status.and(Err(err.exit_code()));
+                let err = err.render(sources);
+                line += &tr!(
+                    "错误: {}",
+                    "error: {}",
+                    err.lines().next().unwrap_or_default()
+                );
+            }
+        }
+        println!("{}", line.trim_end());
+    }
+    status
+}
+
+/// --stdin 和 --file：逐行计算表达式，跳过空行和 # 注释，各行共享变量和 ans
+/// keep_going 为假时遇到出错的行就停止；有行出错时以第一个出错的行的退出状态退出
+/// header 为真时在每个结果前面输出行号和原文，便于对照
+fn run_lines(
+    lines: impl Iterator<Item = std::io::Result<String>>,
+    args: &Args,
+    context: &mut EvalContext,
+    keep_going: bool,
+    header: bool,
+) -> RunResult {
+    let mut status = Ok(());
+    let mut first = true;
+    for (i, line) in lines.enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                print_error(tr!(
+                    "第 {} 行: 无法读取输入: {}",
+                    "line {}: cannot read input: {}",
+                    i + 1,
+                    err
+                ));
+                return Err(1);
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if header {
+            if !first {
+                println!();
+            }
+            println!("{}", tr!("第 {} 行: {}", "line {}: {}", i + 1, line));
+        }
+        first = false;
+        let tokens = tokenize_args(&[line.to_string()]);
+        if let Outcome::Failure(code) = run_expression(tokens, args, context, Some(i + 1)) {
+            status = status.and(Err(code));
+            if !keep_going {
+                break;
+            }
+        }
+    }
+    status
+}
+
+/// 交互模式的帮助信息
+const REPL_HELP: &str = "\
+输入表达式按回车计算，写法与 enjoy calc 相同 (见 enjoy calc --help)，如 base = 0x4000_0000; base + 0x10
+上一行的结果为 ans，赋值的变量在之后的各行中都可以使用；上下方向键翻阅历史记录，Tab 补全函数、常量和变量
+括号没有闭合或以操作符结尾时在 ...> 提示符后继续输入，输入空行或按 Ctrl-C 放弃
+  :hex :dec :bin :oct  只输出该进制的结果
+  :all                 恢复启动时的输出 (默认为十进制、十六进制、八进制和二进制)
+  :history             列出最近的历史记录
+  :help                显示本帮助
+  :quit                退出，也可以按 Ctrl-D";
+
+const REPL_HELP_EN: &str = "\
+Type an expression and press Enter to evaluate it, written as for enjoy calc (see enjoy calc --help), e.g. base = 0x4000_0000; base + 0x10
+The previous result is ans, and assigned variables stay available on later lines; Up/Down browse the history, Tab completes functions, constants and variables
+An unclosed bracket or a trailing operator continues the input after the ...> prompt; enter an empty line or press Ctrl-C to discard it
+  :hex :dec :bin :oct  print only that base
+  :all                 restore the startup output (default: decimal, hex, octal and binary)
+  :history             list recent history
+  :help                show this help
+  :quit                exit, or press Ctrl-D";
+
+/// 交互模式的命令，用于 Tab 补全
+const REPL_COMMANDS: &[&str] = &[
+    ":hex", ":dec", ":bin", ":oct", ":all", ":history", ":help", ":quit",
+];
+
+/// Tab 补全的候选项：内置函数、命名常量、ans、已赋值的变量和交互模式的命令，按字母顺序排列
+fn repl_completions(context: &EvalContext) -> Vec<String> {
+    let mut words: Vec<String> = FUNCTIONS
+        .iter()
+        .map(|function| function.name)
+        .chain(CONSTANTS.iter().map(|(name, _)| *name))
+        .chain(REPL_COMMANDS.iter().copied())
+        .chain(["ans"])
+        .map(str::to_string)
+        .chain(context.variables.keys().cloned())
+        .collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/// :history 列出的历史记录条数
+const REPL_HISTORY_SHOWN: usize = 20;
+
+/// 历史记录文件：$XDG_STATE_HOME/enjoy/history，默认为 ~/.local/state/enjoy/history；
+/// Windows 上为 %LOCALAPPDATA%\enjoy\history
+fn history_path() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    if cfg!(windows) {
+        return non_empty("LOCALAPPDATA")
+            .map(|dir| PathBuf::from(dir).join("enjoy").join("history"));
+    }
+    let state = non_empty("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state.join("enjoy").join("history"))
+}
+
+/// 交互模式：逐行计算表达式，出错时只打印错误并继续；读到文件结尾 (Ctrl-D) 时正常退出
+/// 标准输入不是终端时（如从管道读取）不显示提示符，也不读写历史记录文件，避免脚本的输入混进历史记录；
+/// 历史记录在每次输入后写入文件，--no-history 时只在本次会话中保留
+fn run_repl(args: &mut Args, context: &mut EvalContext) {
+    let terminal = std::io::stdin().is_terminal();
+    if terminal {
+        println!(
+            "{}",
+            tr!(
+                "enjoy 交互模式，输入 :help 查看命令，Ctrl-D 退出",
+                "enjoy interactive mode, type :help for commands, Ctrl-D to exit"
+            )
+        );
+    }
+    let history_path = if args.no_history || !terminal {
+        None
+    } else {
+        history_path()
+    };
+    let history = history_path
+        .as_deref()
+        .map(|path| line_editor::load_history(path, args.history_size))
+        .unwrap_or_default();
+    let mut editor = line_editor::LineEditor::new(history, terminal);
+    let initial_output = args.output.clone();
+    // 写入失败只提示一次
+    let mut save_failed = false;
+    // 还没有输入完整的表达式，各行之间用空格连接
+    let mut pending = String::new();
+    loop {
+        let prompt = if pending.is_empty() {
+            "enjoy> "
+        } else {
+            "...> "
+        };
+        let line = match editor.read_line(prompt, &repl_completions(context)) {
+            Ok(line_editor::Input::Line(line)) => line,
+            // Ctrl-C 放弃未完成的表达式，不退出
+            Ok(line_editor::Input::Interrupted) => {
+                pending.clear();
+                continue;
+            }
+            Ok(line_editor::Input::Eof) => break,
+            Err(err) => {
+                print_error(tr!("无法读取输入: {}", "cannot read input: {}", err));
+                break;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            // 续行时输入空行也放弃未完成的表达式
+            pending.clear();
+            continue;
+        }
+        let entry = if pending.is_empty() {
+            line.to_string()
+        } else {
+            format!("{} {}", pending, line)
+        };
+        let tokens = tokenize_args(std::slice::from_ref(&entry));
+        if !entry.starts_with(':')
+            && tokens
+                .as_ref()
+                .is_ok_and(|(tokens, _)| needs_continuation(tokens))
+        {
+            pending = entry;
+            continue;
+        }
+        pending.clear();
+
+        // 多行的表达式作为一条历史记录
+        editor.add_history(&entry);
+        if let Some(path) = &history_path
+            && let Err(err) = line_editor::save_history(path, editor.history(), args.history_size)
+            && !save_failed
+        {
+            eprintln!(
+                "{}",
+                tr!(
+                    "警告: 无法写入历史记录 {}: {}",
+                    "warning: cannot write history {}: {}",
+                    path.display(),
+                    err
+                )
+            );
+            save_failed = true;
+        }
+        match entry.as_str() {
+            ":quit" | ":q" | ":exit" => break,
+            ":help" | ":h" => println!(
+                "{}",
+                match lang() {
+                    Lang::ZhCn => REPL_HELP,
+                    Lang::EnUs => REPL_HELP_EN,
+                }
+            ),
+            ":hex" => args.output = vec![Base::Hex],
+            ":dec" => args.output = vec![Base::Dec],
+            ":bin" => args.output = vec![Base::Bin],
+            ":oct" => args.output = vec![Base::Oct],
+            ":all" => args.output = initial_output.clone(),
+            ":history" => {
+                let history = editor.history();
+                let start = history.len().saturating_sub(REPL_HISTORY_SHOWN);
+                for (i, entry) in history.iter().enumerate().skip(start) {
+                    println!("{:>5}  {}", i + 1, entry);
+                }
+            }
+            command if command.starts_with(':') => print_error(tr!(
+                "未知的命令 {}，输入 :help 查看命令",
+                "unknown command {}, type :help for commands",
+                command
+            )),
+            _ => {
+                run_expression(tokens, args, context, None);
+            }
+        }
+    }
+}
+
+/// --diff 的退出状态：两个值相同为 0，不同为 1，计算出错为 2 (与 diff/cmp 一致)
+const DIFF_IDENTICAL: i32 = 0;
+const DIFF_DIFFERENT: i32 = 1;
+const DIFF_ERROR: i32 = 2;
+
+/// 逐位比较两个表达式的值：上下对齐输出两者的二进制，在不同的位下方标出 ^，并给出异或值和不同的位
+/// 返回进程的退出状态，--quiet 时不输出，只用退出状态表示比较结果
+fn run_diff(expressions: &[String], args: &Args, context: &mut EvalContext) -> i32 {
+    let options = EvalOptions::from_args(args);
+    let mut values = Vec::new();
+    for expression in expressions {
+        let (tokens, sources) = parse_tokens(std::slice::from_ref(expression), args);
+        let value = tokens.and_then(|tokens| {
+            Ok(evaluate_statements::<Dimensioned<i128>>(
+                &tokens,
+                &options,
+                context,
+                &mut Vec::new(),
+            )?
+            .value)
+        });
+        match value {
+            Ok(value) => values.push(value),
+            Err(err) => {
+                print_error(err.render(&sources));
+                return DIFF_ERROR;
+            }
+        }
+    }
+    let (left, right) = (values[0], values[1]);
+    let quiet = args.quiet.is_some();
+    if left == right {
+        if !quiet {
+            println!(
+                "{}",
+                tr!(
+                    "两个值相同: {} (0x{:X})",
+                    "values are identical: {} (0x{:X})",
+                    left,
+                    left
+                )
+            );
+        }
+        return DIFF_IDENTICAL;
+    }
+    if quiet {
+        return DIFF_DIFFERENT;
+    }
+
+    // 两个值补零到相同的位数，按 --group-bits 分组后逐位对齐
+    let style = Style::from_args(args);
+    let group = args.group_bits;
+    let width = get_padded_binary(left, 0, group)
+        .len()
+        .max(get_padded_binary(right, 0, group).len());
+    let left_bits = get_padded_binary(left, width as u32, group);
+    let right_bits = get_padded_binary(right, width as u32, group);
+    let markers: String = left_bits
+        .chars()
+        .zip(right_bits.chars())
+        .map(|(a, b)| if a == b { ' ' } else { '^' })
+        .collect();
+    let marker_groups: Vec<String> = split_into_groups(&markers, group)
+        .iter()
+        .map(|markers| style.paint(Style::WARNING, markers))
+        .collect();
+    // 不是单个数字时先给出表达式的原文；十六进制与二进制一样按显示的位数取补码
+    for (label, (expression, value)) in ["A", "B"].iter().zip(expressions.iter().zip(&values)) {
+        if parse_number(expression).is_err() {
+            println!("{} = {} = {}", label, expression.trim(), value);
+        }
+    }
+    for (label, bits) in [("A", &left_bits), ("B", &right_bits)] {
+        println!(
+            "{}:    {}  0x{:X}",
+            label,
+            binary_row(&split_into_groups(bits, group), style),
+            u128::from_str_radix(bits, 2).unwrap_or_default()
+        );
+    }
+    println!("      {}", marker_groups.join(" ").trim_end());
+    println!(
+        "      {}",
+        style.paint(Style::INDEX, &index_row(width, group))
+    );
+
+    let xor = left ^ right;
+    let xor_bits: String = left_bits
+        .chars()
+        .zip(right_bits.chars())
+        .map(|(a, b)| if a == b { '0' } else { '1' })
+        .collect();
+    println!(
+        "{}",
+        tr!(
+            "异或: 0x{:X}",
+            "XOR: 0x{:X}",
+            u128::from_str_radix(&xor_bits, 2).unwrap_or_default()
+        )
+    );
+    let differing: Vec<String> = (0..width)
+        .rev()
+        .filter(|&bit| xor >> bit & 1 != 0)
+        .map(|bit| bit.to_string())
+        .collect();
+    println!(
+        "{}",
+        tr!(
+            "不同的位 ({} 位): {}",
+            "differing bits ({} bits): {}",
+            differing.len(),
+            differing.join(", ")
+        )
+    );
+    DIFF_DIFFERENT
+}
+
+impl Outcome {
+    /// 进程的退出状态：比较为假、断言失败时为 1，解析错误为 2，计算错误为 3
+    fn status(self) -> RunResult {
+        match self {
+            Outcome::Success => Ok(()),
+            Outcome::False => Err(1),
+            Outcome::Failure(code) => Err(code),
+        }
+    }
+}
+
+/// --reduce 读到的数值的统计，只有 sum 和 avg 才累加和
+#[derive(Debug, Default, PartialEq)]
+struct Totals {
+    count: i128,
+    sum: i128,
+    min: Option<i128>,
+    max: Option<i128>,
+    /// --skip-bad 跳过的无法解析的行数
+    skipped: usize,
+}
+
+impl Totals {
+    /// 逐行读取数值并累计，空行跳过；Err 为错误信息和退出状态
+    fn read(
+        reduce: Reduce,
+        lines: impl Iterator<Item = std::io::Result<String>>,
+        skip_bad: bool,
+    ) -> Result<Totals, (String, i32)> {
+        let mut totals = Totals::default();
+        for (i, line) in lines.enumerate() {
+            let number = i + 1;
+            let parsed = match &line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => parse_number(line).map_err(|err| err.to_string()),
+                // 不是 UTF-8 的行按无法解析处理，其他读取错误直接退出
+                Err(err) if err.kind() == std::io::ErrorKind::InvalidData => Err(err.to_string()),
+                Err(err) => {
+                    return Err((
+                        tr!(
+                            "无法读取标准输入: {}",
+                            "cannot read standard input: {}",
+                            err
+                        ),
+                        1,
+                    ));
+                }
+            };
+            let value = match parsed {
+                Ok(value) => value,
+                Err(_) if skip_bad => {
+                    totals.skipped += 1;
+                    continue;
+                }
+                Err(err) => return Err((tr!("第 {} 行: {}", "line {}: {}", number, err), 2)),
+            };
+            totals.count += 1;
+            if matches!(reduce, Reduce::Sum | Reduce::Avg) {
+                let Some(sum) = totals.sum.checked_add(value) else {
+                    return Err((
+                        tr!(
+                            "第 {} 行: 和超出 128 位",
+                            "line {}: sum does not fit in 128 bits",
+                            number
+                        ),
+                        3,
+                    ));
+                };
+                totals.sum = sum;
+            }
+            totals.min = Some(totals.min.map_or(value, |min| min.min(value)));
+            totals.max = Some(totals.max.map_or(value, |max| max.max(value)));
+        }
+        Ok(totals)
+    }
+
+    /// 聚合的结果，平均值为整数商；没有读到数值时为 None
+    fn result(&self, reduce: Reduce) -> Option<i128> {
+        match reduce {
+            Reduce::Sum => Some(self.sum),
+            Reduce::Min => self.min,
+            Reduce::Max => self.max,
+            Reduce::Count => Some(self.count),
+            Reduce::Avg if self.count == 0 => None,
+            Reduce::Avg => Some(self.sum / self.count),
+        }
+    }
+}
+
+/// --reduce：逐行读取数值并聚合，不保留读到的数值，结果按普通表达式的结果输出
+fn run_reduce(
+    reduce: Reduce,
+    lines: impl Iterator<Item = std::io::Result<String>>,
+    args: &Args,
+    context: &mut EvalContext,
+) -> RunResult {
+    let totals = match Totals::read(reduce, lines, args.skip_bad) {
+        Ok(totals) => totals,
+        Err((message, code)) => {
+            print_error(message);
+            return Err(code);
+        }
+    };
+    if totals.skipped > 0 {
+        eprintln!(
+            "{}",
+            tr!(
+                "注意: 跳过了 {} 个无法解析的行",
+                "note: skipped {} unparsable lines",
+                totals.skipped
+            )
+        );
+    }
+    let Some(result) = totals.result(reduce) else {
+        print_error(tr!("标准输入中没有数值", "no numbers on standard input"));
+        return Err(1);
+    };
+    if reduce == Reduce::Avg && !args.machine_output() {
+        println!(
+            "{}",
+            tr!(
+                "平均值: {} / {} = {} 余 {} (约 {})",
+                "average: {} / {} = {} remainder {} (about {})",
+                totals.sum,
+                totals.count,
+                result,
+                totals.sum % totals.count,
+                totals.sum as f64 / totals.count as f64
+            )
+        );
+    }
+    let tokens = vec![ExprToken::Number(result)];
+    run_expression(Ok((tokens, vec![result.to_string()])), args, context, None).status()
+}
+
+/// 计算模式：交互模式、--stdin、--file、--diff、--table 以及一个或多个表达式
+fn run_calc(expressions: &[Vec<String>], args: &mut Args, context: &mut EvalContext) -> RunResult {
+    if args.interactive {
+        run_repl(args, context);
+        return Ok(());
+    }
+
+    if args.stdin {
+        let lines = std::io::BufRead::lines(std::io::stdin().lock());
+        return run_lines(lines, args, context, true, false);
+    }
+
+    if let Some(reduce) = args.reduce {
+        let lines = std::io::BufRead::lines(std::io::stdin().lock());
+        return run_reduce(reduce, lines, args, context);
+    }
+
+    if let Some(path) = &args.file {
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                print_error(tr!(
+                    "无法读取 {}: {}",
+                    "cannot read {}: {}",
+                    path.display(),
+                    err
+                ));
+                return Err(1);
+            }
+        };
+        let lines = std::io::BufRead::lines(std::io::BufReader::new(file));
+        let header = args.assert.is_none() && !args.machine_output();
+        return run_lines(lines, args, context, args.keep_going, header);
+    }
+
+    if let Some(operands) = &args.diff {
+        return match run_diff(operands, args, context) {
+            DIFF_IDENTICAL => Ok(()),
+            code => Err(code),
+        };
+    }
+
+    // 同时保存每个元素的原文，用于在错误提示中指出出错的位置
+    if let Some(range) = args.table {
+        if expressions.len() > 1 {
+            print_error(tr!(
+                "--table 只能与一个 --calc 表达式一起使用",
+                "--table can only be used with a single --calc expression"
+            ));
+            return Err(1);
+        }
+        let step = args.step.unwrap_or(1);
+        let expression = expressions.first().map(Vec::as_slice);
+        run_table(range, step, expression, args, context)
+    } else if let Some(path) = &args.tokens_from_json {
+        let tokens = match read_tokens_from_json(path) {
+            Ok(tokens) => tokens,
+            Err(err) if args.json => {
+                print_json(&JsonError {
+                    message: err.to_string(),
+                    token: None,
+                    line: None,
+                });
+                return Err(1);
+            }
+            Err(err) => {
+                print_error(err);
+                return Err(1);
+            }
+        };
+        let sources = tokens.iter().map(|t| t.to_string()).collect();
+        run_expression(Ok((tokens, sources)), args, context, None).status()
+    } else if expressions.len() == 1 {
+        run_expression(tokenize_args(&expressions[0]), args, context, None).status()
+    } else {
+        // 每个表达式独立计算并输出，出错不影响后面的表达式；
+        // 有表达式出错或比较为假时，以第一个这样的表达式的退出状态退出
+        let mut status = Ok(());
+        for (i, expression) in expressions.iter().enumerate() {
+            if args.assert.is_none() && !args.check && !args.machine_output() {
+                if i > 0 {
+                    println!();
+                }
+                println!(
+                    "{}",
+                    tr!(
+                        "表达式 {}: {}",
+                        "expression {}: {}",
+                        i + 1,
+                        expression.join(" ").trim()
+                    )
+                );
+            }
+            status = status.and(
+                run_expression(tokenize_args(expression), args, context, Some(i + 1)).status(),
+            );
+        }
+        status
+    }
+}
+
+/// enjoy conv 的数值：单个数字字面量，可以带负号，--in-signed 同样适用
+fn parse_conv_value(value: &str, in_signed: Option<u32>) -> Result<i128, EnjoyError> {
+    let (mut tokens, sources) = tokenize_args(&[value.to_string()]).map_err(EnjoyError::parse)?;
+    sign_extend_literals(&mut tokens, &sources, in_signed)?;
+    let value = value.trim().to_string();
+    match tokens.as_slice() {
+        [ExprToken::Number(n)] => Ok(*n),
+        [ExprToken::Operator(Operator::Sub), ExprToken::Number(n)] => Ok(-n),
+        [ExprToken::Float(_)] => Err(EnjoyError::parse(Message::ConvNotInteger { value })),
+        _ => Err(EnjoyError::parse(Message::ConvNotLiteral { value })),
+    }
+}
+
+/// enjoy conv：每个数值单独给出一组输出；--to 或 --quiet 等只输出结果的值
+fn run_conv(conv: &ConvArgs, args: &Args, context: &mut EvalContext) -> RunResult {
+    let style = Style::from_args(args);
+    let multiple = conv.values.len() > 1;
+    let mut status = Ok(());
+    for (i, source) in conv.values.iter().enumerate() {
+        let value = match parse_conv_value(source, args.in_signed) {
+            Ok(value) => value,
+            Err(err) => {
+                print_error(&err);
+                status = status.and(Err(err.exit_code()));
+                continue;
+            }
+        };
+        if let Some(base) = conv.to {
+            let options = EvalOptions::from_args(args);
+            println!(
+                "{}",
+                FormattedResult::new(value, &options, None, &[]).quiet(base, false)
+            );
+        } else if args.machine_output() {
+            let index = multiple.then_some(i + 1);
+            let tokens = vec![ExprToken::Number(value)];
+            status = status.and(
+                run_expression(
+                    Ok((tokens, vec![source.trim().to_string()])),
+                    args,
+                    context,
+                    index,
+                )
+                .status(),
+            );
+        } else {
+            if multiple {
+                if i > 0 {
+                    println!();
+                }
+                println!(
+                    "{}",
+                    tr!("数值 {}: {}", "value {}: {}", i + 1, source.trim())
+                );
+            }
+            print_conversion(value, style);
+        }
+    }
+    status
+}
+
+/// enjoy config path：输出配置文件的位置
+fn run_config(config: &ConfigArgs) -> RunResult {
+    match config.action {
+        ConfigAction::Path => match config_path() {
+            Some(path) => {
+                println!("{}", path.display());
+                Ok(())
+            }
+            None => {
+                print_error(tr!(
+                    "无法确定配置文件的位置，需要设置 HOME 或 XDG_CONFIG_HOME (Windows 上为 APPDATA) 环境变量",
+                    "cannot locate the config file: set HOME or XDG_CONFIG_HOME (APPDATA on Windows)"
+                ));
+                Err(1)
+            }
+        },
+    }
+}
+
+/// 读取配置文件，把其中的默认值填入没有在命令行中给出的参数
+/// --no-config 时不读取；不计算表达式的子命令也不读取，配置文件有错误时 enjoy config path 仍然可以使用
+fn load_config(args: &mut Args, matches: &ArgMatches) -> RunResult {
+    let uses_config = !matches!(
+        args.command,
+        Some(Commands::Config(_) | Commands::Completions(_) | Commands::Gerrit(_))
+    );
+    if args.no_config || !uses_config {
+        return Ok(());
+    }
+    let Some(path) = config_path() else {
+        return Ok(());
+    };
+    let config = Config::load(&path).map_err(|err| {
+        print_error(err);
+        1
+    })?;
+    let from_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    if let Some(output) = config.output.filter(|_| !from_command_line("output")) {
+        args.output = output;
+    }
+    if args.width.is_none() {
+        args.width = config.width;
+    }
+    if let Some(group_bits) = config
+        .group_bits
+        .filter(|_| !from_command_line("group_bits"))
+    {
+        args.group_bits = group_bits;
+    }
+    if config.color == Some(false) {
+        args.no_color = true;
+    }
+    args.constants = config.constants;
+    args.default_calc = config.default_calc;
+    Ok(())
+}
+
+/// enjoy completions：把所选 shell 的补全脚本写到标准输出
+fn run_completions(shell: clap_complete::Shell) -> RunResult {
+    print!("{}", completion_script(shell));
+    Ok(())
+}
+
+/// enjoy completions 输出的补全脚本：--width 等自定义解析的参数给出候选值，表达式中补全函数名和常量名
+pub(crate) fn completion_script(shell: clap_complete::Shell) -> String {
+    let widths: &[&str] = &["8", "16", "32", "64"];
+    let values = [
+        ("width", widths),
+        ("in_signed", widths),
+        ("float_bits", &["32", "64"]),
+        ("group_bits", &["4", "8", "16"]),
+    ];
+    let mut words: Vec<&'static str> = FUNCTIONS
+        .iter()
+        .map(|function| function.name)
+        .chain(CONSTANTS.iter().map(|(name, _)| *name))
+        .chain([ANS])
+        .collect();
+    words.sort();
+    completions::generate(
+        Args::command(),
+        shell,
+        &values,
+        &["calc", "expression"],
+        &words,
+    )
+}
+
+/// enjoy gerrit：合并命令行和 git config 中的设置，--show-config 时只输出设置
+fn push_gerrit(gerrit: &GerritArgs, no_config: bool) -> Result<(), GerritError> {
+    let profiles = gerrit_profiles(no_config)?;
+    match &gerrit.action {
+        Some(GerritAction::List(list)) => return list_gerrit_changes(list, &profiles),
+        Some(GerritAction::Profiles) => {
+            profiles.print();
+            return Ok(());
+        }
+        None => {}
+    }
+    let profile = profiles.select(gerrit.profile.as_deref())?;
+    // --refspec 为 refs/for/<branch> 时以其中的分支作为目标分支，用于列出要推送的提交
+    let branch = gerrit
+        .branch
+        .clone()
+        .or_else(|| gerrit.refspec.as_deref().and_then(gerrit::refspec_branch));
+    let settings = gerrit::Settings::resolve(
+        gerrit.remote.clone(),
+        branch,
+        gerrit.reviewers.clone(),
+        profile,
+    )?;
+    if gerrit.show_config {
+        settings.print();
+        return Ok(());
+    }
+    gerrit::push(&gerrit.push_options(settings), &mut gerrit::TerminalPrompt)
+}
+
+/// enjoy gerrit list：由远程仓库的地址得到服务器，查询后输出表格
+fn list_gerrit_changes(
+    list: &GerritListArgs,
+    profiles: &gerrit::Profiles,
+) -> Result<(), GerritError> {
+    let profile = profiles.select(list.profile.as_deref())?;
+    let remote = gerrit::resolve_remote(list.remote.clone(), profile)?.value;
+    let url = gerrit::check_remote_exists(&remote)?;
+    let server = gerrit::server(&url).ok_or_else(|| {
+        GerritError::Query(tr!(
+            "无法从远程仓库 {} 的地址 {} 得到 Gerrit 服务器，需要 ssh:// 或 http(s):// 地址",
+            "cannot find a Gerrit server from remote {} ({}), an ssh:// or http(s):// URL is needed",
+            remote,
+            url
+        ))
+    })?;
+    let changes = changes::list(&server, &mut changes::CommandFetch)?;
+    println!("{}", changes::render_table(&changes));
+    Ok(())
+}
+
+/// 配置文件中的 gerrit 配置，--no-config 或找不到配置文件时为空
+fn gerrit_profiles(no_config: bool) -> Result<gerrit::Profiles, GerritError> {
+    match config_path() {
+        Some(path) if !no_config => Config::load(&path)
+            .map(|config| config.gerrit)
+            .map_err(GerritError::Profile),
+        _ => Ok(gerrit::Profiles::default()),
+    }
+}
+
+/// enjoy gerrit 和已弃用的 --gerrit 的错误处理
+fn run_gerrit(result: Result<(), GerritError>) -> RunResult {
+    result.map_err(|err| {
+        let err = EnjoyError::from(err);
+        print_error(&err);
+        err.exit_code()
+    })
+}
+
+/// 在 clap 解析之前从命令行参数中找出 --lang，使 clap 报告的参数错误也使用该语言；
+/// 没有给出或无法识别时按环境变量选择，无法识别的值留给 clap 报错
+fn lang_from_args(argv: &[String]) -> Lang {
+    let mut iter = argv.iter().skip(1).take_while(|arg| *arg != "--");
+    while let Some(arg) = iter.next() {
+        let value = match arg.strip_prefix("--lang") {
+            Some("") => iter.next().map(String::as_str),
+            Some(value) => value.strip_prefix('='),
+            None => continue,
+        };
+        if let Some(lang) = value.and_then(|value| Lang::from_str(value, true).ok()) {
+            return lang;
+        }
+    }
+    Lang::from_env()
+}
+
+/// 以负号开头、后面紧跟数字的参数（如 -0x10）会被 clap 当成短选项，
+/// 在它们前面补一个空格，让 clap 按普通值处理，数值解析时会去掉空白
+fn normalize_args(args: impl Iterator<Item = String>) -> Vec<String> {
+    args.map(|arg| {
+        let mut chars = arg.chars();
+        if chars.next() == Some('-') && chars.next().is_some_and(|c| c.is_ascii_digit()) {
+            format!(" {}", arg)
+        } else {
+            arg
+        }
+    })
+    .collect()
+}
+
+/// 命令行的入口：解析进程的命令行参数和配置文件并执行，返回进程的退出状态
+pub fn run_cli() -> i32 {
+    let argv = normalize_args(std::env::args());
+    set_lang(lang_from_args(&argv));
+    let matches = Args::command().get_matches_from(argv);
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // 每次出现的 --calc 是一个独立的表达式
+    let expressions: Vec<Vec<String>> = matches
+        .get_occurrences::<String>("calc")
+        .map(|occurrences| {
+            occurrences
+                .map(|values| values.cloned().collect())
+                .collect()
+        })
+        .unwrap_or_default();
+    match load_config(&mut args, &matches).and_then(|()| run(args, expressions)) {
+        Ok(()) => 0,
+        Err(code) => code,
+    }
+}
+
+/// 按解析好的命令行参数执行，expressions 为每次出现的 --calc 的表达式；Err 为进程的退出状态
+fn run(mut args: Args, expressions: Vec<Vec<String>>) -> RunResult {
+    if let Some(lang) = args.lang {
+        set_lang(lang);
+    }
+    debug::set_level(args.debug);
+    debug!(
+        debug::TIMING,
+        "命令行参数: {:?}",
+        "argv: {:?}",
+        std::env::args().collect::<Vec<_>>()
+    );
+    // --decode 的位段从文件中读出后与 --fields 走同一条输出路径
+    if let (Some(path), Some(register)) = (&args.regmap, &args.decode) {
+        match FieldSpec::from_regmap(path, register) {
+            Ok(spec) => args.fields = Some(spec),
+            Err(err) => {
+                print_error(err);
+                return Err(1);
+            }
+        }
+    }
+
+    if args.list_constants {
+        for (name, value) in CONSTANTS {
+            println!("{:<10} = {} (0x{:X})", name, value, value);
+        }
+        for (name, value) in &args.constants {
+            println!(
+                "{:<10} = {} (0x{:X}) {}",
+                name,
+                value,
+                value,
+                tr!("(配置文件)", "(config file)")
+            );
+        }
+        return Ok(());
+    }
+
+    // 按顺序计算的表达式共享上下文，后面的表达式可以用 ans 引用前一个结果，也可以使用前面赋值的变量
+    // 配置文件中的常量作为变量放入上下文
+    let mut context = EvalContext::default();
+    for (name, value) in &args.constants {
+        context.variables.insert(name.clone(), Saved::Int(*value));
+    }
+
+    // 表达式可能来自顶层参数或 calc 子命令，无法用 clap 的 requires 表达，在这里检查
+    let has_expression = !expressions.is_empty()
+        || args.tokens_from_json.is_some()
+        || matches!(args.command, Some(Commands::Calc(_)));
+    if (args.assert.is_some() || args.check) && !has_expression {
+        let flag = if args.check { "--check" } else { "--assert" };
+        Args::command()
+            .error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                tr!(
+                    "{} 需要一个表达式: --calc、--tokens-from-json 或 calc 子命令",
+                    "{} needs an expression: --calc, --tokens-from-json or the calc subcommand",
+                    flag
+                ),
+            )
+            .exit();
+    }
+
+    match args.command.take() {
+        Some(Commands::Calc(calc)) => run_calc(&[calc.expression], &mut args, &mut context),
+        Some(Commands::Conv(conv)) => run_conv(&conv, &args, &mut context),
+        Some(Commands::Gerrit(gerrit)) => run_gerrit(push_gerrit(&gerrit, args.no_config)),
+        Some(Commands::Completions(completions)) => run_completions(completions.target),
+        Some(Commands::Config(config)) => run_config(&config),
+        None => {
+            // 顶层的 --calc 和 --gerrit 保留一个版本，之后移除
+            if !expressions.is_empty() {
+                eprintln!(
+                    "{}",
+                    tr!(
+                        "注意: --calc 已弃用，请改用 enjoy calc <表达式>",
+                        "note: --calc is deprecated, use enjoy calc <EXPR> instead"
+                    )
+                );
+            }
+            if args.gerrit {
+                eprintln!(
+                    "{}",
+                    tr!(
+                        "注意: --gerrit 已弃用，请改用 enjoy gerrit",
+                        "note: --gerrit is deprecated, use enjoy gerrit instead"
+                    )
+                );
+            }
+            // 没有表达式也没有其他输入时计算配置文件中的 default_calc
+            let reads_input = args.gerrit
+                || args.interactive
+                || args.stdin
+                || args.reduce.is_some()
+                || args.file.is_some()
+                || args.diff.is_some()
+                || args.table.is_some()
+                || args.tokens_from_json.is_some();
+            let expressions = match args.default_calc.take() {
+                Some(expression) if expressions.is_empty() && !reads_input => {
+                    vec![vec![expression]]
+                }
+                _ => expressions,
+            };
+            run_calc(&expressions, &mut args, &mut context).and_then(|()| match args.gerrit {
+                true => run_gerrit(gerrit::push(
+                    &gerrit::PushOptions {
+                        branch: args.branch.clone(),
+                        ..Default::default()
+                    },
+                    &mut gerrit::TerminalPrompt,
+                )),
+                false => Ok(()),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr::EvalNote;
+
+    /// 解析一组命令行参数，不读取配置文件
+    fn args(argv: &[&str]) -> Args {
+        let argv = ["enjoy", "--no-config"]
+            .iter()
+            .chain(argv)
+            .map(|arg| arg.to_string());
+        Args::try_parse_from(normalize_args(argv)).unwrap()
+    }
+
+    #[test]
+    fn emit_snippets() {
+        let emit = |value, argv: &[&str]| emit_snippet(value, &args(argv));
+        let c = ["--emit", "c:FOO"];
+        assert_eq!(emit(0x1234, &c).unwrap(), "#define FOO 0x1234u");
+        assert_eq!(
+            emit(1 << 32, &c).unwrap(),
+            "#define FOO 0x0000000100000000ull"
+        );
+        assert_eq!(emit(-5, &c).unwrap(), "#define FOO (-5)");
+        // 最小值写作 (-MAX - 1)
+        assert_eq!(
+            emit(i32::MIN as i128, &c).unwrap(),
+            "#define FOO (-2147483647 - 1)"
+        );
+        assert_eq!(
+            emit(i64::MIN as i128, &c).unwrap(),
+            "#define FOO (-9223372036854775807LL - 1)"
+        );
+        assert_eq!(
+            emit(1 << 64, &c).unwrap_err(),
+            "C 没有 128 位的整数常量，结果 18446744073709551616 超出 64 位"
+        );
+        let rust = ["--emit", "rust:FOO"];
+        assert_eq!(
+            emit(0xDEADBEEF, &rust).unwrap(),
+            "pub const FOO: u32 = 0xDEAD_BEEF;"
+        );
+        assert_eq!(emit(-129, &rust).unwrap(), "pub const FOO: i16 = -129;");
+        assert_eq!(
+            emit(0xFF, &["--emit", "rust:FOO", "--width", "16"]).unwrap(),
+            "pub const FOO: u16 = 0x00FF;"
+        );
+        assert_eq!(
+            emit(256, &["--emit", "c:FOO", "--width", "8"]).unwrap_err(),
+            "结果 256 超出 8 位"
+        );
+        // 字节数组默认按小端序，--endian be 和 --width 决定字节序和字节数
+        assert_eq!(
+            emit(0x12345678, &["--emit", "bytes"]).unwrap(),
+            "{0x78, 0x56, 0x34, 0x12}\n[0x78, 0x56, 0x34, 0x12]"
+        );
+        assert_eq!(
+            emit(
+                0x1234,
+                &["--emit", "bytes", "--endian", "be", "--width", "32"]
+            )
+            .unwrap(),
+            "{0x00, 0x00, 0x12, 0x34}\n[0x00, 0x00, 0x12, 0x34]"
+        );
+        assert_eq!(emit(-1, &["--emit", "bytes"]).unwrap(), "{0xFF}\n[0xFF]");
+    }
+
+    #[test]
+    fn emit_names() {
+        assert!(matches!(parse_emit("c:FOO_MASK"), Ok(Emit::C(name)) if name == "FOO_MASK"));
+        assert!(matches!(parse_emit("rs:_x1"), Ok(Emit::Rust(name)) if name == "_x1"));
+        assert!(matches!(parse_emit("BYTES"), Ok(Emit::Bytes)));
+        for invalid in ["FOO", "c:", "c:1FOO", "c:FOO-BAR", "rust:fn", "go:FOO"] {
+            assert!(parse_emit(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn shell_quoting() {
+        assert_eq!(shell_quote("0xFF"), "0xFF");
+        assert_eq!(shell_quote("-1.5"), "-1.5");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's $HOME"), "'it'\\''s $HOME'");
+        assert_eq!(shell_prefix("RESULT_", Some(3)), "RESULT3_");
+        assert_eq!(shell_prefix("X", Some(2)), "X2");
+        assert_eq!(shell_prefix("RESULT_", None), "RESULT_");
+    }
+
+    /// 加了引号的值经 sh 的 eval 后原样还原
+    #[cfg(unix)]
+    #[test]
+    fn shell_quoting_round_trip() {
+        let values = ["plain", "a b", "it's", "$HOME `id` \\n", "\"'\"", ""];
+        let script: String = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("V{}={}\n", i, shell_quote(value)))
+            .collect();
+        let print: String = (0..values.len())
+            .map(|i| format!("printf '%s\\n' \"$V{}\"\n", i))
+            .collect();
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(format!("eval \"$1\"\n{}", print))
+            .arg("sh")
+            .arg(&script)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let expected: String = values.iter().map(|value| format!("{}\n", value)).collect();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+    }
+
+    /// 固定成功或失败的剪贴板后端，记录收到的文本
+    struct FakeBackend {
+        name: &'static str,
+        works: bool,
+        copied: std::cell::RefCell<Vec<String>>,
+    }
+
+    impl FakeBackend {
+        fn new(name: &'static str, works: bool) -> Self {
+            FakeBackend {
+                name,
+                works,
+                copied: Default::default(),
+            }
+        }
+    }
+
+    impl CopyBackend for FakeBackend {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn copy(&self, text: &str) -> bool {
+            self.copied.borrow_mut().push(text.to_string());
+            self.works
+        }
+    }
+
+    #[test]
+    fn clipboard_fallback() {
+        let (pbcopy, xclip, clip) = (
+            FakeBackend::new("pbcopy", false),
+            FakeBackend::new("xclip", true),
+            FakeBackend::new("clip.exe", true),
+        );
+        // 前面的后端失败时按顺序尝试下一个，成功后不再尝试
+        assert_eq!(
+            copy_to_clipboard("0xFF", &[&pbcopy, &xclip, &clip]).unwrap(),
+            "xclip"
+        );
+        assert_eq!(*pbcopy.copied.borrow(), ["0xFF"]);
+        assert_eq!(*xclip.copied.borrow(), ["0xFF"]);
+        assert!(clip.copied.borrow().is_empty());
+
+        let (pbcopy, xsel) = (
+            FakeBackend::new("pbcopy", false),
+            FakeBackend::new("xsel", false),
+        );
+        assert_eq!(
+            copy_to_clipboard("1", &[&pbcopy, &xsel]).unwrap_err(),
+            "没有可用的剪贴板，需要以下命令之一: pbcopy, xsel"
+        );
+        assert_eq!(*xsel.copied.borrow(), ["1"]);
+    }
+
+    /// 按行读取的输入
+    fn lines(input: &str) -> impl Iterator<Item = std::io::Result<String>> + '_ {
+        input.lines().map(|line| Ok(line.to_string()))
+    }
+
+    #[test]
+    fn reduce_numbers() {
+        let input = "0x10\n\n-3\n1_000\n7\n";
+        let totals = Totals::read(Reduce::Sum, lines(input), false).unwrap();
+        assert_eq!(
+            totals,
+            Totals {
+                count: 4,
+                sum: 1020,
+                min: Some(-3),
+                max: Some(1000),
+                skipped: 0,
+            }
+        );
+        assert_eq!(totals.result(Reduce::Sum), Some(1020));
+        assert_eq!(totals.result(Reduce::Min), Some(-3));
+        assert_eq!(totals.result(Reduce::Max), Some(1000));
+        assert_eq!(totals.result(Reduce::Count), Some(4));
+        assert_eq!(totals.result(Reduce::Avg), Some(255));
+        // 只有 sum 和 avg 累加和，min 不会因为和溢出而失败
+        let huge = format!("{}\n{}\n", i128::MAX, i128::MAX);
+        let totals = Totals::read(Reduce::Min, lines(&huge), false).unwrap();
+        assert_eq!(totals.result(Reduce::Min), Some(i128::MAX));
+        assert_eq!(
+            Totals::read(Reduce::Sum, lines(&huge), false).unwrap_err(),
+            ("第 2 行: 和超出 128 位".to_string(), 3)
+        );
+        // 无法解析的行报告行号，--skip-bad 时跳过并计数
+        let (message, code) = Totals::read(Reduce::Sum, lines("1\nabc\n2"), false).unwrap_err();
+        assert!(message.starts_with("第 2 行: "), "{}", message);
+        assert_eq!(code, 2);
+        let totals = Totals::read(Reduce::Count, lines("1\nabc\n2"), true).unwrap();
+        assert_eq!((totals.count, totals.skipped), (2, 1));
+        // 没有数值时平均值、最小值和最大值没有结果，个数和总和为 0
+        let totals = Totals::read(Reduce::Avg, lines("\n\n"), false).unwrap();
+        assert_eq!(totals.result(Reduce::Avg), None);
+        assert_eq!(totals.result(Reduce::Max), None);
+        assert_eq!(totals.result(Reduce::Count), Some(0));
+    }
+
+    /// 按命令行参数给出的选项以整数计算一个表达式，返回结果和求值时的说明
+    fn eval(argv: &[&str], expression: &str) -> Result<(i128, Vec<EvalNote>), EnjoyError> {
+        let options = EvalOptions::from_args(&args(argv));
+        let (tokens, _) = tokenize_args(&[expression.to_string()]).unwrap();
+        let mut notes = Vec::new();
+        let result = evaluate_statements::<i128>(
+            &tokens,
+            &options,
+            &mut EvalContext::default(),
+            &mut notes,
+        )?;
+        Ok((result, notes))
+    }
+
+    #[test]
+    fn unsigned_mode() {
+        let unsigned = |expression| eval(&["-u"], expression).map(|(result, _)| result);
+        assert_eq!(
+            unsigned("0xFFFFFFFFFFFFFFFF / 2").unwrap(),
+            0x7FFF_FFFF_FFFF_FFFF
+        );
+        assert_eq!(unsigned("0xFFFFFFFFFFFFFFFF % 10").unwrap(), 5);
+        // 负数的字面量和结果都是错误
+        assert!(matches!(
+            unsigned("-1"),
+            Err(EnjoyError::Eval {
+                error: EvalError::Other(Message::NegativeUnsigned { value: -1 }),
+                ..
+            })
+        ));
+        assert!(matches!(
+            unsigned("0 - 1"),
+            Err(EnjoyError::Eval {
+                error: EvalError::Overflow(_),
+                ..
+            })
+        ));
+        assert_eq!(eval(&[], "0 - 1").unwrap().0, -1);
+        assert!(Args::try_parse_from(["enjoy", "-u", "--signed"]).is_err());
+        // 无符号的 64 位结果显示全部 64 位
+        let options = EvalOptions::from_args(&args(&["--unsigned"]));
+        let formatted = FormattedResult::new(u64::MAX as i128, &options, None, &[]);
+        assert_eq!(formatted.hex, "0xFFFFFFFFFFFFFFFF");
+        assert_eq!(formatted.binary, "1".repeat(64));
+        assert_eq!(formatted.bits_set.len(), 64);
+    }
+
+    #[test]
+    fn float_mode_and_promotion() {
+        let tokens = |expression: &str| tokenize_args(&[expression.to_string()]).unwrap().0;
+        let context = EvalContext::default();
+        assert!(float_mode(
+            &tokens("3.3 / 4096 x 1000"),
+            &args(&[]),
+            &context
+        ));
+        assert!(!float_mode(&tokens("33 / 4096"), &args(&[]), &context));
+        assert!(float_mode(&tokens("1 / 3"), &args(&["--float"]), &context));
+        // 只对整数有意义的模式下小数仍按整数报错
+        for mode in [&["--wrap"][..], &["--saturate"], &["-u"], &["--mod", "7"]] {
+            assert!(!float_mode(&tokens("3.3 + 1"), &args(mode), &context));
+        }
+        // 引用浮点数结果时同样按浮点数计算
+        let context = EvalContext {
+            ans: Some(Saved::Float(0.5)),
+            ..EvalContext::default()
+        };
+        assert!(float_mode(&tokens("ans x 2"), &args(&[]), &context));
+        // 整数自动转换为浮点数，除零得到无穷大或 NaN 而不是错误
+        let float = |expression| {
+            evaluate_statements::<f64>(
+                &tokens(expression),
+                &EvalOptions::default(),
+                &mut EvalContext::default(),
+                &mut Vec::new(),
+            )
+            .unwrap()
+        };
+        assert_eq!(float("3.3 / 4096 x 1000"), 0.8056640625);
+        assert_eq!(float("1 / 3"), 1.0 / 3.0);
+        assert_eq!(float("1.0 / 0"), f64::INFINITY);
+        assert!(float("0.0 / 0").is_nan());
+    }
+
+    #[test]
+    fn inexact_division_note() {
+        let notes = |expression| {
+            eval(&[], expression)
+                .unwrap()
+                .1
+                .iter()
+                .map(EvalNote::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(eval(&[], "100 / 7").unwrap().0, 14);
+        assert_eq!(notes("100 / 7"), ["100 / 7 = 14 余 2 (≈14.2857)"]);
+        // 商向零截断，余数与被除数同号
+        assert_eq!(notes("-7 / 2"), ["-7 / 2 = -3 余 -1 (≈-3.5000)"]);
+        // 只记录不能整除的那一次除法
+        assert_eq!(notes("8 / 2 + 1 / 3"), ["1 / 3 = 0 余 1 (≈0.3333)"]);
+        // 整除和没有除法的表达式没有说明，输出与以前相同
+        assert!(notes("8 / 2").is_empty());
+        assert!(notes("7 % 2").is_empty());
+    }
+
+    #[test]
+    fn check_without_evaluating() {
+        let check = |argv: &[&str], expression: &str| {
+            let args = args(argv);
+            let (tokens, _) = parse_tokens(&[expression.to_string()], &args);
+            check_expression(tokens, &args, &mut EvalContext::default())
+        };
+        // 不计算，除零和溢出都不是错误
+        assert!(check(&[], "1 / 0").is_ok());
+        assert!(check(&[], "0x7FFFFFFFFFFFFFFF x 0x7FFFFFFFFFFFFFFF").is_ok());
+        assert!(check(&[], "x = 1; x + 1").is_ok());
+        // 结构、变量、参数个数、量纲和长度限制仍然检查
+        for (argv, expression, code) in [
+            (&[][..], "(1 + 2", 2),
+            (&[], "1 +", 2),
+            (&[], "min[1]", 2),
+            (&[], "foo + 1", 3),
+            (&[], "1s + 1", 3),
+            (&["--max-tokens", "3"], "1 + 2 + 3", 2),
+        ] {
+            let err = check(argv, expression).unwrap_err();
+            assert_eq!(err.exit_code(), code, "{}: {}", expression, err);
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! 生成 shell 补全脚本 (enjoy completions bash|zsh|fish|powershell|elvish)
+//! 选项、子命令和可选值由 clap_complete 从 clap 的命令定义中生成，新增的参数会自动出现在补全中；
+//! 自定义解析的参数（如 --width 的位宽）和表达式（函数名和常量名）的候选值在生成前作为可选值加入命令定义，
+//! 只有 fish 不支持位置参数的可选值，另外写出 enjoy calc 的表达式补全
+
+use clap::Command;
+use clap::builder::PossibleValuesParser;
+use clap_complete::Shell;
+
+/// 生成 shell 的补全脚本：values 按参数 id 给出候选值，
+/// expression 为取值是表达式的参数 id（包括子命令中的位置参数），这些参数补全 words 中的名称
+pub(crate) fn generate(
+    command: Command,
+    shell: Shell,
+    values: &[(&str, &[&'static str])],
+    expression: &[&str],
+    words: &[&'static str],
+) -> String {
+    let candidates = |id: &str| {
+        values
+            .iter()
+            .find(|(value_id, _)| *value_id == id)
+            .map(|(_, values)| values.to_vec())
+            .or_else(|| expression.contains(&id).then(|| words.to_vec()))
+    };
+    let mut command = with_candidates(command, &candidates);
+    if shell == Shell::Zsh {
+        command = without_global_conflicts(command);
+    }
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, &name, &mut script);
+    let mut script = String::from_utf8(script).expect("补全脚本是 UTF-8");
+    if shell == Shell::Fish {
+        script += &fish_expressions(&command, expression, words);
+    }
+    script
+}
+
+/// 把候选值作为可选值加入命令和各个子命令的参数，只用于生成补全，不影响参数的解析
+fn with_candidates(
+    command: Command,
+    candidates: &dyn Fn(&str) -> Option<Vec<&'static str>>,
+) -> Command {
+    let args: Vec<(String, Vec<&'static str>)> = command
+        .get_arguments()
+        .filter_map(|arg| {
+            let id = arg.get_id().as_str();
+            candidates(id).map(|values| (id.to_string(), values))
+        })
+        .collect();
+    let command = args.into_iter().fold(command, |command, (id, values)| {
+        command.mut_arg(id, |arg| {
+            arg.value_parser(PossibleValuesParser::new(values))
+        })
+    });
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    subcommands.into_iter().fold(command, |command, name| {
+        command.mut_subcommand(name, |subcommand| with_candidates(subcommand, candidates))
+    })
+}
+
+/// clap_complete 的 zsh 生成器在全局参数与参数组 (如 expr) 冲突时会 panic，
+/// 生成前去掉全局参数的冲突，只是 zsh 补全时不再排除与它们冲突的参数
+fn without_global_conflicts(command: Command) -> Command {
+    let global: Vec<String> = command
+        .get_arguments()
+        .filter(|arg| arg.is_global_set())
+        .map(|arg| arg.get_id().to_string())
+        .collect();
+    global.into_iter().fold(command, |command, id| {
+        command.mut_arg(id, |arg| {
+            arg.conflicts_with(clap::builder::Resettable::<clap::Id>::Reset)
+        })
+    })
+}
+
+/// fish 的补全只包括选项，为位置参数是表达式的子命令补全函数名和常量名
+fn fish_expressions(command: &Command, expression: &[&str], words: &[&str]) -> String {
+    let name = command.get_name();
+    command
+        .get_subcommands()
+        .filter(|subcommand| {
+            subcommand
+                .get_positionals()
+                .any(|arg| expression.contains(&arg.get_id().as_str()))
+        })
+        .map(|subcommand| {
+            format!(
+                "complete -c {name} -n \"__fish_{name}_using_subcommand {}\" -f -a \"{}\"\n",
+                subcommand.get_name(),
+                words.join(" ")
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{Args, completion_script};
+    use clap::{CommandFactory, ValueEnum};
+    use clap_complete::Shell;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    /// 命令行中所有可见的长选项和子命令，补全脚本中都应该出现
+    fn visible_words() -> Vec<String> {
+        let mut command = Args::command();
+        command.build();
+        let mut words = Vec::new();
+        for sub in command
+            .get_subcommands()
+            .filter(|sub| sub.get_name() != "help")
+        {
+            words.push(sub.get_name().to_string());
+            words.extend(
+                sub.get_arguments()
+                    .filter(|arg| !arg.is_hide_set())
+                    .filter_map(|arg| arg.get_long())
+                    .map(|long| format!("--{}", long)),
+            );
+        }
+        words.extend(
+            command
+                .get_arguments()
+                .filter(|arg| !arg.is_hide_set())
+                .filter_map(|arg| arg.get_long())
+                .map(|long| format!("--{}", long)),
+        );
+        words
+    }
+
+    #[test]
+    fn every_shell_lists_all_options_and_subcommands() {
+        let words = visible_words();
+        assert!(words.iter().any(|word| word == "gerrit"));
+        for shell in Shell::value_variants() {
+            let script = completion_script(*shell);
+            for word in &words {
+                // fish 把长选项写作 -l name
+                let fish = format!("-l {} ", word.trim_start_matches("--"));
+                assert!(
+                    script.contains(word.as_str()) || script.contains(&fish),
+                    "{:?} 的补全脚本中没有 {}",
+                    shell,
+                    word
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn extra_candidates_and_expression_names() {
+        let bash = completion_script(Shell::Bash);
+        assert!(
+            bash.contains("--width)\n                    COMPREPLY=($(compgen -W \"8 16 32 64\"")
+        );
+        assert!(bash.contains("compgen -W \"zh-CN en-US\""));
+        // --calc 之后补全函数名和常量名
+        let line_after = |script: &str, pattern: &str| {
+            let start = script
+                .find(pattern)
+                .unwrap_or_else(|| panic!("没有 {}", pattern));
+            script[start..]
+                .lines()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string()
+        };
+        assert!(line_after(&bash, "--calc)\n").contains(" popcount "));
+
+        let zsh = completion_script(Shell::Zsh);
+        assert!(zsh.starts_with("#compdef enjoy\n"));
+        assert!(zsh.contains(":BITS:(8 16 32 64)"));
+        assert!(zsh.contains("popcount"));
+
+        let fish = completion_script(Shell::Fish);
+        assert!(fish.contains("-l width -d"));
+        assert!(fish.contains("-a \"8\\t''\n16\\t''\n32\\t''\n64\\t''\""));
+        // fish 另外补全 enjoy calc 的表达式
+        let calc = fish
+            .lines()
+            .find(|line| line.contains("using_subcommand calc\" -f -a "))
+            .unwrap();
+        assert!(calc.contains(" popcount "), "{}", calc);
+
+        let powershell = completion_script(Shell::PowerShell);
+        assert!(powershell.contains("Register-ArgumentCompleter -Native -CommandName 'enjoy'"));
+    }
+
+    /// 用 shell 检查语法 (bash -n)，没有安装的 shell 跳过
+    #[test]
+    fn scripts_parse_in_their_shell() {
+        for (shell, program) in [
+            (Shell::Bash, "bash"),
+            (Shell::Zsh, "zsh"),
+            (Shell::Fish, "fish"),
+        ] {
+            let Ok(mut child) = Command::new(program)
+                .arg("-n")
+                .stdin(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+            else {
+                continue;
+            };
+            let script = completion_script(shell);
+            child
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(script.as_bytes())
+                .unwrap();
+            let output = child.wait_with_output().unwrap();
+            assert!(
+                output.status.success(),
+                "{}: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+}
@@ -0,0 +1,601 @@
+//! 用户配置文件 (enjoy config path 给出位置)，为常用的参数提供默认值，并定义自己的常量
+//! 文件是 TOML 的一个子集：注释、[section]、每行一个 key = value，值可以是
+//! 字符串、整数、true/false 或写在一行中的字符串数组：
+//!
+//! ```toml
+//! output = ["hex", "bin"]   # 也可以写作 "hex,bin"
+//! width = 32
+//! group_bits = 8
+//! color = false             # 等同于 --no-color，为 true 时仍然只在终端中输出颜色
+//! default_calc = "DDR_BASE + 0x1234"  # 不带参数运行 enjoy 时计算的表达式
+//!
+//! [constants]
+//! DDR_BASE = "0x80000000"   # 按数字字面量解析，可以写作 0x、0b、4k 等
+//! PAGE = 4096
+//!
+//! [gerrit]
+//! default_profile = "kernel"  # 没有给出 --profile 时使用的配置
+//!
+//! [gerrit.profiles.kernel]    # enjoy gerrit --profile kernel
+//! remote = "gerrit"
+//! branch = "lts/5.10"
+//! reviewers = ["alice", "bob"]
+//! topic_prefix = "kernel-"
+//! ```
+//!
+//! 命令行参数总是优先于配置文件，--no-config 时不读取配置文件
+
+use crate::expr::{check_variable_name, parse_number};
+use crate::format::Base;
+use crate::gerrit::{self, Profile, Profiles};
+use crate::i18n::tr;
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// 配置文件中的默认值，没有写出的项为 None
+#[derive(Debug, Default)]
+pub(crate) struct Config {
+    pub(crate) output: Option<Vec<Base>>,
+    pub(crate) width: Option<u32>,
+    pub(crate) group_bits: Option<usize>,
+    pub(crate) color: Option<bool>,
+    /// 没有给出表达式时计算的表达式
+    pub(crate) default_calc: Option<String>,
+    /// [constants] 中的常量，按文件中的顺序排列，在表达式中作为变量使用
+    pub(crate) constants: Vec<(String, i128)>,
+    /// [gerrit] 和 [gerrit.profiles.<name>] 中的 gerrit 配置
+    pub(crate) gerrit: Profiles,
+}
+
+/// 当前所在的节
+enum Section {
+    Top,
+    Constants,
+    Gerrit,
+    /// [gerrit.profiles.<name>]，为 gerrit.list 中的下标
+    Profile(usize),
+}
+
+/// 配置文件中的值
+enum Value {
+    String(String),
+    /// 整数保留原文，交给各个键自己解析
+    Integer(String),
+    Bool(bool),
+    Array(Vec<String>),
+}
+
+/// 配置文件的位置：Windows 上为 %APPDATA%\enjoy\config.toml，
+/// 其他系统为 $XDG_CONFIG_HOME/enjoy/config.toml，默认为 ~/.config/enjoy/config.toml
+pub(crate) fn config_path() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    if cfg!(windows) {
+        return non_empty("APPDATA")
+            .map(|dir| PathBuf::from(dir).join("enjoy").join("config.toml"));
+    }
+    let config = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("enjoy").join("config.toml"))
+}
+
+impl Config {
+    /// 读取配置文件，文件不存在时返回空的配置
+    /// 错误信息形如 文件:行号: 键: 原因
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => {
+                return Err(tr!(
+                    "无法读取 {}: {}",
+                    "cannot read {}: {}",
+                    path.display(),
+                    err
+                ));
+            }
+        };
+        Config::parse(&content)
+            .map_err(|(line, message)| format!("{}:{}: {}", path.display(), line, message))
+    }
+
+    /// 逐行解析，出错时返回行号（从 1 开始）和错误信息
+    fn parse(content: &str) -> Result<Self, (usize, String)> {
+        let mut config = Config::default();
+        let mut section = Section::Top;
+        let mut section_name = String::new();
+        let mut seen: Vec<String> = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let at = |message: String| (i + 1, message);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                let name = name
+                    .strip_suffix(']')
+                    .ok_or_else(|| {
+                        at(tr!(
+                            "节名缺少 ]: {}",
+                            "section header is missing ]: {}",
+                            line
+                        ))
+                    })?
+                    .trim();
+                section = config.section(name).map_err(at)?;
+                section_name = name.to_string();
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                at(tr!(
+                    "应写作 key = value: {}",
+                    "expected key = value: {}",
+                    line
+                ))
+            })?;
+            let key = unquote_key(key.trim());
+            let qualified = if section_name.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", section_name, key)
+            };
+            let keyed = |message: String| at(format!("{}: {}", qualified, message));
+            if seen.contains(&qualified) {
+                return Err(keyed(tr!("重复的键", "duplicate key")));
+            }
+            let value = parse_value(value.trim()).map_err(keyed)?;
+            match section {
+                Section::Top => config.set(key, value).map_err(keyed)?,
+                Section::Constants => {
+                    let value = constant(key, value).map_err(keyed)?;
+                    config.constants.push((key.to_string(), value));
+                }
+                Section::Gerrit => config.set_gerrit(key, value).map_err(keyed)?,
+                Section::Profile(index) => {
+                    set_profile(&mut config.gerrit.list[index], key, value).map_err(keyed)?
+                }
+            }
+            seen.push(qualified);
+        }
+        Ok(config)
+    }
+
+    /// 进入 [name] 节；[gerrit.profiles.<name>] 每次出现时定义一个新的配置
+    fn section(&mut self, name: &str) -> Result<Section, String> {
+        match name {
+            "constants" => return Ok(Section::Constants),
+            "gerrit" => return Ok(Section::Gerrit),
+            _ => {}
+        }
+        let Some(profile) = name.strip_prefix("gerrit.profiles.") else {
+            return Err(tr!(
+                "未知的节 [{}]，可选 [constants]、[gerrit] 和 [gerrit.profiles.<名称>]",
+                "unknown section [{}], choose [constants], [gerrit] or [gerrit.profiles.<NAME>]",
+                name
+            ));
+        };
+        let profile = unquote_key(profile);
+        let valid = !profile.is_empty()
+            && profile
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(tr!(
+                "配置名只能包含字母、数字、_ 和 -: {}",
+                "profile names may only contain letters, digits, _ and -: {}",
+                profile
+            ));
+        }
+        if self.gerrit.list.iter().any(|p| p.name == profile) {
+            return Err(tr!(
+                "重复定义的配置 {}",
+                "profile {} is defined twice",
+                profile
+            ));
+        }
+        self.gerrit.list.push(Profile {
+            name: profile.to_string(),
+            ..Profile::default()
+        });
+        Ok(Section::Profile(self.gerrit.list.len() - 1))
+    }
+
+    /// [gerrit] 中的键
+    fn set_gerrit(&mut self, key: &str, value: Value) -> Result<(), String> {
+        match key {
+            "default_profile" => self.gerrit.default = Some(string(value)?),
+            _ => {
+                return Err(tr!(
+                    "未知的键，可选 default_profile",
+                    "unknown key, choose default_profile"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 设置顶层的一个键，值的检查与对应的命令行参数相同
+    fn set(&mut self, key: &str, value: Value) -> Result<(), String> {
+        match key {
+            "output" => {
+                let names = match value {
+                    Value::Array(names) => names,
+                    Value::String(text) => text.split(',').map(str::to_string).collect(),
+                    _ => return Err(expected(tr!("字符串数组", "an array of strings"))),
+                };
+                let bases = names
+                    .iter()
+                    .map(|name| {
+                        Base::from_str(name.trim(), true).map_err(|_| {
+                            tr!(
+                                "未知的进制 {}，可选 dec hex bin oct",
+                                "unknown base {}, choose dec, hex, bin or oct",
+                                name.trim()
+                            )
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if bases.is_empty() {
+                    return Err(tr!("至少要给出一种进制", "at least one base is needed"));
+                }
+                self.output = Some(bases);
+            }
+            "width" => self.width = Some(crate::cli::parse_register_bits(&integer(value)?)?),
+            "group_bits" => self.group_bits = Some(crate::cli::parse_group_bits(&integer(value)?)?),
+            "color" => match value {
+                Value::Bool(color) => self.color = Some(color),
+                _ => return Err(expected("true / false".to_string())),
+            },
+            "default_calc" => {
+                let expression = string(value)?;
+                if expression.trim().is_empty() {
+                    return Err(tr!("表达式不能为空", "the expression must not be empty"));
+                }
+                self.default_calc = Some(expression);
+            }
+            _ => {
+                return Err(tr!(
+                    "未知的键，可选 output width group_bits color default_calc 和 [constants]",
+                    "unknown key, choose output, width, group_bits, color, default_calc or [constants]"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// [gerrit.profiles.<name>] 中的键，值的检查与对应的命令行参数相同
+fn set_profile(profile: &mut Profile, key: &str, value: Value) -> Result<(), String> {
+    match key {
+        "remote" => profile.remote = Some(gerrit::check_remote(&string(value)?)?),
+        "branch" => profile.branch = Some(gerrit::check_branch(&string(value)?)?),
+        "reviewers" => {
+            let users = match value {
+                Value::Array(users) => users,
+                Value::String(text) => text
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|user| !user.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                _ => return Err(expected(tr!("字符串数组", "an array of strings"))),
+            };
+            let users = users
+                .iter()
+                .map(|user| gerrit::check_reviewer(user))
+                .collect::<Result<_, _>>()?;
+            profile.reviewers = Some(users);
+        }
+        "topic_prefix" => profile.topic_prefix = Some(gerrit::check_topic(&string(value)?)?),
+        _ => {
+            return Err(tr!(
+                "未知的键，可选 remote branch reviewers topic_prefix",
+                "unknown key, choose remote, branch, reviewers or topic_prefix"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 字符串值
+fn string(value: Value) -> Result<String, String> {
+    match value {
+        Value::String(text) => Ok(text),
+        _ => Err(expected(tr!("字符串", "a string"))),
+    }
+}
+
+/// 值的类型不对时的错误信息
+fn expected(kind: String) -> String {
+    tr!("值应为{}", "expected {}", kind)
+}
+
+/// 整数值的原文，也接受写成字符串的整数
+fn integer(value: Value) -> Result<String, String> {
+    match value {
+        Value::Integer(text) | Value::String(text) => Ok(text),
+        _ => Err(expected(tr!("整数", "an integer"))),
+    }
+}
+
+/// [constants] 中的一项：名称不能与内置的常量、函数或 ans 重名，值按数字字面量解析
+fn constant(name: &str, value: Value) -> Result<i128, String> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(tr!(
+            "不是合法的标识符，只能包含字母、数字和下划线，且不能以数字开头",
+            "not a valid identifier: use only letters, digits and underscores, and do not start with a digit"
+        ));
+    }
+    check_variable_name(name).map_err(|e| e.to_string())?;
+    parse_number(&integer(value)?).map_err(|e| e.to_string())
+}
+
+/// 去掉 # 开始的注释，字符串中的 # 不算
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..i],
+            None => {}
+        }
+    }
+    line
+}
+
+/// 键可以加引号，如 "group_bits" = 8
+fn unquote_key(key: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|q| key.strip_prefix(q)?.strip_suffix(q))
+        .unwrap_or(key)
+}
+
+/// 解析一个值：字符串、整数、true/false 或字符串数组
+fn parse_value(text: &str) -> Result<Value, String> {
+    match text {
+        "" => Err(tr!("缺少值", "missing value")),
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        _ if text.starts_with('[') => {
+            let inner = text.strip_prefix('[').and_then(|t| t.strip_suffix(']'));
+            let inner = inner.ok_or_else(|| {
+                tr!(
+                    "数组缺少 ]，数组必须写在一行中",
+                    "array is missing ], arrays must be written on one line"
+                )
+            })?;
+            split_array(inner)?
+                .into_iter()
+                .map(|item| match parse_value(item)? {
+                    Value::String(text) => Ok(text),
+                    _ => Err(expected(tr!("字符串数组", "an array of strings"))),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array)
+        }
+        _ if text.starts_with('"') || text.starts_with('\'') => {
+            parse_string(text).map(Value::String)
+        }
+        _ => Ok(Value::Integer(text.to_string())),
+    }
+}
+
+/// 按字符串之外的逗号拆分数组的元素，允许末尾多一个逗号
+fn split_array(inner: &str) -> Result<Vec<&str>, String> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in inner.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == ',' => {
+                items.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            None => {}
+        }
+    }
+    let last = inner[start..].trim();
+    if !last.is_empty() {
+        items.push(last);
+    }
+    if items.iter().any(|item| item.is_empty()) {
+        return Err(tr!("数组中有空的元素", "array has an empty element"));
+    }
+    Ok(items)
+}
+
+/// 解析 "..." (支持 \" \\ \n \t 转义) 或 '...' (不转义) 字符串
+fn parse_string(text: &str) -> Result<String, String> {
+    let unterminated = || tr!("字符串没有结束: {}", "unterminated string: {}", text);
+    if let Some(literal) = text.strip_prefix('\'') {
+        return match literal.strip_suffix('\'') {
+            Some(inner) if !inner.contains('\'') => Ok(inner.to_string()),
+            _ => Err(unterminated()),
+        };
+    }
+    let mut result = String::new();
+    let mut chars = text[1..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if chars.as_str().is_empty() => return Ok(result),
+            '"' => {
+                return Err(tr!(
+                    "字符串后面有多余的内容: {}",
+                    "unexpected text after string: {}",
+                    text
+                ));
+            }
+            '\\' => match chars.next() {
+                Some('"') => result.push('"'),
+                Some('\\') => result.push('\\'),
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(other) => {
+                    return Err(tr!("不支持的转义 \\{}", "unsupported escape \\{}", other));
+                }
+                None => return Err(unterminated()),
+            },
+            c => result.push(c),
+        }
+    }
+    Err(unterminated())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 解析出错时的行号和错误信息
+    fn error(content: &str) -> (usize, String) {
+        Config::parse(content).unwrap_err()
+    }
+
+    #[test]
+    fn example_from_module_docs() {
+        let config = Config::parse(
+            r#"
+output = ["hex", "bin"]   # 也可以写作 "hex,bin"
+width = 32
+group_bits = 8
+color = false
+default_calc = "DDR_BASE + 0x1234"
+
+[constants]
+DDR_BASE = "0x80000000"
+PAGE = 4k
+
+[gerrit]
+default_profile = "kernel"
+
+[gerrit.profiles.kernel]
+remote = "gerrit"
+branch = "lts/5.10"
+reviewers = ["alice", "bob",]
+topic_prefix = "kernel-"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.output, Some(vec![Base::Hex, Base::Bin]));
+        assert_eq!(config.width, Some(32));
+        assert_eq!(config.group_bits, Some(8));
+        assert_eq!(config.color, Some(false));
+        assert_eq!(config.default_calc.as_deref(), Some("DDR_BASE + 0x1234"));
+        assert_eq!(
+            config.constants,
+            [
+                ("DDR_BASE".to_string(), 0x8000_0000),
+                ("PAGE".to_string(), 4096)
+            ]
+        );
+        assert_eq!(config.gerrit.default.as_deref(), Some("kernel"));
+        let profile = &config.gerrit.list[0];
+        assert_eq!(profile.name, "kernel");
+        assert_eq!(profile.remote.as_deref(), Some("gerrit"));
+        assert_eq!(profile.branch.as_deref(), Some("lts/5.10"));
+        assert_eq!(profile.reviewers, Some(vec!["alice".into(), "bob".into()]));
+        assert_eq!(profile.topic_prefix.as_deref(), Some("kernel-"));
+    }
+
+    #[test]
+    fn quoted_keys_and_sections() {
+        let config = Config::parse(
+            "\"group_bits\" = 4\n'width' = 16\n[gerrit.profiles.\"my-team\"]\nremote = 'origin'\n",
+        )
+        .unwrap();
+        assert_eq!(config.group_bits, Some(4));
+        assert_eq!(config.width, Some(16));
+        assert_eq!(config.gerrit.list[0].name, "my-team");
+        // 带引号和不带引号的同一个键算重复
+        assert_eq!(error("width = 8\n\"width\" = 16\n").0, 2);
+    }
+
+    #[test]
+    fn strings_and_escapes() {
+        let string = |text: &str| parse_string(text);
+        assert_eq!(string(r#""a\"b\\c\nd\te""#).unwrap(), "a\"b\\c\nd\te");
+        // 单引号字符串不转义
+        assert_eq!(string(r"'C:\path\n'").unwrap(), r"C:\path\n");
+        assert!(string(r#""\x""#).is_err());
+        assert!(string(r#""abc"#).is_err());
+        assert!(string(r#""abc\""#).is_err());
+        assert!(string(r#""a" b"#).is_err());
+        assert!(string("'a'b'").is_err());
+    }
+
+    #[test]
+    fn comments_outside_strings() {
+        assert_eq!(strip_comment("width = 8 # 注释"), "width = 8 ");
+        assert_eq!(
+            strip_comment(r##"topic = "a#b" # c"##),
+            r##"topic = "a#b" "##
+        );
+        assert_eq!(
+            strip_comment(r##"topic = "a\"#b""##),
+            r##"topic = "a\"#b""##
+        );
+        assert_eq!(strip_comment("topic = 'a#b'"), "topic = 'a#b'");
+        assert_eq!(strip_comment("# 整行注释"), "");
+    }
+
+    #[test]
+    fn arrays() {
+        let array = |text: &str| match parse_value(text) {
+            Ok(Value::Array(items)) => Ok(items),
+            Ok(_) => panic!("{} 不是数组", text),
+            Err(err) => Err(err),
+        };
+        assert_eq!(
+            array(r#"["a", 'b,c', "d\"e"]"#).unwrap(),
+            ["a", "b,c", "d\"e"]
+        );
+        assert_eq!(array("[]").unwrap(), Vec::<String>::new());
+        assert_eq!(array(r#"["a",]"#).unwrap(), ["a"]);
+        assert!(array(r#"["a",,"b"]"#).is_err());
+        assert!(array(r#"["a", 1]"#).is_err());
+        assert!(array(r#"["a""#).is_err());
+        // output 也接受逗号分隔的字符串
+        let config = Config::parse("output = \"dec, oct\"").unwrap();
+        assert_eq!(config.output, Some(vec![Base::Dec, Base::Oct]));
+    }
+
+    #[test]
+    fn errors_report_the_line() {
+        assert_eq!(error("\n\nwidth = 12\n").0, 3);
+        assert_eq!(error("[constants\n").0, 1);
+        assert_eq!(error("width\n").0, 1);
+        assert_eq!(error("width =\n").0, 1);
+        assert_eq!(error("[unknown]\n").0, 1);
+        assert_eq!(error("color = 1\n").0, 1);
+        assert_eq!(error("output = []\n").0, 1);
+        assert_eq!(error("default_calc = 1\n").0, 1);
+        assert_eq!(error("default_calc = \" \"\n").0, 1);
+        // 常量不能与内置的名称重名
+        assert_eq!(error("[constants]\nans = 1\n").0, 2);
+        assert_eq!(error("[constants]\n1abc = 1\n").0, 2);
+        assert_eq!(error("[gerrit.profiles.a]\n[gerrit.profiles.a]\n").0, 2);
+        let (line, message) = error("[gerrit.profiles.a]\nremote = \"x\"\nremote = \"y\"\n");
+        assert_eq!(line, 3);
+        assert!(
+            message.starts_with("gerrit.profiles.a.remote: "),
+            "{}",
+            message
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! CRC 校验值计算，逐位实现，数据量很小，不需要查表
+
+/// CRC-32 (IEEE 802.3)：多项式 0x04C11DB7（按位反转为 0xEDB88320），初值与结果异或值均为 0xFFFFFFFF
+/// "123456789" 的校验值为 0xCBF43926
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// CRC-16/CCITT-FALSE：多项式 0x1021，初值 0xFFFF，不反转，结果不异或
+/// "123456789" 的校验值为 0x29B1
+pub fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc = u16::MAX;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_values() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"a"), 0xE8B7_BE43);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+
+    #[test]
+    fn crc16_check_values() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        // 没有数据时为初值
+        assert_eq!(crc16(b""), 0xFFFF);
+        assert_eq!(crc16(b"A"), 0xB915);
+    }
+}
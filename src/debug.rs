@@ -0,0 +1,33 @@
+//! 调试输出 (-d、-dd、-ddd)，全部写到标准错误，不会混进 --quiet、--json 等的标准输出
+//! -d 列出拆分出的表达式元素，gerrit 给出执行的命令和环境；-dd 另外跟踪求值的每一步；
+//! -ddd 另外给出耗时和原始的命令行参数
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 列出表达式元素、gerrit 的命令和环境
+pub(crate) const TOKENS: u8 = 1;
+/// 跟踪求值：每次运算的操作数和结果，以及进入和离开括号
+pub(crate) const TRACE: u8 = 2;
+/// 耗时和原始的命令行参数
+pub(crate) const TIMING: u8 = 3;
+
+/// -d 出现的次数
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+pub(crate) fn set_level(level: u8) {
+    LEVEL.store(level, Ordering::Relaxed);
+}
+
+pub(crate) fn enabled(level: u8) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level
+}
+
+/// 调试级别不低于 level 时，按当前语言向标准错误输出一行带 [debug] 前缀的信息，参数与 tr! 相同
+macro_rules! debug {
+    ($level:expr, $zh:literal, $en:literal $(, $arg:expr)* $(,)?) => {
+        if $crate::debug::enabled($level) {
+            eprintln!("[debug] {}", $crate::i18n::tr!($zh, $en $(, $arg)*));
+        }
+    };
+}
+pub(crate) use debug;
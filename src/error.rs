@@ -0,0 +1,263 @@
+//! 命令行和公开 API 的错误类型，错误的种类决定进程的退出状态
+
+use crate::i18n::{Message, tr};
+use std::fmt;
+use std::io;
+
+/// 解析或计算表达式、推送到 Gerrit 时的错误
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EnjoyError {
+    /// 表达式无法解析：无效的字面量、缺少操作数或操作符、不成对的括号、位置不对的符号等
+    /// token 为出错的元素在表达式中的位置（从 0 开始），无法确定时为 None
+    Parse {
+        message: Message,
+        token: Option<usize>,
+    },
+    /// 表达式可以解析，但计算时出错
+    Eval {
+        error: EvalError,
+        token: Option<usize>,
+    },
+    /// 推送到 Gerrit 失败
+    Gerrit(GerritError),
+}
+
+/// 计算表达式时的错误
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum EvalError {
+    DivisionByZero,
+    /// 结果超出当前模式的范围，信息中带有出错的运算
+    Overflow(Message),
+    /// 其他错误，如未赋值的变量、超出范围的函数参数
+    Other(Message),
+}
+
+/// 执行 git push 时的错误
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GerritError {
+    /// 在 PATH 中找不到 git
+    GitNotFound,
+    /// 无法启动或等待 git
+    Io(io::Error),
+    /// git 以非零状态退出，被信号终止时没有退出码
+    Status(Option<i32>),
+    /// 远程仓库不存在，remotes 为已有的远程仓库
+    UnknownRemote { name: String, remotes: Vec<String> },
+    /// 标准输入不是终端，无法确认推送，需要 --yes
+    NotInteractive,
+    /// 用户没有确认推送
+    Cancelled,
+    /// HEAD 分离，无法确定目标分支，需要 --branch
+    DetachedHead,
+    /// git config 中 enjoy.gerrit.* 的值不合法
+    Config { key: String, message: String },
+    /// HEAD 的提交信息中没有 Change-Id，Gerrit 会拒绝推送
+    /// hook_command 为安装 commit-msg 钩子的命令，installed 表示刚刚安装了钩子
+    MissingChangeId {
+        hook_command: Option<String>,
+        installed: bool,
+    },
+    /// 无法安装 commit-msg 钩子
+    HookInstall(String),
+    /// HEAD 的提交信息不符合要求，每项为一处问题
+    Lint(Vec<String>),
+    /// enjoy gerrit list 查询失败，带有原因和解决办法
+    Query(String),
+    /// 配置文件有错误，或者 --profile、default_profile 给出的配置不存在
+    Profile(String),
+    /// 本地没有领先 <remote>/<branch> 的提交
+    NothingToPush { remote: String, branch: String },
+}
+
+impl EnjoyError {
+    /// 表达式拆分为元素时的错误，此时还没有元素可以指出
+    pub(crate) fn parse(message: Message) -> Self {
+        EnjoyError::Parse {
+            message,
+            token: None,
+        }
+    }
+
+    /// 出错的元素在表达式中的位置（从 0 开始），无法确定时为 None
+    pub fn token(&self) -> Option<usize> {
+        match self {
+            EnjoyError::Parse { token, .. } | EnjoyError::Eval { token, .. } => *token,
+            EnjoyError::Gerrit(_) => None,
+        }
+    }
+
+    /// 进程的退出状态：解析错误为 2，计算错误为 3；推送到 Gerrit 时使用与表达式不同的退出状态：
+    /// - git 没有执行推送时为 4：用户取消推送、远程仓库不存在、HEAD 分离、没有要推送的提交、没有 Change-Id、
+    ///   提交信息不符合要求、查询改动失败、配置不存在、git config 中的值不合法或无法确认推送等
+    /// - git 以非零状态退出时为 git 的退出码，git 被信号终止时为 125
+    /// - 找不到、无法启动或等待 git 时为 127
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            EnjoyError::Parse { .. } => 2,
+            EnjoyError::Eval { .. } => 3,
+            EnjoyError::Gerrit(error) => match error {
+                GerritError::Status(Some(code)) => *code,
+                GerritError::Status(None) => 125,
+                GerritError::GitNotFound | GerritError::Io(_) => 127,
+                _ => 4,
+            },
+        }
+    }
+
+    /// 错误信息，不含位置
+    pub(crate) fn message(&self) -> String {
+        match self {
+            EnjoyError::Parse { message, .. } => message.to_string(),
+            EnjoyError::Eval { error, .. } => error.to_string(),
+            EnjoyError::Gerrit(error) => error.to_string(),
+        }
+    }
+
+    /// 生成错误提示：指出出错的元素，并回显表达式，在出错的元素下方标出 ^
+    pub(crate) fn render(&self, sources: &[String]) -> String {
+        let Some(index) = self.token().filter(|token| *token < sources.len()) else {
+            return self.message();
+        };
+        let column: usize = sources[..index]
+            .iter()
+            .map(|source| source.chars().count() + 1)
+            .sum();
+        format!(
+            "{}\n  {}\n  {}{}",
+            tr!(
+                "第 {} 个元素 ('{}'): {}",
+                "token {} ('{}'): {}",
+                index + 1,
+                sources[index],
+                self.message()
+            ),
+            sources.join(" "),
+            " ".repeat(column),
+            "^".repeat(sources[index].chars().count().max(1))
+        )
+    }
+}
+
+impl fmt::Display for EnjoyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token() {
+            Some(token) => f.write_str(&tr!(
+                "第 {} 个元素: {}",
+                "token {}: {}",
+                token + 1,
+                self.message()
+            )),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match *self {
+            EvalError::DivisionByZero => Message::DivisionByZero.to_string(),
+            EvalError::Overflow(ref message) | EvalError::Other(ref message) => message.to_string(),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl fmt::Display for GerritError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            GerritError::GitNotFound => tr!(
+                "找不到 git 命令，请安装 git 并确认它在 PATH 中",
+                "git not found, install git and check that it is in PATH"
+            ),
+            GerritError::Io(err) => tr!("无法执行 git 命令: {}", "cannot run git: {}", err),
+            GerritError::Status(Some(code)) => tr!(
+                "git 命令执行失败，退出码 {}",
+                "git failed with exit code {}",
+                code
+            ),
+            GerritError::Status(None) => tr!("git 命令被信号终止", "git was killed by a signal"),
+            GerritError::UnknownRemote { name, remotes } if remotes.is_empty() => tr!(
+                "远程仓库 {} 不存在，当前仓库没有任何远程仓库",
+                "remote {} does not exist, this repository has no remotes",
+                name
+            ),
+            GerritError::UnknownRemote { name, remotes } => tr!(
+                "远程仓库 {} 不存在，可选: {}",
+                "remote {} does not exist, available: {}",
+                name,
+                remotes.join(", ")
+            ),
+            GerritError::NotInteractive => tr!(
+                "标准输入不是终端，无法确认推送，请加上 --yes",
+                "stdin is not a terminal, cannot confirm the push; use --yes"
+            ),
+            GerritError::Cancelled => tr!("已取消推送", "push cancelled"),
+            GerritError::DetachedHead => tr!(
+                "HEAD 处于分离状态，无法确定目标分支，请用 --branch 指定",
+                "HEAD is detached, cannot determine the target branch; pass --branch"
+            ),
+            GerritError::Config { key, message } => format!("git config {}: {}", key, message),
+            GerritError::MissingChangeId {
+                installed: true, ..
+            } => tr!(
+                "已安装 commit-msg 钩子，请执行 git commit --amend --no-edit 为 HEAD 生成 Change-Id 后再推送",
+                "installed the commit-msg hook; run git commit --amend --no-edit to add a Change-Id to HEAD, then push again"
+            ),
+            GerritError::MissingChangeId {
+                hook_command: Some(command),
+                ..
+            } => tr!(
+                "HEAD 的提交信息中没有 Change-Id，Gerrit 会拒绝推送；请安装 commit-msg 钩子 ({}) 或加上 --install-hook，\
+                 再执行 git commit --amend --no-edit；--force 跳过检查",
+                "HEAD's commit message has no Change-Id and Gerrit will reject it; install the commit-msg hook ({}) \
+                 or pass --install-hook, then run git commit --amend --no-edit; --force skips this check",
+                command
+            ),
+            GerritError::MissingChangeId { .. } => tr!(
+                "HEAD 的提交信息中没有 Change-Id，Gerrit 会拒绝推送；请安装 Gerrit 的 commit-msg 钩子，\
+                 再执行 git commit --amend --no-edit；--force 跳过检查",
+                "HEAD's commit message has no Change-Id and Gerrit will reject it; install Gerrit's commit-msg hook, \
+                 then run git commit --amend --no-edit; --force skips this check"
+            ),
+            GerritError::Lint(violations) => {
+                let mut text = tr!(
+                    "HEAD 的提交信息不符合要求 (--no-verify 跳过检查):",
+                    "HEAD's commit message has problems (--no-verify skips this check):"
+                );
+                for violation in violations {
+                    text.push_str("\n  - ");
+                    text.push_str(violation);
+                }
+                text
+            }
+            GerritError::Query(message) | GerritError::Profile(message) => message.clone(),
+            GerritError::NothingToPush { remote, branch } => tr!(
+                "没有要推送的提交: HEAD 没有领先 {}/{}",
+                "nothing to push: HEAD is not ahead of {}/{}",
+                remote,
+                branch
+            ),
+            GerritError::HookInstall(reason) => tr!(
+                "无法安装 commit-msg 钩子: {}",
+                "cannot install the commit-msg hook: {}",
+                reason
+            ),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl std::error::Error for EnjoyError {}
+
+impl std::error::Error for EvalError {}
+
+impl std::error::Error for GerritError {}
+
+impl From<GerritError> for EnjoyError {
+    fn from(error: GerritError) -> Self {
+        EnjoyError::Gerrit(error)
+    }
+}
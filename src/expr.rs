@@ -0,0 +1,4673 @@
+//! 表达式：数字字面量的解析、拆分为元素和求值，以及内置的常量和函数
+
+use crate::crc;
+use crate::debug::{self, debug};
+use crate::error::{EnjoyError, EvalError};
+use crate::format::human_duration;
+use crate::i18n::{Message, tr};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::IntErrorKind;
+use std::path::Path;
+
+/// 自定义数值解析函数（支持十进制、十六进制、二进制、八进制，均可带负号，如 -0x80）
+/// 八进制必须写作 0o755，以 0 开头的 0755 仍按十进制解析
+/// 数字之间可以用 _ 分隔，如 0xFFFF_FFFF、1_000_000，但 _ 不能出现在数字的开头或结尾
+/// 十进制数可以带容量后缀（不区分大小写），按 2 的幂计算，如 4k = 4096、16M = 16 x 2^20；
+/// 十六进制数中 E 等字母本身就是数字，为避免歧义，后缀只能用于十进制数
+/// 也支持汇编和数据手册中的写法：0FFh 和 $FF 为十六进制，1010b 为二进制；
+/// b 后缀只有在其余数字全是 0 和 1 时才按二进制解析，否则报错（没有十进制的 b 后缀）；
+/// h 后缀的数也可以以字母开头（如 FFh）；在表达式中这样的名字先按变量查找，没有同名变量时才是十六进制数，
+/// 因此 each = 1 之后 each 是变量，未赋值的 each 是 0xEAC
+/// 为了能直接粘贴 C 代码中的常量，末尾的 U、L、UL、LL、ULL 等整数后缀（不区分大小写）会被忽略，
+/// 十进制数还可以用 , 作千位分隔符，如 1,048,576（除第一组外每组必须是 3 位数字）
+/// 十进制数可以写作结果为整数的科学计数法，如 1e6、25E3、1.5e1；在进制前缀和后缀之后判断，
+/// 因此 0x1e6 和 1e6h 仍是十六进制数，单独的 1e 仍是容量后缀 E
+/// 任意进制写作 <进制>#<数字>，如 36#Z1G、32#7V，进制为 2 到 36，数字 0-9 a-z 不区分大小写，
+/// 这种写法不再识别其他前缀和后缀（U、L 等可能也是数字），可以用 _ 分隔数字
+/// 解析结果为 i128，最多可写 128 位的字面量；是否超出当前模式的范围由求值时检查
+pub(crate) fn parse_number(s: &str) -> Result<i128, Message> {
+    let s = s.trim();
+    let (sign, rest) = match s.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", s),
+    };
+    if let Some((radix, digits)) = rest.split_once('#') {
+        return parse_radix_literal(sign, radix, digits, s);
+    }
+    let rest = strip_c_suffix(rest);
+    // 只有前缀没有数字：0b 不按 b 后缀当作二进制的 0
+    if let Some(prefix) = ["0x", "0b", "0o"]
+        .into_iter()
+        .find(|prefix| rest == *prefix)
+    {
+        return Err(Message::PrefixWithoutDigits {
+            prefix,
+            literal: s.to_string(),
+        });
+    }
+    let (radix, digits) = if let Some(hex) = rest.strip_prefix("0x") {
+        (16, hex)
+    } else if let Some(hex) = rest.strip_prefix('$') {
+        (16, hex)
+    } else if let Some(hex) = strip_radix_suffix(rest, 'h', 16) {
+        (16, hex)
+    } else if let Some(bin) = strip_radix_suffix(rest, 'b', 2) {
+        (2, bin)
+    } else if let Some(digits) = strip_radix_suffix(rest, 'b', 10) {
+        return Err(Message::BinarySuffix {
+            literal: s.to_string(),
+            digits: digits.to_uppercase(),
+        });
+    } else if let Some(bin) = rest.strip_prefix("0b") {
+        (2, bin)
+    } else if let Some(oct) = rest.strip_prefix("0o") {
+        (8, oct)
+    } else {
+        (10, rest)
+    };
+    // 频率后缀按 10 的幂计算，不能再与容量后缀一起使用
+    let (digits, scale) = match strip_frequency_suffix(digits) {
+        Some((digits, scale)) if radix == 10 => (digits, scale),
+        _ => (digits, 1),
+    };
+    let (digits, shift) = match digits.chars().last() {
+        _ if scale != 1 => (digits, 0),
+        Some(c) if radix == 10 && c.is_ascii_alphabetic() => {
+            let shift = size_suffix_shift(c).ok_or_else(|| Message::UnknownSizeSuffix {
+                suffix: c,
+                literal: s.to_string(),
+            })?;
+            (&digits[..digits.len() - 1], shift)
+        }
+        Some(c) if !c.is_digit(radix) && size_suffix_shift(c).is_some() => {
+            return Err(Message::SizeSuffixNotDecimal {
+                literal: s.to_string(),
+            });
+        }
+        _ => (digits, 0),
+    };
+    let digits = if digits.contains(',') {
+        if radix != 10 || !is_thousands_grouped(digits) {
+            return Err(Message::ThousandsSeparator {
+                literal: s.to_string(),
+            });
+        }
+        digits.replace(',', "")
+    } else {
+        digits.to_string()
+    };
+
+    if digits.starts_with('_') || digits.ends_with('_') {
+        return Err(Message::SeparatorAtEdge {
+            literal: s.to_string(),
+        });
+    }
+    // 符号和数字一起交给 from_str_radix，这样 -0x8000000000000000 也能解析
+    let digits = digits.replace('_', "");
+    let value = if radix == 10 && digits.contains(['e', 'E']) {
+        parse_scientific(&format!("{}{}", sign, digits), s)?
+    } else {
+        i128::from_str_radix(&format!("{}{}", sign, digits), radix).map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => Message::NumberTooLarge {
+                literal: s.to_string(),
+            },
+            _ => Message::InvalidNumber {
+                literal: s.to_string(),
+            },
+        })?
+    };
+    value
+        .checked_mul(1 << shift)
+        .and_then(|value| value.checked_mul(scale))
+        .ok_or_else(|| Message::NumberTooLarge {
+            literal: s.to_string(),
+        })
+}
+
+/// 解析 <进制>#<数字> 形式的字面量，sign 为 "-" 或空
+fn parse_radix_literal(
+    sign: &str,
+    radix: &str,
+    digits: &str,
+    literal: &str,
+) -> Result<i128, Message> {
+    let radix = Some(radix)
+        .filter(|radix| radix.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|radix| radix.parse::<u32>().ok())
+        .filter(|radix| (2..=36).contains(radix))
+        .ok_or_else(|| Message::InvalidRadix {
+            radix: radix.to_string(),
+            literal: literal.to_string(),
+        })?;
+    if digits.is_empty() {
+        return Err(Message::InvalidNumber {
+            literal: literal.to_string(),
+        });
+    }
+    if digits.starts_with('_') || digits.ends_with('_') {
+        return Err(Message::SeparatorAtEdge {
+            literal: literal.to_string(),
+        });
+    }
+    if let Some(digit) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+        return Err(Message::InvalidRadixDigit {
+            digit,
+            radix,
+            literal: literal.to_string(),
+        });
+    }
+    i128::from_str_radix(&format!("{}{}", sign, digits.replace('_', "")), radix).map_err(|_| {
+        Message::NumberTooLarge {
+            literal: literal.to_string(),
+        }
+    })
+}
+
+/// 科学计数法的整数，如 1e6、-25E+3、1.5e1：尾数可以是小数，指数不能为负，
+/// 小数部分（去掉末尾的 0）的位数不能多于指数，这样结果总是精确的整数；
+/// 结果必须在 i64 的范围内 (最大为 9.223372036854775807e18)，更大的数请用十六进制或 ** 写出
+fn parse_scientific(digits: &str, literal: &str) -> Result<i128, Message> {
+    let scientific_range = || Message::ScientificRange {
+        literal: literal.to_string(),
+    };
+    let invalid = || Message::InvalidNumber {
+        literal: literal.to_string(),
+    };
+    let (mantissa, exponent) = digits.split_once(['e', 'E']).ok_or_else(invalid)?;
+    if exponent.starts_with('-') {
+        return Err(Message::ScientificNegativeExponent {
+            literal: literal.to_string(),
+        });
+    }
+    let exponent = exponent.strip_prefix('+').unwrap_or(exponent);
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let fraction = fraction.trim_end_matches('0');
+    let is_digits = |text: &str| text.chars().all(|c| c.is_ascii_digit());
+    let unsigned = integer.strip_prefix('-').unwrap_or(integer);
+    if unsigned.is_empty()
+        || !is_digits(unsigned)
+        || !is_digits(fraction)
+        || exponent.is_empty()
+        || !is_digits(exponent)
+    {
+        return Err(invalid());
+    }
+    // 指数太大时先看尾数是否为 0：0e100 仍然是 0
+    let value: i128 = format!("{}{}", integer, fraction)
+        .parse()
+        .map_err(|_| scientific_range())?;
+    if value == 0 {
+        return Ok(0);
+    }
+    let exponent: u32 = exponent.parse().map_err(|_| scientific_range())?;
+    let scale = exponent.checked_sub(fraction.len() as u32).ok_or_else(|| {
+        Message::ScientificNotInteger {
+            literal: literal.to_string(),
+        }
+    })?;
+    let value = 10i128
+        .checked_pow(scale)
+        .and_then(|power| value.checked_mul(power))
+        .ok_or_else(scientific_range)?;
+    if i64::try_from(value).is_err() {
+        return Err(scientific_range());
+    }
+    Ok(value)
+}
+
+/// 十六进制和二进制字面量按书写的位数所占的位宽（每个十六进制数字 4 位，含前导零，不含 _），
+/// 如 0xFFFC 为 16 位、0b101 为 3 位；其他字面量返回 None
+fn literal_bits(s: &str) -> Option<u32> {
+    let rest = strip_c_suffix(s.trim().trim_start_matches('-'));
+    let (bits_per_digit, digits) = if let Some(hex) = rest.strip_prefix("0x") {
+        (4, hex)
+    } else if let Some(hex) = rest.strip_prefix('$') {
+        (4, hex)
+    } else if let Some(hex) = strip_radix_suffix(rest, 'h', 16) {
+        (4, hex)
+    } else if let Some(bin) = strip_radix_suffix(rest, 'b', 2) {
+        (1, bin)
+    } else if let Some(bin) = rest.strip_prefix("0b") {
+        (1, bin)
+    } else {
+        return None;
+    };
+    Some(bits_per_digit * digits.chars().filter(|c| *c != '_').count() as u32)
+}
+
+/// 把 value 的低 width 位按补码理解，第 width - 1 位为符号位 (width 为 1..=64)
+/// sext[] 和 --in-signed 共用
+pub(crate) fn sign_extend(value: i128, width: u32) -> i128 {
+    let low = value as u128 & (u128::MAX >> (128 - width));
+    let sign = 1u128 << (width - 1);
+    low as i128 - ((low & sign) << 1) as i128
+}
+
+/// --in-signed：位宽正好为 bits 的十六进制和二进制字面量按补码理解，如 16 位时 0xFFFC 为 -4
+/// 位宽较窄的字面量仍为正数，超出 bits 位的字面量是错误；负号写在字面量前面时对扩展后的值取负
+pub(crate) fn sign_extend_literals(
+    tokens: &mut [ExprToken],
+    sources: &[String],
+    bits: Option<u32>,
+) -> Result<(), EnjoyError> {
+    let Some(bits) = bits else {
+        return Ok(());
+    };
+    for (i, (token, source)) in tokens.iter_mut().zip(sources).enumerate() {
+        let ExprToken::Number(value) = token else {
+            continue;
+        };
+        match literal_bits(source) {
+            Some(width) if width > bits => {
+                return Err(EnjoyError::Parse {
+                    message: Message::LiteralTooWide {
+                        literal: source.clone(),
+                        width,
+                        bits,
+                    },
+                    token: Some(i),
+                });
+            }
+            Some(width) if width == bits => {
+                let extended = sign_extend(value.unsigned_abs() as i128, bits);
+                *value = if *value < 0 { -extended } else { extended };
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// C 语言的整数后缀，较长的排在前面
+const C_INTEGER_SUFFIXES: &[&str] = &["ull", "llu", "ul", "lu", "ll", "u", "l"];
+
+/// 去掉末尾的 C 语言整数后缀（不区分大小写），如 0x00200000UL、4096U
+/// 后缀前面必须是数字，只能去掉一个后缀，因此 0x10UU 仍然是无效的数字
+fn strip_c_suffix(s: &str) -> &str {
+    let lower = s.to_ascii_lowercase();
+    C_INTEGER_SUFFIXES
+        .iter()
+        .find(|suffix| lower.ends_with(*suffix))
+        .map(|suffix| &s[..s.len() - suffix.len()])
+        .filter(|digits| digits.ends_with(|c: char| c.is_ascii_hexdigit()))
+        .unwrap_or(s)
+}
+
+/// 按千位分组的十进制数字，如 1,048,576：第一组 1 到 3 位，其余每组 3 位
+fn is_thousands_grouped(digits: &str) -> bool {
+    let mut groups = digits.split(',');
+    let first = groups.next().unwrap_or_default();
+    (1..=3).contains(&first.len())
+        && first.chars().all(|c| c.is_ascii_digit())
+        && groups.all(|group| group.len() == 3 && group.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// 去掉汇编风格的进制后缀（不区分大小写），如 0FFh、1010b，其余部分必须都是该进制的数字
+fn strip_radix_suffix(s: &str, suffix: char, radix: u32) -> Option<&str> {
+    let digits = s
+        .strip_suffix(suffix)
+        .or_else(|| s.strip_suffix(suffix.to_ascii_uppercase()))?;
+    let valid = digits.starts_with(|c: char| c.is_digit(radix))
+        && digits.chars().all(|c| c.is_digit(radix) || c == '_');
+    valid.then_some(digits)
+}
+
+/// 去掉频率后缀 Hz kHz MHz GHz（不区分大小写，m 也表示兆），返回数字部分和倍数
+/// 频率按 10 的幂计算，与按 2 的幂计算的容量后缀（72M 为 72 x 2^20）区分开: 72MHz 为 72000000
+fn strip_frequency_suffix(digits: &str) -> Option<(&str, i128)> {
+    let stem = digits.get(..digits.len().checked_sub(2)?)?;
+    if !digits[stem.len()..].eq_ignore_ascii_case("hz") {
+        return None;
+    }
+    let scale = match stem.chars().last().map(|c| c.to_ascii_lowercase()) {
+        Some('k') => 1_000,
+        Some('m') => 1_000_000,
+        Some('g') => 1_000_000_000,
+        _ => return Some((stem, 1)),
+    };
+    Some((&stem[..stem.len() - 1], scale))
+}
+
+/// 时间单位的后缀及其纳秒数，写在十进制数后面: 2s、350ms、1.5us
+/// 小时写作 hr，因为 10h 是汇编风格的十六进制数；较长的后缀在前，避免 ms 被当作 s
+const DURATION_UNITS: [(&str, i128); 6] = [
+    ("min", 60_000_000_000),
+    ("hr", 3_600_000_000_000),
+    ("ns", 1),
+    ("us", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+];
+
+/// 解析带时间单位的十进制数，返回纳秒数；不是这种写法时返回 None
+/// 数字部分可以是小数 (1.5us)，但必须能精确换算为整数纳秒
+fn parse_duration(s: &str) -> Option<Result<i128, Message>> {
+    let s = s.trim();
+    let (number, scale) = DURATION_UNITS
+        .iter()
+        .find_map(|(suffix, scale)| Some((s.strip_suffix(suffix)?, *scale)))?;
+    let is_decimal = number.starts_with(|c: char| c.is_ascii_digit())
+        && number
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '_' || c == '.');
+    if !is_decimal {
+        return None;
+    }
+    let number = number.replace('_', "");
+    let (integer, fraction) = number.split_once('.').unwrap_or((&number, ""));
+    let too_large = || Message::NumberTooLarge {
+        literal: s.to_string(),
+    };
+    let invalid = || Message::InvalidNumber {
+        literal: s.to_string(),
+    };
+    let parse = |digits: &str| -> Result<i128, Message> {
+        if digits.is_empty() {
+            return Ok(0);
+        }
+        digits.parse::<i128>().map_err(|e| match e.kind() {
+            IntErrorKind::PosOverflow => too_large(),
+            _ => invalid(),
+        })
+    };
+    let result = (|| {
+        if fraction.contains('.') || (integer.is_empty() && fraction.is_empty()) {
+            return Err(invalid());
+        }
+        let fraction = fraction.trim_end_matches('0');
+        let denominator = u32::try_from(fraction.len())
+            .ok()
+            .and_then(|digits| 10i128.checked_pow(digits))
+            .ok_or_else(|| Message::DurationTooPrecise {
+                literal: s.to_string(),
+            })?;
+        let fraction = parse(fraction)?.checked_mul(scale).ok_or_else(too_large)?;
+        if fraction % denominator != 0 {
+            return Err(Message::DurationTooPrecise {
+                literal: s.to_string(),
+            });
+        }
+        parse(integer)?
+            .checked_mul(scale)
+            .and_then(|nanos| nanos.checked_add(fraction / denominator))
+            .ok_or_else(too_large)
+    })();
+    Some(result)
+}
+
+/// 容量后缀对应的移位量：k = 2^10、M = 2^20 …… E = 2^60
+fn size_suffix_shift(c: char) -> Option<u32> {
+    match c.to_ascii_lowercase() {
+        'k' => Some(10),
+        'm' => Some(20),
+        'g' => Some(30),
+        't' => Some(40),
+        'p' => Some(50),
+        'e' => Some(60),
+        _ => None,
+    }
+}
+
+/// 解析小数字面量（如 3.3、-0.5），供浮点模式使用，同样允许 _ 分隔数字
+fn parse_float(s: &str) -> Result<f64, Message> {
+    let s = s.trim();
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    // 只接受十进制小数，避免把 inf、nan、1e5 之类的写法也当成数字
+    if !digits.contains('.')
+        || !digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == '_')
+    {
+        return Err(Message::InvalidDecimal {
+            literal: s.to_string(),
+        });
+    }
+    if digits.starts_with('_') || digits.ends_with('_') {
+        return Err(Message::SeparatorAtEdge {
+            literal: s.to_string(),
+        });
+    }
+    s.replace('_', "")
+        .parse()
+        .map_err(|_| Message::InvalidDecimal {
+            literal: s.to_string(),
+        })
+}
+
+/// 字符字面量最多包含的字符数，拼接结果不超过 64 位
+const MAX_CHAR_LITERAL_LEN: usize = 8;
+
+/// 解析单引号括起的 ASCII 字符字面量（如 'A'、'\n'），结果为字符的编码
+/// 多个字符按大端序拼接，如 'ABCD' 为 0x41424344，便于查看 FourCC 之类的魔数
+fn parse_char_literal(s: &str) -> Result<i128, Message> {
+    let s = s.trim();
+    let literal = || s.to_string();
+    let unterminated = || Message::UnterminatedChar { literal: literal() };
+    let body = s
+        .strip_prefix('\'')
+        .ok_or_else(|| Message::UnquotedChar { literal: literal() })?
+        .strip_suffix('\'')
+        .ok_or_else(unterminated)?;
+
+    let mut bytes = Vec::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        let byte = match c {
+            '\\' => match chars.next() {
+                Some('n') => b'\n',
+                Some('t') => b'\t',
+                Some('r') => b'\r',
+                Some('0') => 0,
+                Some('\\') => b'\\',
+                Some('\'') => b'\'',
+                Some('"') => b'"',
+                Some(other) => {
+                    return Err(Message::UnknownEscape {
+                        escape: other,
+                        literal: literal(),
+                    });
+                }
+                None => return Err(unterminated()),
+            },
+            '\'' => return Err(Message::UnescapedQuote { literal: literal() }),
+            c if c.is_ascii() => c as u8,
+            c => {
+                return Err(Message::NonAsciiChar {
+                    c,
+                    literal: literal(),
+                });
+            }
+        };
+        bytes.push(byte);
+    }
+
+    if bytes.is_empty() {
+        return Err(Message::EmptyChar { literal: literal() });
+    }
+    if bytes.len() > MAX_CHAR_LITERAL_LEN {
+        return Err(Message::CharTooLong {
+            max: MAX_CHAR_LITERAL_LEN,
+            literal: literal(),
+        });
+    }
+    Ok(bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | byte as i128))
+}
+
+/// 内置的命名常量，表达式中可以直接引用，如 4 x MB、SZ_1G / PAGE_SIZE
+/// 容量单位都按 2 的幂计算，KiB 等写法是对应的别名
+pub(crate) const CONSTANTS: &[(&str, i128)] = &[
+    ("KB", 1 << 10),
+    ("MB", 1 << 20),
+    ("GB", 1 << 30),
+    ("TB", 1 << 40),
+    ("PB", 1 << 50),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+    ("PiB", 1 << 50),
+    ("PAGE_SIZE", 4096),
+    ("SZ_1K", 1 << 10),
+    ("SZ_4K", 4 << 10),
+    ("SZ_64K", 64 << 10),
+    ("SZ_1M", 1 << 20),
+    ("SZ_2M", 2 << 20),
+    ("SZ_16M", 16 << 20),
+    ("SZ_1G", 1 << 30),
+    ("SZ_2G", 2 << 30),
+    ("SZ_4G", 4 << 30),
+];
+
+/// 查找命名常量，区分大小写
+fn lookup_constant(name: &str) -> Option<i128> {
+    CONSTANTS
+        .iter()
+        .find(|(constant, _)| *constant == name)
+        .map(|(_, value)| *value)
+}
+
+/// 两个字符串之间的编辑距离，用于给写错的标识符提示相近的名字
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// 未赋值标识符的错误信息，附带名字最相近的常量或函数（忽略大小写比较，编辑距离不超过 2）
+fn unknown_identifier(name: &str) -> Message {
+    let names = || {
+        CONSTANTS
+            .iter()
+            .map(|(constant, _)| *constant)
+            .chain(FUNCTIONS.iter().map(|function| function.name))
+    };
+    let distance = |known: &str| edit_distance(&known.to_lowercase(), &name.to_lowercase());
+    let best = names().map(distance).min().unwrap_or(usize::MAX);
+    let close: Vec<&str> = names()
+        .filter(|known| best <= 2 && distance(known) == best)
+        .collect();
+    let name = name.to_string();
+    if close.is_empty() {
+        Message::UnassignedVariable { name }
+    } else {
+        Message::UnknownIdentifier {
+            name,
+            suggestions: close.join(", "),
+        }
+    }
+}
+
+/// 表达式支持的操作符，序列化时使用其写法（如 "+"、"<<"）
+///
+/// ```
+/// let json = serde_json::to_string(&enjoy::Operator::Shl).unwrap();
+/// assert_eq!(json, r#""<<""#);
+/// let op: enjoy::Operator = serde_json::from_str(r#""*""#).unwrap();
+/// assert_eq!(op, enjoy::Operator::Mul);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Operator {
+    #[serde(rename = "**")]
+    Pow,
+    #[serde(rename = "x", alias = "*")]
+    Mul,
+    #[serde(rename = "/")]
+    Div,
+    #[serde(rename = "%")]
+    Mod,
+    #[serde(rename = "+")]
+    Add,
+    #[serde(rename = "-")]
+    Sub,
+    #[serde(rename = "<<")]
+    Shl,
+    #[serde(rename = ">>")]
+    Shr,
+    #[serde(rename = "&")]
+    And,
+    #[serde(rename = "^")]
+    Xor,
+    #[serde(rename = "|")]
+    Or,
+    #[serde(rename = "~")]
+    Not,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+}
+
+/// 二元操作符的结合方向，决定优先级相同时先计算哪一边
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Associativity {
+    Left,
+    Right,
+    /// 不能连用，如比较操作符 a < b < c
+    None,
+}
+
+/// 操作符的写法、优先级（与 C 语言一致，数值越大结合越紧）及结合方向
+/// 乘方与 Python 一致：高于左侧的一元操作符，并且右结合
+/// 同一个操作符有多种写法时，排在前面的写法用于显示
+/// 求值时的分组完全由这张表决定
+const OPERATORS: &[(&str, Operator, u8, Associativity)] = &[
+    ("**", Operator::Pow, 8, Associativity::Right),
+    ("~", Operator::Not, 7, Associativity::Right), // 前缀一元操作符总是右结合
+    ("x", Operator::Mul, 6, Associativity::Left),
+    ("*", Operator::Mul, 6, Associativity::Left), // 与 C 语言一致的写法，在 shell 中需要加引号
+    ("/", Operator::Div, 6, Associativity::Left),
+    ("%", Operator::Mod, 6, Associativity::Left),
+    ("+", Operator::Add, 5, Associativity::Left),
+    ("-", Operator::Sub, 5, Associativity::Left),
+    ("<<", Operator::Shl, 4, Associativity::Left),
+    (">>", Operator::Shr, 4, Associativity::Left),
+    ("&", Operator::And, 3, Associativity::Left),
+    ("^", Operator::Xor, 2, Associativity::Left),
+    ("|", Operator::Or, 1, Associativity::Left),
+    // 比较操作符优先级最低，结果为 1 或 0
+    ("==", Operator::Eq, 0, Associativity::None),
+    ("!=", Operator::Ne, 0, Associativity::None),
+    ("<", Operator::Lt, 0, Associativity::None),
+    ("<=", Operator::Le, 0, Associativity::None),
+    (">", Operator::Gt, 0, Associativity::None),
+    (">=", Operator::Ge, 0, Associativity::None),
+];
+
+impl Operator {
+    fn from_symbol(s: &str) -> Option<Operator> {
+        OPERATORS
+            .iter()
+            .find(|(symbol, ..)| *symbol == s)
+            .map(|(_, op, ..)| *op)
+    }
+
+    fn entry(self) -> &'static (&'static str, Operator, u8, Associativity) {
+        OPERATORS
+            .iter()
+            .find(|(_, op, ..)| *op == self)
+            .expect("操作符缺少优先级表项")
+    }
+
+    fn symbol(self) -> &'static str {
+        self.entry().0
+    }
+
+    fn precedence(self) -> u8 {
+        self.entry().2
+    }
+
+    /// 只能作为一元操作符使用
+    fn is_unary(self) -> bool {
+        self == Operator::Not
+    }
+
+    /// 出现在操作数位置时可以作为一元操作符（如负号）
+    fn can_be_unary(self) -> bool {
+        matches!(self, Operator::Not | Operator::Sub)
+    }
+
+    fn associativity(self) -> Associativity {
+        self.entry().3
+    }
+
+    pub(crate) fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Operator::Eq | Operator::Ne | Operator::Lt | Operator::Le | Operator::Gt | Operator::Ge
+        )
+    }
+
+    /// 计算比较操作符的结果
+    fn compare<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Operator::Eq => left == right,
+            Operator::Ne => left != right,
+            Operator::Lt => left < right,
+            Operator::Le => left <= right,
+            Operator::Gt => left > right,
+            Operator::Ge => left >= right,
+            op => unreachable!("{} 不是比较操作符", op),
+        }
+    }
+}
+
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.symbol())
+    }
+}
+
+/// 操作符栈中等待计算的操作符
+#[derive(Debug, Clone, Copy)]
+enum PendingOp {
+    Unary(Operator),
+    Binary(Operator),
+}
+
+impl PendingOp {
+    fn precedence(self) -> u8 {
+        match self {
+            // 负号与 ~ 同级，高于所有二元操作符（乘方除外）
+            PendingOp::Unary(_) => Operator::Not.precedence(),
+            PendingOp::Binary(op) => op.precedence(),
+        }
+    }
+}
+
+/// 表达式中的元素：数字或操作符
+#[derive(Debug, Clone, Deserialize)] // 派生 Clone 特性，Deserialize 用于 --tokens-from-json
+#[non_exhaustive]
+pub enum ExprToken {
+    Number(i128),
+    Float(f64),     // 带小数点的字面量，出现时整个表达式按浮点数计算
+    Duration(i128), // 带时间单位的字面量，如 350ms，值为纳秒数
+    Operator(Operator),
+    LeftParen,       // 左中括号 [
+    RightParen,      // 右中括号 ]
+    LeftRoundParen,  // 左圆括号 (，与 [ 等价
+    RightRoundParen, // 右圆括号 )，与 ] 等价
+    Colon,           // 位段提取中分隔高低位的 :，如 v[27:24]
+    Comma,           // 分隔函数参数的逗号
+    Assign,          // 给变量赋值的 =，如 base = 0x1000
+    Semicolon,       // 分隔语句的 ;
+    Ident(String),   // 函数名（后面紧跟参数列表，如 min[a, b]）、ans 或变量名
+}
+
+impl ExprToken {
+    /// 左括号的写法，不是左括号时返回 None
+    pub(crate) fn open_bracket(&self) -> Option<char> {
+        match self {
+            ExprToken::LeftParen => Some('['),
+            ExprToken::LeftRoundParen => Some('('),
+            _ => None,
+        }
+    }
+
+    /// 右括号的写法，不是右括号时返回 None
+    pub(crate) fn close_bracket(&self) -> Option<char> {
+        match self {
+            ExprToken::RightParen => Some(']'),
+            ExprToken::RightRoundParen => Some(')'),
+            _ => None,
+        }
+    }
+}
+
+/// 没有原文时（如从 JSON 读取）用于错误提示的写法
+impl std::fmt::Display for ExprToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ExprToken::Number(num) => write!(f, "{}", num),
+            ExprToken::Float(num) => write!(f, "{:?}", num),
+            ExprToken::Duration(nanos) => write!(f, "{}ns", nanos),
+            ExprToken::Operator(op) => write!(f, "{}", op),
+            ExprToken::Colon => write!(f, ":"),
+            ExprToken::Comma => write!(f, ","),
+            ExprToken::Assign => write!(f, "="),
+            ExprToken::Semicolon => write!(f, ";"),
+            ExprToken::Ident(name) => write!(f, "{}", name),
+            bracket => {
+                let c = bracket.open_bracket().or(bracket.close_bracket()).unwrap();
+                write!(f, "{}", c)
+            }
+        }
+    }
+}
+
+/// 与左括号成对的右括号
+fn matching_bracket(open: char) -> char {
+    if open == '(' { ')' } else { ']' }
+}
+
+/// 解析单个表达式元素
+fn parse_expression_token(input: &str) -> Result<ExprToken, Message> {
+    if let Some(nanos) = parse_duration(input) {
+        nanos.map(ExprToken::Duration)
+    } else if let Ok(num) = parse_number(input) {
+        Ok(ExprToken::Number(num))
+    } else if let Ok(num) = parse_float(input) {
+        Ok(ExprToken::Float(num))
+    } else if let Ok(num) = parse_char_literal(input) {
+        Ok(ExprToken::Number(num))
+    } else if let Some(num) = lookup_constant(input) {
+        Ok(ExprToken::Number(num))
+    } else if lookup_function(input).is_some() || input == ANS {
+        Ok(ExprToken::Ident(input.to_string()))
+    } else if let Some(op) = Operator::from_symbol(input) {
+        Ok(ExprToken::Operator(op))
+    } else if input == "[" {
+        Ok(ExprToken::LeftParen)
+    } else if input == "]" {
+        Ok(ExprToken::RightParen)
+    } else if input == ":" {
+        Ok(ExprToken::Colon)
+    } else if input == "," {
+        Ok(ExprToken::Comma)
+    } else if input == "=" {
+        Ok(ExprToken::Assign)
+    } else if input == ";" {
+        Ok(ExprToken::Semicolon)
+    } else if input == "(" {
+        Ok(ExprToken::LeftRoundParen)
+    } else if input == ")" {
+        Ok(ExprToken::RightRoundParen)
+    } else {
+        Err(Message::InvalidToken {
+            input: input.to_string(),
+        })
+    }
+}
+
+/// 读取一个数字字面量（0x/0b/0o 前缀或十进制），返回字面量的结束位置
+fn scan_number(chars: &[char], start: usize) -> usize {
+    let is_digit: fn(&char) -> bool = match (chars[start], chars.get(start + 1)) {
+        ('0', Some('x')) => |c| c.is_ascii_hexdigit() || *c == '_',
+        ('0', Some('b')) => |c| c.is_digit(2) || *c == '_',
+        ('0', Some('o')) => |c| c.is_digit(8) || *c == '_',
+        _ => |c| c.is_ascii_digit() || *c == '_',
+    };
+    let mut end = start;
+    if chars[start] == '0' && matches!(chars.get(start + 1), Some('x' | 'b' | 'o')) {
+        end += 2;
+    }
+    while end < chars.len() && is_digit(&chars[end]) {
+        end += 1;
+    }
+    end
+}
+
+/// 把一个完整的表达式字符串拆分为表达式元素，如 "1+2x[3+4]"，空白会被忽略
+/// 每个元素同时返回它在输入中的原文，用于错误提示
+/// 注意以 0x 开头的总是十六进制数，0 乘以某个数时需写作 "0 x 5"
+fn tokenize(input: &str) -> Result<Vec<(ExprToken, String)>, Message> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens: Vec<(ExprToken, String)> = Vec::new();
+    let mut in_call = Vec::new(); // 每层括号是否是函数参数列表
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let at_column = |error| Message::AtColumn {
+            column,
+            error: Box::new(error),
+        };
+        let (token, end) = if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c.is_ascii_digit() {
+            let mut end = scan_number(&chars, i);
+            // 十进制数后面紧跟小数点和数字时按小数读取，如 3.3
+            let prefixed = c == '0' && matches!(chars.get(i + 1), Some('x' | 'b' | 'o'));
+            // <进制>#<数字> 形式的任意进制数，如 36#Z1G
+            let radix_literal = !prefixed && chars.get(end) == Some(&'#');
+            if radix_literal {
+                end += 1;
+                while end < chars.len() && (chars[end].is_ascii_alphanumeric() || chars[end] == '_')
+                {
+                    end += 1;
+                }
+            }
+            if !prefixed
+                && chars.get(end) == Some(&'.')
+                && chars.get(end + 1).is_some_and(|c| c.is_ascii_digit())
+            {
+                end += 1;
+                while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '_') {
+                    end += 1;
+                }
+            }
+            // 函数参数列表之外，十进制整数可以用 , 作千位分隔符，如 1,048,576；
+            // 参数列表中的 , 总是分隔参数，min[1,000, 2] 有三个参数
+            // 其他进制的数同样读入，由 parse_number 给出明确的错误
+            if !in_call.last().copied().unwrap_or(false)
+                && !radix_literal
+                && !chars[i..end].contains(&'.')
+            {
+                while chars.get(end) == Some(&',')
+                    && chars.get(end + 1).is_some_and(|c| c.is_ascii_digit())
+                {
+                    end += 1;
+                    while end < chars.len() && chars[end].is_ascii_digit() {
+                        end += 1;
+                    }
+                }
+            }
+            // 数字后面紧跟字母或数字（乘号 x 除外）说明字面量写错了
+            if chars
+                .get(end)
+                .is_some_and(|c| c.is_ascii_alphanumeric() && *c != 'x')
+            {
+                while end < chars.len() && chars[end].is_ascii_alphanumeric() {
+                    end += 1;
+                }
+            }
+            // 科学计数法的指数可以带符号，如 1e+6；1e-3 按负指数报错，而不是当作 1E 减 3
+            if !prefixed
+                && !radix_literal
+                && matches!(chars.get(end - 1), Some('e' | 'E'))
+                && matches!(chars.get(end), Some('+' | '-'))
+                && chars.get(end + 1).is_some_and(|c| c.is_ascii_digit())
+            {
+                end += 1;
+                while end < chars.len() && chars[end].is_ascii_digit() {
+                    end += 1;
+                }
+            }
+            let literal: String = chars[i..end].iter().collect();
+            let token = if let Some(nanos) = parse_duration(&literal) {
+                nanos.map(ExprToken::Duration)
+            } else if literal.contains('.') && !literal.contains(['e', 'E']) {
+                parse_float(&literal).map(ExprToken::Float)
+            } else {
+                parse_number(&literal).map(ExprToken::Number)
+            };
+            (token.map_err(at_column)?, end)
+        } else if c == '$' {
+            // $FF 形式的十六进制数
+            let mut end = i + 1;
+            while end < chars.len() && (chars[end].is_ascii_alphanumeric() || chars[end] == '_') {
+                end += 1;
+            }
+            let literal: String = chars[i..end].iter().collect();
+            let num = parse_number(&literal).map_err(at_column)?;
+            (ExprToken::Number(num), end)
+        } else if c == '\'' {
+            // 字符字面量到下一个没有转义的 ' 为止
+            let mut end = i + 1;
+            while end < chars.len() && chars[end] != '\'' {
+                end += if chars[end] == '\\' { 2 } else { 1 };
+            }
+            if end >= chars.len() {
+                return Err(at_column(Message::MissingCharQuote));
+            }
+            let literal: String = chars[i..=end].iter().collect();
+            let num = parse_char_literal(&literal).map_err(at_column)?;
+            (ExprToken::Number(num), end + 1)
+        } else if c == '[' {
+            (ExprToken::LeftParen, i + 1)
+        } else if c == ']' {
+            (ExprToken::RightParen, i + 1)
+        } else if c == ':' {
+            (ExprToken::Colon, i + 1)
+        } else if c == ',' {
+            (ExprToken::Comma, i + 1)
+        } else if c == '=' && chars.get(i + 1) != Some(&'=') {
+            (ExprToken::Assign, i + 1)
+        } else if c == ';' {
+            (ExprToken::Semicolon, i + 1)
+        } else if c == '(' {
+            (ExprToken::LeftRoundParen, i + 1)
+        } else if c == ')' {
+            (ExprToken::RightRoundParen, i + 1)
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = i;
+            while end < chars.len() && (chars[end].is_ascii_alphanumeric() || chars[end] == '_') {
+                end += 1;
+            }
+            let name: String = chars[i..end].iter().collect();
+            let is_builtin = |name: &str| {
+                lookup_constant(name).is_some() || lookup_function(name).is_some() || name == ANS
+            };
+            // 乘号 x 可以紧跟操作数或内置名称，如 4xMB、2xabs[-3]；其他以 x 开头的是变量名
+            let follows_operand = tokens.last().is_some_and(|(token, _): &(ExprToken, _)| {
+                matches!(
+                    token,
+                    ExprToken::Number(_)
+                        | ExprToken::Float(_)
+                        | ExprToken::Duration(_)
+                        | ExprToken::Ident(_)
+                        | ExprToken::RightParen
+                        | ExprToken::RightRoundParen
+                )
+            });
+            match lookup_constant(&name) {
+                Some(num) => (ExprToken::Number(num), end),
+                None if c == 'x'
+                    && !is_builtin(&name)
+                    && (follows_operand || is_builtin(&name[1..])) =>
+                {
+                    (ExprToken::Operator(Operator::Mul), i + 1)
+                }
+                None => (ExprToken::Ident(name), end),
+            }
+        } else {
+            // 优先匹配较长的操作符，如 ** 和 <<
+            let symbol = OPERATORS
+                .iter()
+                .map(|(symbol, ..)| *symbol)
+                .filter(|symbol| chars[i..].starts_with(&symbol.chars().collect::<Vec<_>>()))
+                .max_by_key(|symbol| symbol.len())
+                .ok_or_else(|| at_column(Message::InvalidChar { c }))?;
+            let op = Operator::from_symbol(symbol).unwrap();
+            (ExprToken::Operator(op), i + symbol.chars().count())
+        };
+        if token.open_bracket().is_some() {
+            let function = matches!(tokens.last(), Some((ExprToken::Ident(name), _)) if lookup_function(name).is_some());
+            in_call.push(function);
+        } else if token.close_bracket().is_some() {
+            in_call.pop();
+        }
+        tokens.push((token, chars[i..end].iter().collect()));
+        i = end;
+    }
+    Ok(tokens)
+}
+
+/// 把 --calc 的参数转换为表达式元素：单独的元素直接解析，否则按完整表达式拆分
+/// 返回的第二个列表是每个元素的原文，与元素一一对应
+pub(crate) fn tokenize_args(args: &[String]) -> Result<(Vec<ExprToken>, Vec<String>), Message> {
+    let mut tokens = Vec::new();
+    let mut sources = Vec::new();
+    for arg in args {
+        match parse_expression_token(arg) {
+            Ok(token) => {
+                tokens.push(token);
+                sources.push(arg.trim().to_string());
+            }
+            Err(_) => {
+                let lexed = tokenize(arg).map_err(|err| {
+                    let (expression, error) = (arg.clone(), Box::new(err));
+                    // 没加引号的 * 会被 shell 展开成当前目录下的文件名
+                    if Path::new(arg).exists() {
+                        Message::UnquotedGlob { expression, error }
+                    } else {
+                        Message::InvalidExpression { expression, error }
+                    }
+                })?;
+                for (token, source) in lexed {
+                    tokens.push(token);
+                    sources.push(source);
+                }
+            }
+        }
+    }
+    Ok((tokens, sources))
+}
+
+/// 从 JSON 文件读取表达式元素，格式如 [{"Number": 1}, {"Operator": "+"}, "LeftParen"]
+pub(crate) fn read_tokens_from_json(path: &Path) -> Result<Vec<ExprToken>, Message> {
+    let path_text = || path.display().to_string();
+    let content = std::fs::read_to_string(path).map_err(|e| Message::ReadFailed {
+        path: path_text(),
+        error: e.to_string(),
+    })?;
+    let tokens: Vec<ExprToken> =
+        serde_json::from_str(&content).map_err(|e| Message::InvalidTokenJson {
+            path: path_text(),
+            error: e.to_string(),
+        })?;
+
+    if tokens.is_empty() {
+        return Err(Message::EmptyTokenList { path: path_text() });
+    }
+    Ok(tokens)
+}
+
+/// --max-tokens 和 --max-depth 的默认值
+pub(crate) const DEFAULT_MAX_TOKENS: usize = 10_000;
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// 在计算之前检查表达式的 token 数量、括号嵌套深度以及括号是否成对，
+/// 这样不成对的括号总是解析错误，而不会因为先算出的除零等错误变成计算错误
+pub(crate) fn check_limits(
+    tokens: &[ExprToken],
+    max_tokens: usize,
+    max_depth: usize,
+) -> Result<(), EnjoyError> {
+    if tokens.len() > max_tokens {
+        return Err(EnjoyError::parse(Message::TooManyTokens {
+            count: tokens.len(),
+            max: max_tokens,
+        }));
+    }
+
+    let at = |token: usize, message: Message| EnjoyError::Parse {
+        message,
+        token: Some(token),
+    };
+    // 还没有闭合的左括号的位置
+    let mut open: Vec<usize> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.open_bracket().is_some() {
+            open.push(i);
+            if open.len() > max_depth {
+                return Err(EnjoyError::parse(Message::TooDeep { max: max_depth }));
+            }
+        } else if let Some(close) = token.close_bracket() {
+            let Some(start) = open.pop() else {
+                return Err(at(i, Message::UnexpectedClosingBracket { close }));
+            };
+            let open_char = tokens[start].open_bracket().unwrap();
+            if close != matching_bracket(open_char) {
+                return Err(at(
+                    start,
+                    Message::MismatchedBrackets {
+                        open: open_char,
+                        close,
+                    },
+                ));
+            }
+        }
+    }
+    // 指向最外层没有闭合的括号
+    if let Some(&start) = open.first() {
+        let open_char = tokens[start].open_bracket().unwrap();
+        return Err(at(start, Message::UnclosedBracket { open: open_char }));
+    }
+    Ok(())
+}
+
+/// 表达式求值选项
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct EvalOptions {
+    /// 溢出时按补码回绕，而不是报错 (--wrap)
+    pub(crate) wrap: bool,
+    /// 只允许非负数，结果按 u64 显示 (--unsigned)
+    pub(crate) unsigned: bool,
+    /// 溢出时限制在寄存器的最小值和最大值，而不是报错 (--saturate)
+    pub(crate) saturate: bool,
+    /// 回绕或饱和模式下的寄存器位宽 (--width)，未指定时为 64
+    pub(crate) width: Option<u32>,
+    /// 每一步运算的结果都对该模数取余，范围为 1..=u64::MAX (--mod)
+    pub(crate) modulus: Option<i128>,
+}
+
+impl EvalOptions {
+    /// 当前模式下数值是否在可表示范围内
+    /// 回绕和饱和模式模拟寄存器，限定为 i64 或 u64（或 --width 指定的位宽）；否则按 i128 计算，无符号模式只要求非负
+    fn contains(&self, value: i128) -> bool {
+        if self.wrap || self.saturate {
+            self.fits_register(value)
+        } else {
+            !self.unsigned || value >= 0
+        }
+    }
+
+    /// 寄存器的位宽，默认为 64
+    pub(crate) fn register_bits(&self) -> u32 {
+        self.width.unwrap_or(64)
+    }
+
+    /// 寄存器类型的名称，如 i64、u8
+    pub(crate) fn register_name(&self) -> String {
+        let sign = if self.unsigned { 'u' } else { 'i' };
+        format!("{}{}", sign, self.register_bits())
+    }
+
+    /// 数值是否能放进寄存器（有符号模式为 i64，无符号模式为 u64；或 --width 指定位宽的对应类型）
+    pub(crate) fn fits_register(&self, value: i128) -> bool {
+        let bits = self.register_bits();
+        if self.unsigned {
+            (0..1i128 << bits).contains(&value)
+        } else {
+            (-(1i128 << (bits - 1))..1i128 << (bits - 1)).contains(&value)
+        }
+    }
+
+    /// 把数值截断到寄存器位宽，即按补码回绕；有符号模式下按最高位符号扩展
+    fn truncate(&self, value: i128) -> i128 {
+        let shift = 128 - self.register_bits();
+        if self.unsigned {
+            ((value as u128) << shift >> shift) as i128
+        } else {
+            (value << shift) >> shift
+        }
+    }
+
+    /// 把数值限制在寄存器的最小值和最大值之间
+    fn clamp(&self, value: i128) -> i128 {
+        let bits = self.register_bits();
+        if self.unsigned {
+            value.clamp(0, (1i128 << bits) - 1)
+        } else {
+            value.clamp(-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1)
+        }
+    }
+
+    /// 不回绕也不饱和时的选项，用于求出饱和前的结果
+    fn exact(&self) -> EvalOptions {
+        EvalOptions {
+            wrap: false,
+            saturate: false,
+            ..*self
+        }
+    }
+
+    /// 取模模式下把运算结果规约到 0..N-1，负数同样规约为非负数；其他模式下原样返回
+    fn reduce(&self, value: i128) -> i128 {
+        match self.modulus {
+            Some(modulus) => value.rem_euclid(modulus),
+            None => value,
+        }
+    }
+
+    /// 当前模式下的位宽，决定移位量的上限
+    fn bits(&self) -> u32 {
+        if self.wrap || self.saturate {
+            self.register_bits()
+        } else {
+            128
+        }
+    }
+
+    /// 检查数字字面量是否能用当前模式表示
+    /// 用 --width 指定位宽时字面量按位模式理解，如 8 位有符号模式下 200 即 -56
+    pub(crate) fn check_literal(&self, value: i128) -> Result<i128, Message> {
+        if self.contains(value) {
+            Ok(value)
+        } else if self.unsigned && value < 0 {
+            Err(Message::NegativeUnsigned { value })
+        } else if let Some(width) = self.width.filter(|_| self.wrap) {
+            if (-(1i128 << (width - 1))..1i128 << width).contains(&value) {
+                Ok(self.truncate(value))
+            } else {
+                Err(Message::OutOfWidth { width, value })
+            }
+        } else if self.unsigned {
+            Err(Message::OutOfRegister {
+                register: self.register_name(),
+                value,
+            })
+        } else {
+            Err(Message::OutOfSignedRegister {
+                register: self.register_name(),
+                value,
+            })
+        }
+    }
+
+    /// 连 i128 也放不下的结果：饱和模式下按结果的符号取 i128 的最值，之后再限制到寄存器范围
+    fn overflow_limit(&self, negative: bool) -> Option<i128> {
+        self.saturate
+            .then_some(if negative { i128::MIN } else { i128::MAX })
+    }
+
+    /// 根据是否回绕选择结果：回绕模式截断 wrapping 的结果，饱和模式限制到寄存器范围，否则超出范围时报错
+    fn overflow_checked(
+        &self,
+        wrapping: i128,
+        checked: Option<i128>,
+        describe: impl FnOnce() -> String,
+    ) -> Result<i128, Message> {
+        let overflow = || Message::Overflow {
+            operation: describe(),
+        };
+        if self.wrap {
+            Ok(self.truncate(wrapping))
+        } else if self.saturate {
+            checked.map(|value| self.clamp(value)).ok_or_else(overflow)
+        } else {
+            checked
+                .filter(|value| self.contains(*value))
+                .ok_or_else(overflow)
+        }
+    }
+}
+
+/// 对操作数应用一元操作符
+fn apply_unary_operator(op: Operator, value: i128, options: &EvalOptions) -> Result<i128, Message> {
+    if let Some(modulus) = options.modulus {
+        // 取模模式下取负即求加法逆元，无符号模式下也允许
+        return match op {
+            Operator::Sub => Ok((-value.rem_euclid(modulus)).rem_euclid(modulus)),
+            _ => apply_unary_operator(
+                op,
+                value,
+                &EvalOptions {
+                    modulus: None,
+                    ..*options
+                },
+            )
+            .map(|result| result.rem_euclid(modulus)),
+        };
+    }
+    match op {
+        // 回绕和饱和模式下数值已在寄存器范围内，取反后截断即只翻转寄存器的各位
+        Operator::Not if options.wrap || options.saturate => Ok(options.truncate(!value)),
+        // 无符号数取反只翻转低 64 位，否则结果会变成负数
+        Operator::Not if options.unsigned && value > u64::MAX as i128 => {
+            Err(Message::UnsignedNotTooWide { value })
+        }
+        Operator::Not if options.unsigned => Ok(value ^ u64::MAX as i128),
+        Operator::Not => Ok(!value),
+        Operator::Sub if options.unsigned && !options.wrap && !options.saturate && value != 0 => {
+            Err(Message::UnsignedNegate { value })
+        }
+        Operator::Sub => {
+            options.overflow_checked(value.wrapping_neg(), value.checked_neg(), || {
+                format!("-({})", value)
+            })
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// 检查移位量是否小于当前位宽
+fn shift_amount(right: i128, bits: u32) -> Result<u32, Message> {
+    if (0..bits as i128).contains(&right) {
+        Ok(right as u32)
+    } else {
+        Err(Message::ShiftOutOfRange {
+            shift: right,
+            max: bits - 1,
+        })
+    }
+}
+
+/// (a x b) mod m，a 和 b 已经规约到 0..m-1；m 不超过 u64::MAX，乘积在 u128 内不会溢出
+fn mul_mod(a: i128, b: i128, modulus: i128) -> i128 {
+    ((a as u128 * b as u128) % modulus as u128) as i128
+}
+
+/// (base ** exp) mod m，按平方-乘算法计算，指数不需要规约
+fn pow_mod(base: i128, mut exp: u128, modulus: i128) -> i128 {
+    let mut base = base.rem_euclid(modulus);
+    let mut result = 1 % modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exp >>= 1;
+    }
+    result
+}
+
+/// 取模模式下的二元操作符：+ - x ** << 直接按模运算，不会溢出；
+/// 比较按原值进行，结果为 1 或 0；其余操作符按普通整数计算后再取余
+fn apply_modular_operator(
+    op: Operator,
+    left: i128,
+    right: i128,
+    options: &EvalOptions,
+    modulus: i128,
+) -> Result<i128, Message> {
+    let (a, b) = (left.rem_euclid(modulus), right.rem_euclid(modulus));
+    match op {
+        // a 和 b 都小于 2^64，和与差都在 i128 内
+        Operator::Add => Ok((a + b) % modulus),
+        Operator::Sub => Ok((a - b).rem_euclid(modulus)),
+        Operator::Mul => Ok(mul_mod(a, b, modulus)),
+        Operator::Pow => {
+            if right < 0 {
+                return Err(Message::NegativeExponent { exponent: right });
+            }
+            Ok(pow_mod(a, right as u128, modulus))
+        }
+        Operator::Shl => {
+            let shift = shift_amount(right, options.bits())?;
+            Ok(mul_mod(a, pow_mod(2, shift as u128, modulus), modulus))
+        }
+        op if op.is_comparison() => Ok(op.compare(left, right) as i128),
+        _ => apply_operator(
+            op,
+            left,
+            right,
+            &EvalOptions {
+                modulus: None,
+                ..*options
+            },
+        )
+        .map(|result| result.rem_euclid(modulus)),
+    }
+}
+
+/// 对两个操作数应用二元操作符，默认检查溢出
+/// 中间结果按 i128 计算，两个 64 位数相乘也不会溢出；回绕模式下每一步都截断到 64 位
+fn apply_operator(
+    op: Operator,
+    left: i128,
+    right: i128,
+    options: &EvalOptions,
+) -> Result<i128, Message> {
+    if let Some(modulus) = options.modulus {
+        return apply_modular_operator(op, left, right, options, modulus);
+    }
+    let describe = || format!("{} {} {}", left, op, right);
+    match op {
+        Operator::Add => {
+            options.overflow_checked(left.wrapping_add(right), left.checked_add(right), describe)
+        }
+        Operator::Sub => {
+            options.overflow_checked(left.wrapping_sub(right), left.checked_sub(right), describe)
+        }
+        Operator::Mul => {
+            options.overflow_checked(left.wrapping_mul(right), left.checked_mul(right), describe)
+        }
+        Operator::Div => {
+            if right == 0 {
+                return Err(Message::DivisionByZero);
+            }
+            // i64::MIN / -1 同样会溢出；无符号模式下操作数非负，即为无符号除法
+            options.overflow_checked(left.wrapping_div(right), left.checked_div(right), describe)
+        }
+        Operator::Mod => {
+            if right == 0 {
+                return Err(Message::DivisionByZero);
+            }
+            options.overflow_checked(left.wrapping_rem(right), left.checked_rem(right), describe)
+        }
+        // 回绕模式下移出 64 位的部分直接丢弃，便于构造 1 << 63 这样的掩码；
+        // 否则移出 i128 的有效位视为溢出
+        Operator::Shl => {
+            let shift = shift_amount(right, options.bits())?;
+            let shifted = left << shift;
+            let checked = Some(shifted)
+                .filter(|value| value >> shift == left)
+                .or_else(|| options.overflow_limit(left < 0));
+            options.overflow_checked(shifted, checked, describe)
+        }
+        // 有符号模式下为算术右移，负数高位补 1；无符号模式下为逻辑右移
+        Operator::Shr => Ok(left >> shift_amount(right, options.bits())?),
+        Operator::And => Ok(left & right),
+        Operator::Xor => Ok(left ^ right),
+        Operator::Or => Ok(left | right),
+        Operator::Pow => {
+            if right < 0 {
+                return Err(Message::NegativeExponent { exponent: right });
+            }
+            let exp =
+                u32::try_from(right).map_err(|_| Message::ExponentTooLarge { exponent: right })?;
+            let checked = left
+                .checked_pow(exp)
+                .or_else(|| options.overflow_limit(left < 0 && exp % 2 == 1));
+            options.overflow_checked(left.wrapping_pow(exp), checked, describe)
+        }
+        op if op.is_comparison() => Ok(op.compare(left, right) as i128),
+        _ => unreachable!(),
+    }
+}
+
+/// 函数接受的参数个数
+#[derive(Debug, Clone, Copy)]
+enum Arity {
+    Exact(usize),
+    /// 可变参数，至少需要给出的个数
+    AtLeast(usize),
+}
+
+/// 内置函数，在表达式中写作 name[a, b]
+pub(crate) struct Function {
+    pub(crate) name: &'static str,
+    arity: Arity,
+    /// 指定位宽的参数位置，结果会按该位宽补零显示
+    pub(crate) width_arg: Option<usize>,
+    apply: fn(&[i128], &EvalOptions) -> Result<i128, Message>,
+}
+
+pub(crate) const FUNCTIONS: &[Function] = &[
+    Function {
+        name: "abs",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, options| {
+            let value = args[0];
+            options.overflow_checked(value.wrapping_abs(), value.checked_abs(), || {
+                format!("abs[{}]", value)
+            })
+        },
+    },
+    Function {
+        name: "min",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, _| Ok(args[0].min(args[1])),
+    },
+    Function {
+        name: "max",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, _| Ok(args[0].max(args[1])),
+    },
+    Function {
+        name: "gcd",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, options| {
+            let g = gcd(args[0].unsigned_abs(), args[1].unsigned_abs());
+            options.overflow_checked(g as i128, i128::try_from(g).ok(), || {
+                format!("gcd[{}, {}]", args[0], args[1])
+            })
+        },
+    },
+    Function {
+        name: "lcm",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, options| {
+            let (a, b) = (args[0].unsigned_abs(), args[1].unsigned_abs());
+            if a == 0 || b == 0 {
+                return Ok(0);
+            }
+            let lcm = (a / gcd(a, b)).checked_mul(b);
+            let wrapping = (a / gcd(a, b)).wrapping_mul(b) as i128;
+            options.overflow_checked(wrapping, lcm.and_then(|l| i128::try_from(l).ok()), || {
+                format!("lcm[{}, {}]", args[0], args[1])
+            })
+        },
+    },
+    Function {
+        name: "mulwide",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, options| {
+            let product = wide_product(args[0], args[1], options)?;
+            // 有符号乘积总能放进 i128；无符号乘积超出 i128 时只有回绕模式能表示（取低 64 位）
+            let exact = if options.unsigned {
+                i128::try_from(product).ok()
+            } else {
+                Some(product as i128)
+            };
+            options
+                .overflow_checked(product as i128, exact, String::new)
+                .map_err(|_| Message::WideProductOverflow {
+                    a: args[0],
+                    b: args[1],
+                })
+        },
+    },
+    Function {
+        name: "popcount",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| Ok(machine_word(args[0]).0.count_ones() as i128),
+    },
+    Function {
+        name: "parity",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        // 与 popcount 一样，负数按 64 位补码计算
+        apply: |args, _| Ok((machine_word(args[0]).0.count_ones() & 1) as i128),
+    },
+    Function {
+        name: "clz",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| {
+            let (word, width) = machine_word(args[0]);
+            if word == 0 {
+                return Err(Message::NoBitsSet { function: "clz" });
+            }
+            Ok((word.leading_zeros() - (128 - width)) as i128)
+        },
+    },
+    Function {
+        name: "ctz",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| {
+            let (word, _) = machine_word(args[0]);
+            if word == 0 {
+                return Err(Message::NoBitsSet { function: "ctz" });
+            }
+            Ok(word.trailing_zeros() as i128)
+        },
+    },
+    Function {
+        name: "log2",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| {
+            args[0]
+                .checked_ilog2()
+                .map(|log| log as i128)
+                .ok_or(Message::Log2NotPositive { value: args[0] })
+        },
+    },
+    Function {
+        name: "bit",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        // 与 1 << n 完全一致，移位量的检查也相同
+        apply: |args, options| apply_operator(Operator::Shl, 1, args[0], options),
+    },
+    Function {
+        name: "mask",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, options| {
+            // mask[64] 为全 1 的 64 位掩码，即 u64::MAX；回绕模式下有符号数为 -1，位数不超过寄存器位宽
+            let max = if options.wrap || options.saturate {
+                options.bits() as i128
+            } else {
+                127
+            };
+            let n = args[0];
+            if !(0..=max).contains(&n) {
+                return Err(Message::MaskOutOfRange { n, max });
+            }
+            let mask = if n == 0 { 0 } else { u128::MAX >> (128 - n) };
+            Ok(wrap_result(mask as i128, options))
+        },
+    },
+    Function {
+        name: "setbit",
+        arity: Arity::AtLeast(2),
+        width_arg: None,
+        apply: |args, options| modify_bits("setbit", args, options, |value, bit| value | bit),
+    },
+    Function {
+        name: "clrbit",
+        arity: Arity::AtLeast(2),
+        width_arg: None,
+        apply: |args, options| modify_bits("clrbit", args, options, |value, bit| value & !bit),
+    },
+    Function {
+        name: "tglbit",
+        arity: Arity::AtLeast(2),
+        width_arg: None,
+        apply: |args, options| modify_bits("tglbit", args, options, |value, bit| value ^ bit),
+    },
+    Function {
+        name: "align_up",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, options| {
+            let (value, align) = (args[0], check_alignment(args[1])?);
+            let describe = || format!("align_up[{}, {}]", value, align);
+            let bumped = options.overflow_checked(
+                value.wrapping_add(align - 1),
+                value.checked_add(align - 1),
+                describe,
+            )?;
+            Ok(bumped & !(align - 1))
+        },
+    },
+    Function {
+        name: "align_down",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, _| Ok(args[0] & !(check_alignment(args[1])? - 1)),
+    },
+    Function {
+        name: "period_ns",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| {
+            let frequency = check_frequency("period_ns", args[0])?;
+            Ok(div_round(NANOS_PER_SECOND, frequency) as i128)
+        },
+    },
+    Function {
+        name: "cycles",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, options| {
+            let (time, frequency) = (args[0], check_frequency("cycles", args[1])?);
+            if time < 0 {
+                return Err(Message::NegativeArgument {
+                    function: "cycles",
+                    value: time,
+                });
+            }
+            let describe = || format!("cycles[{}, {}]", time, frequency);
+            let cycles = (time as u128)
+                .checked_mul(frequency)
+                .map(|product| div_round(product, NANOS_PER_SECOND))
+                .and_then(|cycles| i128::try_from(cycles).ok());
+            options.overflow_checked(cycles.unwrap_or(i128::MAX), cycles, describe)
+        },
+    },
+    Function {
+        name: "rotl",
+        arity: Arity::Exact(3),
+        width_arg: Some(2),
+        apply: |args, options| {
+            let (value, shift, width) = rotate_operands("rotl", args)?;
+            Ok(wrap_result(rotate_left(value, shift, width), options))
+        },
+    },
+    Function {
+        name: "rotr",
+        arity: Arity::Exact(3),
+        width_arg: Some(2),
+        apply: |args, options| {
+            let (value, shift, width) = rotate_operands("rotr", args)?;
+            Ok(wrap_result(
+                rotate_left(value, (width - shift) % width, width),
+                options,
+            ))
+        },
+    },
+    Function {
+        name: "rbit",
+        arity: Arity::Exact(2),
+        width_arg: Some(1),
+        apply: |args, options| {
+            // 反转低 width 位，其余位清零；负数取其补码的低 width 位
+            let width = register_width("rbit", args[1])?;
+            let reversed = (args[0] as u64).reverse_bits() >> (64 - width);
+            Ok(wrap_result(reversed as i128, options))
+        },
+    },
+    Function {
+        name: "bswap16",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| Ok((fit_width("bswap16", args[0], 16)? as u16).swap_bytes() as i128),
+    },
+    Function {
+        name: "bswap32",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| Ok((fit_width("bswap32", args[0], 32)? as u32).swap_bytes() as i128),
+    },
+    Function {
+        name: "bswap64",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, options| {
+            let swapped = fit_width("bswap64", args[0], 64)?.swap_bytes();
+            Ok(wrap_result(swapped as i128, options))
+        },
+    },
+    Function {
+        name: "revnibbles",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, options| {
+            // 反转十六进制数字的顺序 (0x1234 -> 0x4321)，不同于 bswap 按字节、rbit 按位反转；
+            // 给出 --width 时按该位宽反转（含前导零），否则只反转有效数字，负数取其 64 位补码
+            let value = fit_width("revnibbles", args[0], options.width.unwrap_or(64))?;
+            let digits = match options.width {
+                Some(width) => width / 4,
+                None => (u64::BITS - value.leading_zeros()).div_ceil(4),
+            };
+            let reversed = (0..digits).fold(0u64, |reversed, digit| {
+                reversed << 4 | (value >> (4 * digit) & 0xF)
+            });
+            Ok(wrap_result(reversed as i128, options))
+        },
+    },
+    Function {
+        name: "byte",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, _| field_of("byte", args[0], args[1], 8),
+    },
+    Function {
+        name: "nibble",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, _| field_of("nibble", args[0], args[1], 4),
+    },
+    Function {
+        name: "sext",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, options| {
+            // 取低 width 位，第 width - 1 位为符号位；无符号模式下得到扩展后的 u64 位模式
+            let extended = sign_extend(args[0], extend_width(args[1])?);
+            Ok(if options.unsigned {
+                extended as u64 as i128
+            } else {
+                extended
+            })
+        },
+    },
+    Function {
+        name: "zext",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, options| {
+            let width = extend_width(args[1])?;
+            let low = args[0] as u128 & (u128::MAX >> (128 - width));
+            Ok(wrap_result(low as i128, options))
+        },
+    },
+    Function {
+        name: "tobcd",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, options| Ok(wrap_result(to_bcd(args[0])? as i128, options)),
+    },
+    Function {
+        name: "frombcd",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| Ok(from_bcd(args[0])? as i128),
+    },
+    Function {
+        name: "interleave",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, options| {
+            let x = morton_coordinate("interleave", args[0])?;
+            let y = morton_coordinate("interleave", args[1])?;
+            Ok(wrap_result(
+                (spread_bits(x) | spread_bits(y) << 1) as i128,
+                options,
+            ))
+        },
+    },
+    Function {
+        name: "deinterleave_x",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| Ok(compact_bits(fit_width("deinterleave_x", args[0], 64)?) as i128),
+    },
+    Function {
+        name: "deinterleave_y",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| Ok(compact_bits(fit_width("deinterleave_y", args[0], 64)? >> 1) as i128),
+    },
+    Function {
+        name: "gray",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| {
+            let value = non_negative("gray", args[0])?;
+            Ok(value ^ (value >> 1))
+        },
+    },
+    Function {
+        name: "ungray",
+        arity: Arity::Exact(1),
+        width_arg: None,
+        apply: |args, _| {
+            // 格雷码的逆变换是前缀异或：每一位是它及更高所有位的异或
+            let mut value = non_negative("ungray", args[0])?;
+            let mut shift = 1;
+            while shift < 128 {
+                value ^= value >> shift;
+                shift <<= 1;
+            }
+            Ok(value)
+        },
+    },
+    Function {
+        name: "crc32",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, _| Ok(crc::crc32(&le_bytes("crc32", args[0], args[1])?) as i128),
+    },
+    Function {
+        name: "crc16",
+        arity: Arity::Exact(2),
+        width_arg: None,
+        apply: |args, _| Ok(crc::crc16(&le_bytes("crc16", args[0], args[1])?) as i128),
+    },
+];
+
+/// 取数值的低 nbytes 个字节（1..8，负数按补码），按小端序排列，即最低字节在前
+/// 两个 64 位数的完整 128 位乘积（位模式）：有符号模式下操作数为 i64，无符号模式下为 u64
+fn wide_product(a: i128, b: i128, options: &EvalOptions) -> Result<u128, Message> {
+    let range = if options.unsigned {
+        0..=u64::MAX as i128
+    } else {
+        i64::MIN as i128..=i64::MAX as i128
+    };
+    if let Some(value) = [a, b].into_iter().find(|value| !range.contains(value)) {
+        let domain = if options.unsigned { "u64" } else { "i64" };
+        return Err(Message::WideOperandRange { domain, value });
+    }
+    Ok(if options.unsigned {
+        a as u128 * b as u128
+    } else {
+        (a * b) as u128
+    })
+}
+
+fn le_bytes(function: &'static str, value: i128, nbytes: i128) -> Result<Vec<u8>, Message> {
+    if !(1..=8).contains(&nbytes) {
+        return Err(Message::ByteCountRange {
+            function,
+            count: nbytes,
+        });
+    }
+    let nbytes = nbytes as usize;
+    let word = fit_width(function, value, nbytes as u32 * 8)?;
+    Ok(word.to_le_bytes()[..nbytes].to_vec())
+}
+
+/// 只接受非负参数的函数（如 gray）检查参数
+fn non_negative(function: &'static str, value: i128) -> Result<i128, Message> {
+    if value < 0 {
+        Err(Message::NegativeArgument { function, value })
+    } else {
+        Ok(value)
+    }
+}
+
+/// Morton 编码的坐标，每个坐标最多 32 位
+fn morton_coordinate(function: &'static str, value: i128) -> Result<u32, Message> {
+    u32::try_from(value).map_err(|_| Message::MortonRange { function, value })
+}
+
+/// 把 32 位数的各位分散到偶数位上（第 i 位移到第 2i 位）
+fn spread_bits(value: u32) -> u64 {
+    let mut x = value as u64;
+    x = (x | x << 16) & 0x0000_FFFF_0000_FFFF;
+    x = (x | x << 8) & 0x00FF_00FF_00FF_00FF;
+    x = (x | x << 4) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | x << 2) & 0x3333_3333_3333_3333;
+    x = (x | x << 1) & 0x5555_5555_5555_5555;
+    x
+}
+
+/// spread_bits 的逆变换：取出偶数位并压缩到低 32 位
+fn compact_bits(value: u64) -> u32 {
+    let mut x = value & 0x5555_5555_5555_5555;
+    x = (x | x >> 1) & 0x3333_3333_3333_3333;
+    x = (x | x >> 2) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | x >> 4) & 0x00FF_00FF_00FF_00FF;
+    x = (x | x >> 8) & 0x0000_FFFF_0000_FFFF;
+    x = (x | x >> 16) & 0x0000_0000_FFFF_FFFF;
+    x as u32
+}
+
+/// 把十进制数转换为压缩 BCD 码，每个半字节一位十进制数，最多 16 位
+fn to_bcd(value: i128) -> Result<u64, Message> {
+    let mut rest = non_negative("tobcd", value)?;
+    if rest > 9_999_999_999_999_999 {
+        return Err(Message::BcdTooLarge { value });
+    }
+    let mut bcd = 0;
+    let mut shift = 0;
+    while rest > 0 {
+        bcd |= ((rest % 10) as u64) << shift;
+        rest /= 10;
+        shift += 4;
+    }
+    Ok(bcd)
+}
+
+/// 把压缩 BCD 码转换为十进制数，每个半字节都必须是 0..9
+fn from_bcd(value: i128) -> Result<u64, Message> {
+    let bcd = fit_width("frombcd", value, 64)?;
+    let mut decimal = 0;
+    for n in (0..16).rev() {
+        let digit = (bcd >> (n * 4)) & 0xF;
+        if digit > 9 {
+            return Err(Message::NotBcd {
+                value: bcd,
+                nibble: n,
+                hi: n * 4 + 3,
+                lo: n * 4,
+                digit,
+            });
+        }
+        decimal = decimal * 10 + digit;
+    }
+    Ok(decimal)
+}
+
+/// 取 64 位数值（负数按补码）中第 n 个 width 位宽的字段，第 0 个为最低位
+fn field_of(function: &'static str, value: i128, n: i128, width: u32) -> Result<i128, Message> {
+    let count = 64 / width as i128;
+    if !(0..count).contains(&n) {
+        return Err(Message::FieldIndexRange {
+            function,
+            index: n,
+            max: count - 1,
+        });
+    }
+    let word = machine_word(value).0;
+    Ok((word >> (n as u32 * width) & ((1 << width) - 1)) as i128)
+}
+
+/// 依次修改第一个参数中由其余参数指定的位（0..63），如 setbit[v, 3, 7]
+/// 64 位范围内的负数按补码修改，结果仍按 i64 解释，如 clrbit[-1, 63] 为 i64::MAX
+fn modify_bits(
+    function: &'static str,
+    args: &[i128],
+    options: &EvalOptions,
+    modify: fn(i128, i128) -> i128,
+) -> Result<i128, Message> {
+    let mut value = args[0];
+    for &n in &args[1..] {
+        if !(0..64).contains(&n) {
+            return Err(Message::BitIndexRange { function, bit: n });
+        }
+        value = modify(value, 1 << n);
+    }
+    if (i64::MIN as i128..0).contains(&args[0]) {
+        value = value as i64 as i128;
+    }
+    Ok(wrap_result(value, options))
+}
+
+/// 检查符号扩展和零扩展的位宽是否在 1..64 之间
+fn extend_width(width: i128) -> Result<u32, Message> {
+    if (1..=64).contains(&width) {
+        Ok(width as u32)
+    } else {
+        Err(Message::ExtendWidthRange { width })
+    }
+}
+
+/// 检查数值能否放进 width 位（负数按补码），返回截断后的位模式
+fn fit_width(function: &'static str, value: i128, width: u32) -> Result<u64, Message> {
+    let min = -(1i128 << (width - 1));
+    let max = (1i128 << width) - 1;
+    if !(min..=max).contains(&value) {
+        let shown = if value < 0 {
+            value.to_string()
+        } else {
+            format!("0x{:X}", value)
+        };
+        return Err(Message::ArgumentTooWide {
+            function,
+            width,
+            value: shown,
+        });
+    }
+    Ok(value as u64 & (u64::MAX >> (64 - width)))
+}
+
+/// 检查按寄存器位宽计算的函数的位宽参数，只能是 8、16、32、64
+fn register_width(function: &'static str, width: i128) -> Result<u32, Message> {
+    match width {
+        8 | 16 | 32 | 64 => Ok(width as u32),
+        width => Err(Message::RegisterWidth { function, width }),
+    }
+}
+
+/// 检查循环移位的参数：数值先截断到位宽内，移位量按位宽取模
+fn rotate_operands(function: &'static str, args: &[i128]) -> Result<(u64, u32, u32), Message> {
+    let width = register_width(function, args[2])?;
+    let mask = u64::MAX >> (64 - width);
+    let shift = args[1].rem_euclid(width as i128) as u32;
+    Ok((args[0] as u64 & mask, shift, width))
+}
+
+/// 结果按无符号数计算，回绕模式下再截断为寄存器位宽（如有符号模式下最高位为 1 时为负数）
+fn wrap_result(value: i128, options: &EvalOptions) -> i128 {
+    if options.wrap {
+        options.truncate(value)
+    } else {
+        value
+    }
+}
+
+/// 在 width 位内循环左移，value 已截断到该位宽
+fn rotate_left(value: u64, shift: u32, width: u32) -> i128 {
+    let mask = u64::MAX >> (64 - width);
+    let rotated = if shift == 0 {
+        value
+    } else {
+        (value << shift | value >> (width - shift)) & mask
+    };
+    rotated as i128
+}
+
+/// 对齐值必须是 2 的幂
+fn check_alignment(align: i128) -> Result<i128, Message> {
+    if align > 0 && align & (align - 1) == 0 {
+        Ok(align)
+    } else {
+        Err(Message::AlignmentNotPowerOfTwo { align })
+    }
+}
+
+/// 一秒的纳秒数，period_ns 和 cycles 使用
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// 四舍五入的整数除法，正好是 .5 时向上舍入
+fn div_round(numerator: u128, denominator: u128) -> u128 {
+    let (quotient, remainder) = (numerator / denominator, numerator % denominator);
+    if remainder >= denominator - remainder {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+/// 检查 period_ns 和 cycles 的频率，必须为正数
+fn check_frequency(function: &'static str, frequency: i128) -> Result<u128, Message> {
+    if frequency > 0 {
+        Ok(frequency as u128)
+    } else {
+        Err(Message::FrequencyNotPositive {
+            function,
+            value: frequency,
+        })
+    }
+}
+
+/// 按机器字解释数值，返回其位模式和位宽：64 位范围内的数（负数按补码）为 64 位，否则为 128 位
+fn machine_word(value: i128) -> (u128, u32) {
+    if (i64::MIN as i128..=u64::MAX as i128).contains(&value) {
+        (value as u64 as u128, 64)
+    } else {
+        (value as u128, 128)
+    }
+}
+
+pub(crate) fn lookup_function(name: &str) -> Option<&'static Function> {
+    FUNCTIONS.iter().find(|function| function.name == name)
+}
+
+/// 辗转相除求最大公约数
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// 浮点模式下应用一元操作符，只支持负号
+fn apply_float_unary_operator(op: Operator, value: f64) -> Result<f64, Message> {
+    match op {
+        Operator::Sub => Ok(-value),
+        _ => Err(Message::FloatOperator { op }),
+    }
+}
+
+/// 浮点模式下应用二元操作符，除零按 IEEE 754 得到 inf 或 NaN，不报错
+fn apply_float_operator(op: Operator, left: f64, right: f64) -> Result<f64, Message> {
+    match op {
+        Operator::Add => Ok(left + right),
+        Operator::Sub => Ok(left - right),
+        Operator::Mul => Ok(left * right),
+        Operator::Div => Ok(left / right),
+        Operator::Mod => Ok(left % right),
+        Operator::Pow => Ok(left.powf(right)),
+        op if op.is_comparison() => Ok(if op.compare(left, right) { 1.0 } else { 0.0 }),
+        _ => Err(Message::FloatOperator { op }),
+    }
+}
+
+/// 求值过程中需要在结果之后补充说明的信息
+#[derive(Debug, Clone)]
+pub(crate) enum EvalNote {
+    /// 不能整除的一次除法，给出余数，提醒结果被截断
+    /// 被除数是时间时 duration 为 Some(除数是否也是时间)，此时按时间单位显示
+    InexactDivision {
+        left: i128,
+        right: i128,
+        quotient: i128,
+        remainder: i128,
+        duration: Option<bool>,
+    },
+    /// 按固定位宽计算的函数（如 rotl），按该位宽补零显示结果
+    Width {
+        function: &'static str,
+        value: i128,
+        width: u32,
+    },
+    /// mulwide 的完整乘积，分为高低两个 64 位字
+    WideProduct { high: u64, low: u64, unsigned: bool },
+    /// 饱和模式下被限制到寄存器最值的一次运算；exact 为饱和前的结果，连 i128 也放不下时为 None
+    Saturated {
+        expression: String,
+        exact: Option<i128>,
+        result: i128,
+        register: String,
+    },
+}
+
+impl std::fmt::Display for EvalNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            EvalNote::InexactDivision {
+                left,
+                right,
+                quotient,
+                remainder,
+                duration: Some(right_is_time),
+            } => {
+                // 时间除以时间得到普通的数，除以普通的数得到时间，余数总是时间
+                let right = match right_is_time {
+                    true => human_duration(right),
+                    false => right.to_string(),
+                };
+                let quotient = match right_is_time {
+                    true => quotient.to_string(),
+                    false => human_duration(quotient),
+                };
+                f.write_str(&tr!(
+                    "{} / {} = {} 余 {}",
+                    "{} / {} = {} remainder {}",
+                    human_duration(left),
+                    right,
+                    quotient,
+                    human_duration(remainder)
+                ))
+            }
+            EvalNote::InexactDivision {
+                left,
+                right,
+                quotient,
+                remainder,
+                duration: None,
+            } => f.write_str(&tr!(
+                "{} / {} = {} 余 {} (≈{:.4})",
+                "{} / {} = {} remainder {} (≈{:.4})",
+                left,
+                right,
+                quotient,
+                remainder,
+                left as f64 / right as f64
+            )),
+            EvalNote::Width {
+                function,
+                value,
+                width,
+            } => {
+                // 回绕模式下的负数只显示其低 width 位的补码
+                let hex = format!(
+                    "0x{:0digits$X}",
+                    value as u128 & (u128::MAX >> (128 - width)),
+                    digits = width as usize / 4
+                );
+                f.write_str(&tr!(
+                    "{} 结果 ({} 位): {}",
+                    "{} result ({} bits): {}",
+                    function,
+                    width,
+                    hex
+                ))
+            }
+            EvalNote::WideProduct {
+                high,
+                low,
+                unsigned,
+            } => {
+                let product = (high as u128) << 64 | low as u128;
+                let decimal = if unsigned {
+                    product.to_string()
+                } else {
+                    (product as i128).to_string()
+                };
+                f.write_str(&tr!(
+                    "mulwide 乘积: {} = 高 64 位 0x{:016X} : 低 64 位 0x{:016X}",
+                    "mulwide product: {} = high 64 bits 0x{:016X} : low 64 bits 0x{:016X}",
+                    decimal,
+                    high,
+                    low
+                ))
+            }
+            EvalNote::Saturated {
+                ref expression,
+                exact,
+                result,
+                ref register,
+            } => match exact {
+                Some(exact) => f.write_str(&tr!(
+                    "饱和 (saturated): {} = {} 超出 {} 范围，结果限制为 {}",
+                    "saturated: {} = {} is out of {} range, result clamped to {}",
+                    expression,
+                    exact,
+                    register,
+                    result
+                )),
+                None => f.write_str(&tr!(
+                    "饱和 (saturated): {} 超出 {} 范围，结果限制为 {}",
+                    "saturated: {} is out of {} range, result clamped to {}",
+                    expression,
+                    register,
+                    result
+                )),
+            },
+        }
+    }
+}
+
+/// 表达式求值使用的数值类型：默认为整数 (i128)，浮点模式下为 f64
+pub(crate) trait Value: Copy + std::fmt::Display {
+    /// 转换整数字面量
+    fn from_int(value: i128, options: &EvalOptions) -> Result<Self, Message>;
+    /// 转换小数字面量
+    fn from_float(value: f64) -> Result<Self, Message>;
+    /// 转换带时间单位的字面量，除量纲检查外都按纳秒数计算
+    fn from_duration(nanos: i128, options: &EvalOptions) -> Result<Self, Message> {
+        Self::from_int(nanos, options)
+    }
+    /// 保存结果，供 ans 和变量使用
+    fn save(self) -> Saved;
+    fn apply_unary(op: Operator, value: Self, options: &EvalOptions) -> Result<Self, Message>;
+    fn apply(op: Operator, left: Self, right: Self, options: &EvalOptions)
+    -> Result<Self, Message>;
+
+    /// 调用内置函数，参数个数已经检查过；需要按固定位宽显示的结果记录到 notes 中
+    fn call(
+        function: &Function,
+        args: &[Self],
+        options: &EvalOptions,
+        notes: &mut Vec<EvalNote>,
+    ) -> Result<Self, Message>;
+
+    /// 提取 value 的第 hi 到 lo 位并右移到最低位 (v[hi:lo])
+    fn extract_bits(
+        value: Self,
+        hi: Self,
+        lo: Self,
+        options: &EvalOptions,
+    ) -> Result<Self, Message>;
+
+    /// 除法结果是否被截断，只有整数除法会截断
+    fn inexact_division(_left: Self, _right: Self, _quotient: Self) -> Option<EvalNote> {
+        None
+    }
+
+    /// 饱和模式下一元运算的结果是否被限制过，只有整数会饱和
+    fn unary_saturation(
+        _op: Operator,
+        _value: Self,
+        _result: Self,
+        _options: &EvalOptions,
+    ) -> Option<EvalNote> {
+        None
+    }
+
+    /// 饱和模式下二元运算的结果是否被限制过，只有整数会饱和
+    fn saturation(
+        _op: Operator,
+        _left: Self,
+        _right: Self,
+        _result: Self,
+        _options: &EvalOptions,
+    ) -> Option<EvalNote> {
+        None
+    }
+}
+
+/// 饱和模式下按不饱和的选项重新计算一次，出错（如无符号数减成负数）或结果不同说明发生了饱和
+fn saturation_note(
+    result: i128,
+    options: &EvalOptions,
+    exact: impl FnOnce(&EvalOptions) -> Result<i128, Message>,
+    describe: impl FnOnce() -> String,
+) -> Option<EvalNote> {
+    if !options.saturate {
+        return None;
+    }
+    let exact = exact(&options.exact()).ok();
+    (exact != Some(result)).then(|| EvalNote::Saturated {
+        expression: describe(),
+        exact,
+        result,
+        register: options.register_name(),
+    })
+}
+
+/// 提取第 hi 到 lo 位并右移到最低位，调用前已检查 lo <= hi < 128
+/// v[hi:lo] 和 --fields 共用
+pub(crate) fn bit_field(value: i128, hi: u32, lo: u32) -> i128 {
+    // 在 u128 上计算掩码，提取全部 128 位时也不会溢出
+    let mask = u128::MAX >> (127 - (hi - lo));
+    ((value >> lo) as u128 & mask) as i128
+}
+
+impl Value for i128 {
+    fn from_int(value: i128, options: &EvalOptions) -> Result<Self, Message> {
+        options.check_literal(value)
+    }
+
+    fn from_float(value: f64) -> Result<Self, Message> {
+        Err(Message::FloatInIntegerMode { value })
+    }
+
+    fn save(self) -> Saved {
+        Saved::Int(self)
+    }
+
+    fn apply_unary(op: Operator, value: Self, options: &EvalOptions) -> Result<Self, Message> {
+        apply_unary_operator(op, value, options)
+    }
+
+    fn apply(
+        op: Operator,
+        left: Self,
+        right: Self,
+        options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        apply_operator(op, left, right, options)
+    }
+
+    fn call(
+        function: &Function,
+        args: &[Self],
+        options: &EvalOptions,
+        notes: &mut Vec<EvalNote>,
+    ) -> Result<Self, Message> {
+        let value = wrap_result(options.reduce((function.apply)(args, options)?), options);
+        // 回绕模式下结果只保留低 64 位，完整乘积另外给出
+        if function.name == "mulwide" {
+            let product = wide_product(args[0], args[1], options)?;
+            notes.push(EvalNote::WideProduct {
+                high: (product >> 64) as u64,
+                low: product as u64,
+                unsigned: options.unsigned,
+            });
+        }
+        notes.extend(saturation_note(
+            value,
+            options,
+            |exact| (function.apply)(args, exact),
+            || {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                format!("{}[{}]", function.name, args.join(", "))
+            },
+        ));
+        if let Some(index) = function.width_arg {
+            notes.push(EvalNote::Width {
+                function: function.name,
+                value,
+                width: args[index] as u32,
+            });
+        }
+        Ok(value)
+    }
+
+    /// 位的范围与移位量相同，由当前模式的位宽决定：默认为 0..=127，
+    /// --wrap、--saturate 时为寄存器的位宽（默认 0..=63）
+    fn extract_bits(
+        value: Self,
+        hi: Self,
+        lo: Self,
+        options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        let bits = options.bits() as i128;
+        if !(0..bits).contains(&hi) || !(0..bits).contains(&lo) {
+            return Err(Message::BitRangeOutOfRange {
+                hi,
+                lo,
+                max: bits - 1,
+            });
+        }
+        if hi < lo {
+            return Err(Message::BitRangeReversed { hi, lo });
+        }
+        Ok(bit_field(value, hi as u32, lo as u32))
+    }
+
+    fn inexact_division(left: Self, right: Self, quotient: Self) -> Option<EvalNote> {
+        // i64::MIN / -1 这类回绕的情况 checked_rem 返回 None，余数视为 0
+        let remainder = left.checked_rem(right).filter(|r| *r != 0)?;
+        Some(EvalNote::InexactDivision {
+            left,
+            right,
+            quotient,
+            remainder,
+            duration: None,
+        })
+    }
+
+    fn unary_saturation(
+        op: Operator,
+        value: Self,
+        result: Self,
+        options: &EvalOptions,
+    ) -> Option<EvalNote> {
+        saturation_note(
+            result,
+            options,
+            |exact| apply_unary_operator(op, value, exact),
+            || format!("{}({})", op, value),
+        )
+    }
+
+    fn saturation(
+        op: Operator,
+        left: Self,
+        right: Self,
+        result: Self,
+        options: &EvalOptions,
+    ) -> Option<EvalNote> {
+        saturation_note(
+            result,
+            options,
+            |exact| apply_operator(op, left, right, exact),
+            || format!("{} {} {}", left, op, right),
+        )
+    }
+}
+
+impl Value for f64 {
+    // 整数字面量自动提升为浮点数
+    fn from_int(value: i128, _options: &EvalOptions) -> Result<Self, Message> {
+        Ok(value as f64)
+    }
+
+    fn from_float(value: f64) -> Result<Self, Message> {
+        Ok(value)
+    }
+
+    fn save(self) -> Saved {
+        Saved::Float(self)
+    }
+
+    fn apply_unary(op: Operator, value: Self, _options: &EvalOptions) -> Result<Self, Message> {
+        apply_float_unary_operator(op, value)
+    }
+
+    fn apply(
+        op: Operator,
+        left: Self,
+        right: Self,
+        _options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        apply_float_operator(op, left, right)
+    }
+
+    fn call(
+        function: &Function,
+        args: &[Self],
+        _options: &EvalOptions,
+        _notes: &mut Vec<EvalNote>,
+    ) -> Result<Self, Message> {
+        match function.name {
+            "abs" => Ok(args[0].abs()),
+            "min" => Ok(args[0].min(args[1])),
+            "max" => Ok(args[0].max(args[1])),
+            function => Err(Message::FloatFunction { function }),
+        }
+    }
+
+    fn extract_bits(
+        _value: Self,
+        _hi: Self,
+        _lo: Self,
+        _options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        Err(Message::FloatBitRange)
+    }
+}
+
+/// 只检查表达式结构时使用的数值类型 (--check)
+/// 所有运算都直接成功，求值过程只检查括号、操作数、函数参数等结构，
+/// 不会因为除零、溢出之类只有真正计算时才出现的错误而失败
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Unevaluated;
+
+// 跟踪求值时 (-dd) 没有计算的值显示为 ?
+impl std::fmt::Display for Unevaluated {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("?")
+    }
+}
+
+impl Value for Unevaluated {
+    fn from_int(_value: i128, _options: &EvalOptions) -> Result<Self, Message> {
+        Ok(Unevaluated)
+    }
+
+    fn from_float(_value: f64) -> Result<Self, Message> {
+        Ok(Unevaluated)
+    }
+
+    // 只有结构检查会读取这个值，数值本身没有意义
+    fn save(self) -> Saved {
+        Saved::Int(0)
+    }
+
+    fn apply_unary(_op: Operator, _value: Self, _options: &EvalOptions) -> Result<Self, Message> {
+        Ok(Unevaluated)
+    }
+
+    fn apply(
+        _op: Operator,
+        _left: Self,
+        _right: Self,
+        _options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        Ok(Unevaluated)
+    }
+
+    fn call(
+        _function: &Function,
+        _args: &[Self],
+        _options: &EvalOptions,
+        _notes: &mut Vec<EvalNote>,
+    ) -> Result<Self, Message> {
+        Ok(Unevaluated)
+    }
+
+    fn extract_bits(
+        _value: Self,
+        _hi: Self,
+        _lo: Self,
+        _options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        Ok(Unevaluated)
+    }
+}
+
+/// 量纲检查时的值：普通的数或时间长度
+/// 时间只能与时间相加减、比较和取余，可以乘以或除以普通的数，两个时间之比是普通的数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dimension {
+    Plain,
+    Time,
+}
+
+// 跟踪求值时 (-dd) 量纲检查的每一步显示量纲
+impl std::fmt::Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let text = match self {
+            Dimension::Plain => tr!("[数]", "[number]"),
+            Dimension::Time => tr!("[时间]", "[time]"),
+        };
+        f.write_str(&text)
+    }
+}
+
+impl Value for Dimension {
+    fn from_int(_value: i128, _options: &EvalOptions) -> Result<Self, Message> {
+        Ok(Dimension::Plain)
+    }
+
+    fn from_float(_value: f64) -> Result<Self, Message> {
+        Ok(Dimension::Plain)
+    }
+
+    fn from_duration(_nanos: i128, _options: &EvalOptions) -> Result<Self, Message> {
+        Ok(Dimension::Time)
+    }
+
+    // 只在量纲检查用的上下文副本中保存，数值没有意义
+    fn save(self) -> Saved {
+        match self {
+            Dimension::Plain => Saved::Int(0),
+            Dimension::Time => Saved::Duration(0),
+        }
+    }
+
+    fn apply_unary(op: Operator, value: Self, _options: &EvalOptions) -> Result<Self, Message> {
+        match (op, value) {
+            (Operator::Sub, _) | (_, Dimension::Plain) => Ok(value),
+            _ => Err(Message::DurationOperator { op }),
+        }
+    }
+
+    fn apply(
+        op: Operator,
+        left: Self,
+        right: Self,
+        _options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        use Dimension::{Plain, Time};
+        match (op, left, right) {
+            (_, Plain, Plain) => Ok(Plain),
+            (op, _, _) if op.is_comparison() && left == right => Ok(Plain),
+            (Operator::Add | Operator::Sub | Operator::Mod, Time, Time) => Ok(Time),
+            (Operator::Mul, Time, Time) => Err(Message::DurationProduct),
+            (Operator::Mul, _, _) => Ok(Time),
+            (Operator::Div, Time, Time) => Ok(Plain),
+            (Operator::Div, Time, Plain) => Ok(Time),
+            (Operator::Div, Plain, Time) => Err(Message::DivideByDuration),
+            (op, _, _)
+                if op.is_comparison()
+                    || matches!(op, Operator::Add | Operator::Sub | Operator::Mod) =>
+            {
+                Err(Message::DurationMismatch { op })
+            }
+            (op, _, _) => Err(Message::DurationOperator { op }),
+        }
+    }
+
+    /// abs min max 保持参数的量纲，cycles 的第一个参数可以是时间，其他函数只接受普通的数
+    fn call(
+        function: &Function,
+        args: &[Self],
+        _options: &EvalOptions,
+        _notes: &mut Vec<EvalNote>,
+    ) -> Result<Self, Message> {
+        match function.name {
+            "abs" | "min" | "max" => match args.split_first() {
+                Some((first, rest)) if rest.iter().all(|arg| arg == first) => Ok(*first),
+                Some(_) => Err(Message::DurationArgumentsMismatch {
+                    function: function.name,
+                }),
+                None => Ok(Dimension::Plain),
+            },
+            "cycles" if args.get(1) == Some(&Dimension::Plain) => Ok(Dimension::Plain),
+            _ if args.contains(&Dimension::Time) => Err(Message::DurationArgument {
+                function: function.name,
+            }),
+            _ => Ok(Dimension::Plain),
+        }
+    }
+
+    fn extract_bits(
+        value: Self,
+        hi: Self,
+        lo: Self,
+        _options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        if [value, hi, lo].contains(&Dimension::Time) {
+            Err(Message::DurationBitRange)
+        } else {
+            Ok(Dimension::Plain)
+        }
+    }
+}
+
+/// 带量纲的值：数值按 V 计算，同时按 Dimension 的规则检查量纲，量纲不对按计算错误处理
+/// 库的 evaluate 和命令行的各种模式都按它计算，因此 1s + 1 在任何地方都是错误
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Dimensioned<V> {
+    pub(crate) value: V,
+    pub(crate) dimension: Dimension,
+}
+
+impl<V> Dimensioned<V> {
+    fn plain(value: V) -> Self {
+        Dimensioned {
+            value,
+            dimension: Dimension::Plain,
+        }
+    }
+}
+
+// 跟踪求值时 (-dd) 时间带上单位
+impl<V: Value> std::fmt::Display for Dimensioned<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.dimension {
+            Dimension::Plain => write!(f, "{}", self.value),
+            Dimension::Time => write!(f, "{} ns", self.value),
+        }
+    }
+}
+
+impl<V: Value> Value for Dimensioned<V> {
+    fn from_int(value: i128, options: &EvalOptions) -> Result<Self, Message> {
+        V::from_int(value, options).map(Dimensioned::plain)
+    }
+
+    fn from_float(value: f64) -> Result<Self, Message> {
+        V::from_float(value).map(Dimensioned::plain)
+    }
+
+    fn from_duration(nanos: i128, options: &EvalOptions) -> Result<Self, Message> {
+        Ok(Dimensioned {
+            value: V::from_duration(nanos, options)?,
+            dimension: Dimension::Time,
+        })
+    }
+
+    // 整数的时间结果按时间保存，之后的表达式中同样检查量纲
+    fn save(self) -> Saved {
+        match (self.value.save(), self.dimension) {
+            (Saved::Int(nanos), Dimension::Time) => Saved::Duration(nanos),
+            (saved, _) => saved,
+        }
+    }
+
+    // 先检查量纲，量纲的错误优先于数值的错误
+    fn apply_unary(op: Operator, value: Self, options: &EvalOptions) -> Result<Self, Message> {
+        Ok(Dimensioned {
+            dimension: Dimension::apply_unary(op, value.dimension, options)?,
+            value: V::apply_unary(op, value.value, options)?,
+        })
+    }
+
+    fn apply(
+        op: Operator,
+        left: Self,
+        right: Self,
+        options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        Ok(Dimensioned {
+            dimension: Dimension::apply(op, left.dimension, right.dimension, options)?,
+            value: V::apply(op, left.value, right.value, options)?,
+        })
+    }
+
+    fn call(
+        function: &Function,
+        args: &[Self],
+        options: &EvalOptions,
+        notes: &mut Vec<EvalNote>,
+    ) -> Result<Self, Message> {
+        let dimensions: Vec<Dimension> = args.iter().map(|arg| arg.dimension).collect();
+        let values: Vec<V> = args.iter().map(|arg| arg.value).collect();
+        Ok(Dimensioned {
+            dimension: Dimension::call(function, &dimensions, options, notes)?,
+            value: V::call(function, &values, options, notes)?,
+        })
+    }
+
+    fn extract_bits(
+        value: Self,
+        hi: Self,
+        lo: Self,
+        options: &EvalOptions,
+    ) -> Result<Self, Message> {
+        Ok(Dimensioned {
+            dimension: Dimension::extract_bits(
+                value.dimension,
+                hi.dimension,
+                lo.dimension,
+                options,
+            )?,
+            value: V::extract_bits(value.value, hi.value, lo.value, options)?,
+        })
+    }
+
+    // 时间的除法按时间单位给出余数 (90s / 1min = 1 余 30 s)
+    fn inexact_division(left: Self, right: Self, quotient: Self) -> Option<EvalNote> {
+        let mut note = V::inexact_division(left.value, right.value, quotient.value)?;
+        if let EvalNote::InexactDivision { duration, .. } = &mut note
+            && left.dimension == Dimension::Time
+        {
+            *duration = Some(right.dimension == Dimension::Time);
+        }
+        Some(note)
+    }
+
+    fn unary_saturation(
+        op: Operator,
+        value: Self,
+        result: Self,
+        options: &EvalOptions,
+    ) -> Option<EvalNote> {
+        V::unary_saturation(op, value.value, result.value, options)
+    }
+
+    fn saturation(
+        op: Operator,
+        left: Self,
+        right: Self,
+        result: Self,
+        options: &EvalOptions,
+    ) -> Option<EvalNote> {
+        V::saturation(op, left.value, right.value, result.value, options)
+    }
+}
+
+/// 表达式求值错误，记录出错的元素位置（从 0 开始），便于指出问题所在
+/// 对外转换为 EnjoyError，kind 决定对应的错误种类
+#[derive(Debug)]
+pub(crate) struct ExprError {
+    token: Option<usize>,
+    kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ErrorKind {
+    /// 语法错误，如缺少操作数、位置不对的符号
+    Syntax(Message),
+    /// 操作符或函数计算时出错
+    Eval(Message),
+}
+
+impl ExprError {
+    /// 指向 token 的语法错误
+    fn at(token: usize, message: Message) -> Self {
+        ExprError {
+            token: Some(token),
+            kind: ErrorKind::Syntax(message),
+        }
+    }
+
+    /// 操作符、函数或字面量在计算时出错，如除零、溢出，出错时指向该元素
+    fn eval(token: usize, message: Message) -> Self {
+        ExprError {
+            token: Some(token),
+            kind: ErrorKind::Eval(message),
+        }
+    }
+
+    /// 子表达式中的错误位置换算为整个表达式中的位置
+    fn offset(self, start: usize) -> Self {
+        ExprError {
+            token: self.token.map(|token| token + start),
+            ..self
+        }
+    }
+
+    /// 括号内的错误位置换算为整个表达式中的位置，没有位置时指向括号本身
+    fn within(self, start: usize, bracket: usize) -> Self {
+        ExprError {
+            token: Some(self.token.map_or(bracket, |token| token + start)),
+            ..self
+        }
+    }
+}
+
+impl From<Message> for ExprError {
+    fn from(message: Message) -> Self {
+        ExprError {
+            token: None,
+            kind: ErrorKind::Syntax(message),
+        }
+    }
+}
+
+impl From<ExprError> for EnjoyError {
+    fn from(error: ExprError) -> Self {
+        let ExprError { token, kind } = error;
+        let error = match kind {
+            ErrorKind::Syntax(message) => return EnjoyError::Parse { message, token },
+            ErrorKind::Eval(Message::DivisionByZero) => EvalError::DivisionByZero,
+            ErrorKind::Eval(message) if message.is_overflow() => EvalError::Overflow(message),
+            ErrorKind::Eval(message) => EvalError::Other(message),
+        };
+        EnjoyError::Eval { error, token }
+    }
+}
+
+/// 弹出栈顶操作符及其操作数，并把结果压回数值栈，不能整除的除法记录到 notes 中
+/// 操作符栈中同时保存每个操作符的位置，出错时指向该操作符
+fn reduce<V: Value>(
+    values: &mut Vec<V>,
+    operators: &mut Vec<(PendingOp, usize)>,
+    options: &EvalOptions,
+    notes: &mut Vec<EvalNote>,
+) -> Result<(), ExprError> {
+    let (pending, index) = operators.pop().ok_or(Message::MissingOperator)?;
+    match pending {
+        PendingOp::Unary(op) => {
+            let value = values
+                .pop()
+                .ok_or_else(|| ExprError::at(index, Message::MissingOperand))?;
+            let result =
+                V::apply_unary(op, value, options).map_err(|e| ExprError::eval(index, e))?;
+            debug!(debug::TRACE, "{}{} = {}", "{}{} = {}", op, value, result);
+            notes.extend(V::unary_saturation(op, value, result, options));
+            values.push(result);
+        }
+        PendingOp::Binary(op) => {
+            let right = values
+                .pop()
+                .ok_or_else(|| ExprError::at(index, Message::MissingRightOperand))?;
+            let left = values
+                .pop()
+                .ok_or_else(|| ExprError::at(index, Message::MissingLeftOperand))?;
+            let result =
+                V::apply(op, left, right, options).map_err(|e| ExprError::eval(index, e))?;
+            debug!(
+                debug::TRACE,
+                "{} {} {} = {}", "{} {} {} = {}", left, op, right, result
+            );
+            if op == Operator::Div {
+                notes.extend(V::inexact_division(left, right, result));
+            }
+            notes.extend(V::saturation(op, left, right, result, options));
+            values.push(result);
+        }
+    }
+    Ok(())
+}
+
+/// 找出 start..end 范围内不在括号中的分隔符位置（如函数参数之间的逗号）
+pub(crate) fn top_level_positions(
+    tokens: &[ExprToken],
+    start: usize,
+    end: usize,
+    is_separator: fn(&ExprToken) -> bool,
+) -> Vec<usize> {
+    let mut depth = 0;
+    let mut positions = Vec::new();
+    for (k, token) in tokens.iter().enumerate().take(end).skip(start) {
+        if token.open_bracket().is_some() {
+            depth += 1;
+        } else if token.close_bracket().is_some() {
+            depth -= 1;
+        } else if depth == 0 && is_separator(token) {
+            positions.push(k);
+        }
+    }
+    positions
+}
+
+/// 引用上一个表达式结果的标识符
+pub(crate) const ANS: &str = "ans";
+
+/// 保存下来的结果（ans 或变量的值），按计算时的模式保存
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Saved {
+    Int(i128),
+    Float(f64),
+    /// 时间长度，按纳秒保存，之后的表达式中同样检查量纲
+    Duration(i128),
+}
+
+impl Saved {
+    /// 按当前的计算模式读取，与同样的字面量一样检查范围
+    fn load<V: Value>(self, options: &EvalOptions) -> Result<V, Message> {
+        match self {
+            Saved::Int(value) => V::from_int(value, options),
+            Saved::Float(value) => V::from_float(value),
+            Saved::Duration(nanos) => V::from_duration(nanos, options),
+        }
+    }
+}
+
+/// 表达式之间共享的求值上下文
+#[derive(Debug, Default, Clone)]
+pub(crate) struct EvalContext {
+    /// 上一个成功计算的结果 (ans)
+    pub(crate) ans: Option<Saved>,
+    /// 赋值过的变量，在之后的语句和表达式中都可以使用
+    pub(crate) variables: HashMap<String, Saved>,
+}
+
+/// 检查能否给 name 赋值：变量名不能与内置的常量、函数或 ans 重名
+pub(crate) fn check_variable_name(name: &str) -> Result<(), Message> {
+    if lookup_constant(name).is_some() {
+        Err(Message::AssignConstant {
+            name: name.to_string(),
+        })
+    } else if lookup_function(name).is_some() {
+        Err(Message::AssignFunction {
+            name: name.to_string(),
+        })
+    } else if name == ANS {
+        Err(Message::AssignAns)
+    } else {
+        Ok(())
+    }
+}
+
+/// 按顶层的 ; 拆分语句依次计算，返回最后一条语句的结果
+/// 形如 name = expr 的语句给变量赋值，结果为所赋的值
+pub(crate) fn evaluate_statements<V: Value>(
+    tokens: &[ExprToken],
+    options: &EvalOptions,
+    context: &mut EvalContext,
+    notes: &mut Vec<EvalNote>,
+) -> Result<V, ExprError> {
+    let separators = top_level_positions(tokens, 0, tokens.len(), |t| {
+        matches!(t, ExprToken::Semicolon)
+    });
+    let mut result = None;
+    let mut start = 0;
+    for end in separators.into_iter().chain([tokens.len()]) {
+        if start == end && !tokens.is_empty() {
+            return Err(ExprError::at(
+                end.min(tokens.len() - 1),
+                Message::EmptyStatement,
+            ));
+        }
+        let value = match &tokens[start..end] {
+            [ExprToken::Ident(name), ExprToken::Assign, expr @ ..] => {
+                check_variable_name(name).map_err(|e| ExprError::at(start, e))?;
+                if expr.is_empty() {
+                    return Err(ExprError::at(
+                        start + 1,
+                        Message::MissingAssignedValue { name: name.clone() },
+                    ));
+                }
+                let value: V = evaluate_expression(expr, options, context, notes)
+                    .map_err(|e| e.within(start + 2, start + 1))?;
+                context.variables.insert(name.clone(), value.save());
+                value
+            }
+            [_, ExprToken::Assign, ..] => {
+                return Err(ExprError::at(start, Message::AssignToNonVariable));
+            }
+            statement => evaluate_expression(statement, options, context, notes)
+                .map_err(|e| e.offset(start))?,
+        };
+        result = Some(value);
+        start = end + 1;
+    }
+    Ok(result.expect("至少有一条语句"))
+}
+
+/// 正在计算的一层括号：分组、函数参数列表或位段提取
+enum Nesting<V> {
+    /// 最外层的表达式
+    Top,
+    /// 分组括号 [ ... ]，open 为左括号的位置
+    Group { open: usize },
+    /// 函数参数列表 f[a, b]，name 为函数名的位置，args 为已经算完的参数
+    Call {
+        function: &'static Function,
+        name: usize,
+        args: Vec<V>,
+    },
+    /// 位段提取 v[hi:lo]，value 为被提取的数值，hi 为已经算完的高位及 : 的位置
+    BitRange {
+        open: usize,
+        value: V,
+        hi: Option<(V, usize)>,
+    },
+}
+
+impl<V> Nesting<V> {
+    /// 这一层左括号的位置
+    fn open(&self) -> Option<usize> {
+        match self {
+            Nesting::Top => None,
+            Nesting::Group { open } | Nesting::BitRange { open, .. } => Some(*open),
+            Nesting::Call { name, .. } => Some(name + 1),
+        }
+    }
+}
+
+/// 一层括号内的求值状态：括号内的每个子表达式（参数、高位、低位）各自使用操作符栈
+struct Frame<V> {
+    nesting: Nesting<V>,
+    start: usize,                       // 当前子表达式第一个元素的位置
+    values: Vec<V>,                     // 存储数字
+    operators: Vec<(PendingOp, usize)>, // 存储操作符及其位置
+    expect_operand: bool,               // 下一个元素应当是操作数（或一元操作符）
+}
+
+impl<V: Value> Frame<V> {
+    fn new(nesting: Nesting<V>, start: usize) -> Self {
+        Frame {
+            nesting,
+            start,
+            values: Vec::new(),
+            operators: Vec::new(),
+            expect_operand: true,
+        }
+    }
+
+    /// 当前子表达式到 end 为止，计算剩余的操作符得到它的值，并为下一个子表达式重置状态
+    fn finish(
+        &mut self,
+        end: usize,
+        options: &EvalOptions,
+        notes: &mut Vec<EvalNote>,
+    ) -> Result<V, ExprError> {
+        if self.expect_operand && end > self.start {
+            return Err(ExprError::at(end - 1, Message::TrailingOperator));
+        }
+
+        // 处理剩余的操作符，此时栈中的优先级自底向上递增
+        while !self.operators.is_empty() {
+            reduce(&mut self.values, &mut self.operators, options, notes)?;
+        }
+        let value = self.values.pop().ok_or(Message::EvaluationFailed)?;
+
+        self.start = end + 1;
+        self.values.clear();
+        self.expect_operand = true;
+        Ok(value)
+    }
+}
+
+/// 计算表达式结果，数值类型决定按整数还是浮点数计算
+/// 计算过程中需要补充说明的信息（如不能整除的除法）会追加到 notes 中
+/// 只从左到右扫描一遍，括号用显式的栈保存，嵌套深度不受调用栈限制
+fn evaluate_expression<V: Value>(
+    tokens: &[ExprToken],
+    options: &EvalOptions,
+    context: &EvalContext,
+    notes: &mut Vec<EvalNote>,
+) -> Result<V, ExprError> {
+    let mut frames = vec![Frame::new(Nesting::Top, 0)];
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let frame = frames.last_mut().expect("最外层的求值状态不会出栈");
+        match &tokens[i] {
+            ExprToken::Number(num) => {
+                if !frame.expect_operand {
+                    return Err(ExprError::at(
+                        i,
+                        Message::OperatorExpected {
+                            token: num.to_string(),
+                        },
+                    ));
+                }
+                frame
+                    .values
+                    .push(V::from_int(*num, options).map_err(|e| ExprError::eval(i, e))?);
+                frame.expect_operand = false;
+            }
+            ExprToken::Float(num) => {
+                if !frame.expect_operand {
+                    return Err(ExprError::at(
+                        i,
+                        Message::OperatorExpected {
+                            token: num.to_string(),
+                        },
+                    ));
+                }
+                frame
+                    .values
+                    .push(V::from_float(*num).map_err(|e| ExprError::eval(i, e))?);
+                frame.expect_operand = false;
+            }
+            ExprToken::Duration(nanos) => {
+                if !frame.expect_operand {
+                    return Err(ExprError::at(
+                        i,
+                        Message::OperatorExpected {
+                            token: tokens[i].to_string(),
+                        },
+                    ));
+                }
+                frame
+                    .values
+                    .push(V::from_duration(*nanos, options).map_err(|e| ExprError::eval(i, e))?);
+                frame.expect_operand = false;
+            }
+            ExprToken::Ident(name) => {
+                if !frame.expect_operand {
+                    return Err(ExprError::at(
+                        i,
+                        Message::OperatorExpected {
+                            token: name.clone(),
+                        },
+                    ));
+                }
+                // 不是函数名时读取保存的值：ans 或变量；没有同名变量的 FFh 是十六进制数
+                let Some(function) = lookup_function(name) else {
+                    let saved = if name == ANS {
+                        context.ans.ok_or(Message::NoPreviousResult)
+                    } else if let Some(saved) = context.variables.get(name) {
+                        Ok(*saved)
+                    } else if strip_radix_suffix(name, 'h', 16).is_some() {
+                        parse_number(name).map(Saved::Int)
+                    } else {
+                        Err(unknown_identifier(name))
+                    };
+                    frame.values.push(
+                        saved
+                            .and_then(|saved| saved.load(options))
+                            .map_err(|e| ExprError::eval(i, e))?,
+                    );
+                    frame.expect_operand = false;
+                    i += 1;
+                    continue;
+                };
+                if tokens
+                    .get(i + 1)
+                    .and_then(ExprToken::open_bracket)
+                    .is_none()
+                {
+                    return Err(ExprError::at(
+                        i,
+                        Message::MissingArguments {
+                            function: name.clone(),
+                        },
+                    ));
+                }
+                // 参数从左括号之后开始，按不在括号中的逗号拆分
+                frames.push(Frame::new(
+                    Nesting::Call {
+                        function,
+                        name: i,
+                        args: Vec::new(),
+                    },
+                    i + 2,
+                ));
+                debug!(
+                    debug::TRACE,
+                    "进入函数 {} 的参数列表 (第 {} 个元素，第 {} 层)",
+                    "enter arguments of {} (token {}, depth {})",
+                    name,
+                    i + 2,
+                    frames.len() - 1
+                );
+                // 跳过左括号
+                i += 1;
+            }
+            ExprToken::Operator(op) if frame.expect_operand && op.can_be_unary() => {
+                // 一元操作符右结合，直接入栈，等操作数就绪后再计算
+                frame.operators.push((PendingOp::Unary(*op), i));
+            }
+            ExprToken::Operator(op) if op.is_unary() => {
+                return Err(ExprError::at(i, Message::UnaryAfterOperand { op: *op }));
+            }
+            ExprToken::Operator(op) => {
+                if frame.expect_operand {
+                    return Err(ExprError::at(i, Message::OperatorWithoutLeft { op: *op }));
+                }
+                // 先计算栈中优先级更高的部分；优先级相同时左结合的操作符也要先计算
+                while let Some((prev_op, _)) = frame.operators.last() {
+                    let prev = prev_op.precedence();
+                    if prev == op.precedence() && op.associativity() == Associativity::None {
+                        return Err(ExprError::at(i, Message::ChainedComparison { op: *op }));
+                    }
+                    if prev > op.precedence()
+                        || (prev == op.precedence() && op.associativity() == Associativity::Left)
+                    {
+                        reduce(&mut frame.values, &mut frame.operators, options, notes)?;
+                    } else {
+                        break;
+                    }
+                }
+                frame.operators.push((PendingOp::Binary(*op), i));
+                frame.expect_operand = true;
+            }
+            ExprToken::LeftParen | ExprToken::LeftRoundParen => {
+                let nesting = if frame.expect_operand {
+                    Nesting::Group { open: i }
+                } else {
+                    // 紧跟在操作数后面的括号是位段提取 v[hi:lo]，只作用于前一个操作数，
+                    // 结合得比一元操作符更紧，如 ~v[3:0] 先提取再取反
+                    let value = frame.values.pop().ok_or(Message::MissingOperand)?;
+                    Nesting::BitRange {
+                        open: i,
+                        value,
+                        hi: None,
+                    }
+                };
+                frames.push(Frame::new(nesting, i + 1));
+                debug!(
+                    debug::TRACE,
+                    "进入括号 {} (第 {} 个元素，第 {} 层)",
+                    "enter bracket {} (token {}, depth {})",
+                    tokens[i],
+                    i + 1,
+                    frames.len() - 1
+                );
+            }
+            ExprToken::Colon => match &frame.nesting {
+                Nesting::BitRange { hi: None, .. } => {
+                    if frame.start == i {
+                        return Err(ExprError::at(i, Message::BitRangeMissingBound));
+                    }
+                    let hi = frame
+                        .finish(i, options, notes)
+                        .map_err(|e| e.within(0, i))?;
+                    if let Nesting::BitRange { hi: slot, .. } = &mut frame.nesting {
+                        *slot = Some((hi, i));
+                    }
+                }
+                _ => return Err(ExprError::at(i, Message::MisplacedColon)),
+            },
+            ExprToken::Comma => match &frame.nesting {
+                Nesting::Call { name, args, .. } => {
+                    let name = *name;
+                    if frame.start == i {
+                        return Err(ExprError::at(
+                            i,
+                            Message::EmptyArgument {
+                                function: tokens[name].to_string(),
+                                index: args.len() + 1,
+                            },
+                        ));
+                    }
+                    let arg = frame
+                        .finish(i, options, notes)
+                        .map_err(|e| e.within(0, name))?;
+                    if let Nesting::Call { args, .. } = &mut frame.nesting {
+                        args.push(arg);
+                    }
+                }
+                _ => return Err(ExprError::at(i, Message::MisplacedComma)),
+            },
+            ExprToken::Assign => {
+                return Err(ExprError::at(i, Message::MisplacedAssign));
+            }
+            ExprToken::Semicolon => {
+                return Err(ExprError::at(i, Message::MisplacedSemicolon));
+            }
+            token @ (ExprToken::RightParen | ExprToken::RightRoundParen) => {
+                let close = token.close_bracket().unwrap();
+                let Some(open) = frame.nesting.open() else {
+                    return Err(ExprError::at(
+                        i,
+                        Message::UnexpectedClosingBracket { close },
+                    ));
+                };
+                // 两种括号共用同一个嵌套栈，但必须成对
+                let open_char = tokens[open].open_bracket().unwrap();
+                if close != matching_bracket(open_char) {
+                    return Err(ExprError::at(
+                        open,
+                        Message::MismatchedBrackets {
+                            open: open_char,
+                            close,
+                        },
+                    ));
+                }
+                let empty = frame.start == i;
+
+                let mut frame = frames.pop().unwrap();
+                let value = match std::mem::replace(&mut frame.nesting, Nesting::Top) {
+                    Nesting::Top => unreachable!("最外层没有左括号"),
+                    // 没有参数的函数调用，如 f[]
+                    Nesting::Call { function, name, .. } if empty && open + 1 == i => {
+                        check_arity(tokens, name, function, 0)?;
+                        V::call(function, &[], options, notes)
+                            .map_err(|e| ExprError::eval(name, e))?
+                    }
+                    _ if empty && open + 1 == i => {
+                        return Err(ExprError::at(
+                            open,
+                            Message::EmptyBrackets {
+                                open: open_char,
+                                close,
+                            },
+                        ));
+                    }
+                    Nesting::Group { open } => frame
+                        .finish(i, options, notes)
+                        .map_err(|e| e.within(0, open))?,
+                    Nesting::Call {
+                        function,
+                        name,
+                        mut args,
+                    } => {
+                        if empty {
+                            return Err(ExprError::at(
+                                i,
+                                Message::EmptyArgument {
+                                    function: tokens[name].to_string(),
+                                    index: args.len() + 1,
+                                },
+                            ));
+                        }
+                        let arg = frame
+                            .finish(i, options, notes)
+                            .map_err(|e| e.within(0, name))?;
+                        args.push(arg);
+                        check_arity(tokens, name, function, args.len())?;
+                        let result = V::call(function, &args, options, notes)
+                            .map_err(|e| ExprError::eval(name, e))?;
+                        debug!(
+                            debug::TRACE,
+                            "{}[{}] = {}",
+                            "{}[{}] = {}",
+                            function.name,
+                            args.iter()
+                                .map(ToString::to_string)
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            result
+                        );
+                        result
+                    }
+                    Nesting::BitRange { open, value, hi } => {
+                        let Some((hi, colon)) = hi else {
+                            return Err(ExprError::at(
+                                open,
+                                Message::BitRangeOperatorExpected { open: open_char },
+                            ));
+                        };
+                        if empty {
+                            return Err(ExprError::at(colon, Message::BitRangeMissingBound));
+                        }
+                        let lo = frame
+                            .finish(i, options, notes)
+                            .map_err(|e| e.within(0, colon))?;
+                        let bits = V::extract_bits(value, hi, lo, options)
+                            .map_err(|e| ExprError::eval(open, e))?;
+                        debug!(
+                            debug::TRACE,
+                            "{}[{}:{}] = {}", "{}[{}:{}] = {}", value, hi, lo, bits
+                        );
+                        bits
+                    }
+                };
+
+                debug!(
+                    debug::TRACE,
+                    "离开括号 {}{} (第 {} 个元素，第 {} 层) = {}",
+                    "leave bracket {}{} (token {}, depth {}) = {}",
+                    open_char,
+                    close,
+                    i + 1,
+                    frames.len(),
+                    value
+                );
+                let parent = frames.last_mut().expect("最外层的求值状态不会出栈");
+                parent.values.push(value);
+                parent.expect_operand = false;
+            }
+        }
+        i += 1;
+    }
+
+    if let Some(open) = frames.get(1).and_then(|frame| frame.nesting.open()) {
+        let open_char = tokens[open].open_bracket().unwrap();
+        return Err(ExprError::at(
+            open,
+            Message::UnclosedBracket { open: open_char },
+        ));
+    }
+    frames.pop().unwrap().finish(tokens.len(), options, notes)
+}
+
+/// 检查函数调用的参数个数，出错时指向函数名
+fn check_arity(
+    tokens: &[ExprToken],
+    name: usize,
+    function: &Function,
+    count: usize,
+) -> Result<(), ExprError> {
+    let function_name = tokens[name].to_string();
+    let error = match function.arity {
+        Arity::Exact(expected) if count != expected => Message::ArgumentCount {
+            function: function_name,
+            expected,
+            count,
+        },
+        Arity::AtLeast(min) if count < min => Message::TooFewArguments {
+            function: function_name,
+            min,
+            count,
+        },
+        _ => return Ok(()),
+    };
+    Err(ExprError::at(name, error))
+}
+
+/// 把表达式字符串拆分为表达式元素，写法与命令行中的 --calc 相同
+///
+/// ```
+/// use enjoy::{ExprToken, Operator, parse_expression};
+///
+/// let tokens = parse_expression("0x10 << 2").unwrap();
+/// assert!(matches!(
+///     tokens.as_slice(),
+///     [ExprToken::Number(16), ExprToken::Operator(Operator::Shl), ExprToken::Number(2)]
+/// ));
+/// assert!(parse_expression("1 + 0xZZ").is_err());
+/// ```
+pub fn parse_expression(input: &str) -> Result<Vec<ExprToken>, EnjoyError> {
+    let tokens = tokenize(input).map_err(EnjoyError::parse)?;
+    Ok(tokens.into_iter().map(|(token, _)| token).collect())
+}
+
+/// 按默认模式（有符号 64 位，溢出时报错）计算表达式，可以使用 ; 分隔多条语句并给变量赋值
+///
+/// ```
+/// use enjoy::{EnjoyError, EvalError, evaluate, parse_expression};
+///
+/// let tokens = parse_expression("base = 0x40000000 ; base + 0x2000").unwrap();
+/// assert_eq!(evaluate(&tokens).unwrap(), 0x4000_2000);
+///
+/// let error = evaluate(&parse_expression("1 / 0").unwrap()).unwrap_err();
+/// assert!(matches!(
+///     error,
+///     EnjoyError::Eval { error: EvalError::DivisionByZero, token: Some(1) }
+/// ));
+/// assert_eq!(error.exit_code(), 3);
+///
+/// // 时间只能与时间相加减，两个时间也不能相乘
+/// assert_eq!(evaluate(&parse_expression("2s + 350ms").unwrap()).unwrap(), 2_350_000_000);
+/// assert!(evaluate(&parse_expression("1s + 1").unwrap()).is_err());
+/// assert!(evaluate(&parse_expression("1s x 1s").unwrap()).is_err());
+/// ```
+pub fn evaluate(tokens: &[ExprToken]) -> Result<i64, EnjoyError> {
+    check_limits(tokens, DEFAULT_MAX_TOKENS, DEFAULT_MAX_DEPTH)?;
+    let mut notes = Vec::new();
+    let value = evaluate_statements::<Dimensioned<i128>>(
+        tokens,
+        &EvalOptions::default(),
+        &mut EvalContext::default(),
+        &mut notes,
+    )?
+    .value;
+    i64::try_from(value).map_err(|_| EnjoyError::Eval {
+        error: EvalError::Overflow(Message::ResultOutOfI64 { value }),
+        token: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按库的 evaluate 计算一个表达式字符串
+    fn calc(input: &str) -> Result<i64, EnjoyError> {
+        evaluate(&parse_expression(input)?)
+    }
+
+    /// 按给定选项以 i128 计算一个表达式字符串
+    fn calc_with(input: &str, options: &EvalOptions) -> Result<i128, EnjoyError> {
+        let tokens = parse_expression(input)?;
+        Ok(evaluate_statements::<i128>(
+            &tokens,
+            options,
+            &mut EvalContext::default(),
+            &mut Vec::new(),
+        )?)
+    }
+
+    /// 按 --calc 的参数拆分后计算
+    fn calc_args(args: &[&str]) -> i64 {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        evaluate(&tokenize_args(&args).unwrap().0).unwrap()
+    }
+
+    #[test]
+    fn prefix_without_digits() {
+        for literal in ["0b", "0x", "0o", "-0b"] {
+            assert!(
+                matches!(
+                    parse_number(literal),
+                    Err(Message::PrefixWithoutDigits { .. })
+                ),
+                "{}",
+                literal
+            );
+        }
+        assert_eq!(parse_number("0b0"), Ok(0));
+        assert_eq!(parse_number("0b1"), Ok(1));
+        // b 后缀的二进制数不受影响
+        assert_eq!(parse_number("10b"), Ok(2));
+        assert_eq!(parse_number("0bh"), Ok(0xB));
+        assert!(calc("0b + 1").is_err());
+    }
+
+    #[test]
+    fn assembly_literals() {
+        for (literal, value) in [
+            ("0FFh", 0xFF),
+            ("FFh", 0xFF),
+            ("0ffH", 0xFF),
+            ("10h", 0x10),
+            ("DEAD_BEEFh", 0xDEAD_BEEF),
+            ("$FF", 0xFF),
+            ("$1_0000", 0x1_0000),
+            ("1010b", 0b1010),
+            ("1010B", 0b1010),
+            ("-1010b", -0b1010),
+        ] {
+            assert_eq!(parse_number(literal), Ok(value), "{}", literal);
+        }
+        for literal in ["GGh", "h", "0FGh", "$", "$G", "102b", "b"] {
+            assert!(parse_number(literal).is_err(), "{}", literal);
+        }
+        assert!(matches!(
+            parse_number("12b"),
+            Err(Message::BinarySuffix { .. })
+        ));
+        // 在完整的表达式中使用
+        assert_eq!(calc("0FFh + $10 - 1010b").unwrap(), 0xFF + 0x10 - 0b1010);
+        assert_eq!(calc("FFh x 2").unwrap(), 0x1FE);
+        assert_eq!(calc("[ABh]").unwrap(), 0xAB);
+        assert_eq!(calc("1hr / 1s").unwrap(), 3600);
+        // 以字母开头时先按变量查找，没有同名变量时才是十六进制数
+        assert_eq!(calc("each").unwrap(), 0xEAC);
+        assert_eq!(calc("each = 5; each + 1").unwrap(), 6);
+        assert!(calc("GGh").unwrap_err().to_string().contains("GGh"));
+    }
+
+    #[test]
+    fn radix_literals() {
+        assert_eq!(parse_number("36#Z1G"), Ok(35 * 36 * 36 + 36 + 16));
+        assert_eq!(parse_number("36#z1g"), Ok(45412));
+        assert_eq!(parse_number("32#7V"), Ok(7 * 32 + 31));
+        assert_eq!(parse_number("2#1_0"), Ok(2));
+        assert_eq!(parse_number("-36#Z"), Ok(-35));
+        // 与 from_str_radix 的结果一致
+        for radix in [2, 3, 7, 8, 10, 16, 32, 36] {
+            for value in [0i64, 1, 35, 4096, 1 << 40, i64::MAX] {
+                let digits = crate::format::to_radix(value as i128, radix);
+                assert_eq!(
+                    i64::from_str_radix(&digits, radix).unwrap(),
+                    value,
+                    "{}#{}",
+                    radix,
+                    digits
+                );
+                assert_eq!(
+                    parse_number(&format!("{}#{}", radix, digits)),
+                    Ok(value as i128),
+                    "{}#{}",
+                    radix,
+                    digits
+                );
+            }
+        }
+        // 进制之外的数字给出出错的数字和进制
+        for (literal, digit, radix) in [("2#102", '2', 2), ("16#FG", 'G', 16), ("36#Z!", '!', 36)] {
+            assert_eq!(
+                parse_number(literal),
+                Err(Message::InvalidRadixDigit {
+                    digit,
+                    radix,
+                    literal: literal.to_string()
+                }),
+                "{}",
+                literal
+            );
+        }
+        // 进制的范围是 2 到 36
+        for radix in ["0", "1", "37", "x"] {
+            let literal = format!("{}#1", radix);
+            assert_eq!(
+                parse_number(&literal),
+                Err(Message::InvalidRadix {
+                    radix: radix.to_string(),
+                    literal: literal.clone()
+                }),
+                "{}",
+                literal
+            );
+        }
+        assert_eq!(parse_number("2#1"), Ok(1));
+        assert_eq!(parse_number("36#1"), Ok(1));
+        assert!(parse_number("16#").is_err());
+        assert!(parse_number("10#_1").is_err());
+        assert_eq!(calc("36#Z1G + 0x10").unwrap(), 45428);
+    }
+
+    #[test]
+    fn scientific_literals() {
+        assert_eq!(parse_number("1e6"), Ok(1_000_000));
+        assert_eq!(parse_number("25E+3"), Ok(25_000));
+        assert_eq!(parse_number("-25e3"), Ok(-25_000));
+        assert_eq!(parse_number("1.5e1"), Ok(15));
+        assert_eq!(parse_number("1.50e1"), Ok(15));
+        assert_eq!(parse_number("1e0"), Ok(1));
+        assert_eq!(parse_number("0e100"), Ok(0));
+        assert!(matches!(
+            parse_number("1.5e0"),
+            Err(Message::ScientificNotInteger { .. })
+        ));
+        assert!(matches!(
+            parse_number("1e-3"),
+            Err(Message::ScientificNegativeExponent { .. })
+        ));
+        // 十六进制中的 e 不是指数
+        assert_eq!(parse_number("0x1e6"), Ok(0x1e6));
+        assert_eq!(parse_number("0x1E6"), Ok(486));
+    }
+
+    #[test]
+    fn scientific_range_is_i64() {
+        assert_eq!(parse_number("1e18"), Ok(1_000_000_000_000_000_000));
+        assert_eq!(
+            parse_number("9.223372036854775807e18"),
+            Ok(i64::MAX as i128)
+        );
+        assert_eq!(
+            parse_number("-9.223372036854775808e18"),
+            Ok(i64::MIN as i128)
+        );
+        for literal in ["1e19", "9.223372036854775808e18", "1e40", "1e4294967296"] {
+            assert!(
+                matches!(parse_number(literal), Err(Message::ScientificRange { .. })),
+                "{}",
+                literal
+            );
+        }
+    }
+
+    #[test]
+    fn scientific_in_expressions() {
+        assert_eq!(calc("1e6 / 25e3").unwrap(), 40);
+        assert_eq!(calc("2 x 1e3 + 0x1e6").unwrap(), 2486);
+        assert_eq!(calc("1e3 ** 2").unwrap(), 1_000_000);
+        // 1E 容量后缀减 3 需要空格
+        assert_eq!(calc("1E - 3").unwrap(), (1 << 60) - 3);
+        assert!(calc("1e-3").is_err());
+    }
+
+    #[test]
+    fn bit_range_follows_the_register_width() {
+        assert_eq!(calc("0xDEADBEEF[27:24]").unwrap(), 0xE);
+        assert_eq!(calc("[0xDEAD + 0xBEEF00][15:8]").unwrap(), 0xCD);
+        // 默认按 128 位计算
+        assert_eq!(calc("(1 << 100)[127:100]").unwrap(), 1);
+        assert!(calc("1[128:0]").is_err());
+        assert!(calc("1[3:4]").is_err());
+        let wrap = EvalOptions {
+            wrap: true,
+            ..EvalOptions::default()
+        };
+        assert_eq!(i128::extract_bits(-1, 63, 0, &wrap), Ok(u64::MAX as i128));
+        assert!(matches!(
+            i128::extract_bits(1, 64, 0, &wrap),
+            Err(Message::BitRangeOutOfRange { max: 63, .. })
+        ));
+    }
+
+    #[test]
+    fn crc_functions_use_little_endian_bytes() {
+        // 0x34333231 的 4 个字节按小端序为 "1234"
+        assert_eq!(calc("crc32[0x34333231, 4]").unwrap(), 0x9BE3_E0A3);
+        assert_eq!(
+            calc("crc16[0x34333231, 4]").unwrap(),
+            crate::crc::crc16(b"1234") as i64
+        );
+    }
+
+    /// 优先级和结合性：期望值按 C 语言（乘方按 Python）的规则独立算出
+    #[test]
+    fn precedence_and_associativity_table() {
+        let cases: &[(&str, i64)] = &[
+            ("1 + 2 x 3", 7),
+            ("[1 + 2] x 3", 9),
+            ("2 x 3 + 4 x 5", 26),
+            ("10 - 3 - 2", 5),
+            ("10 - [3 - 2]", 9),
+            ("100 / 10 / 5", 2),
+            ("100 / [10 / 5]", 50),
+            ("2 x 3 % 4", 2),
+            ("7 % 4 x 3", 9),
+            ("17 % 5 % 3", 2),
+            ("- 7 / 2", -3),
+            ("-7 % 2", -1),
+            ("7 % -2", 1),
+            ("1 << 2 + 1", 8),
+            ("[1 << 2] + 1", 5),
+            ("256 >> 2 >> 1", 32),
+            ("1 << 4 >> 2", 4),
+            ("0xF0 | 0x0F & 0x3C", 0xFC),
+            ("0xFF ^ 0x0F", 0xF0),
+            ("0xF0 & 0xCC ^ 0x0F | 0x100", 0x1CF),
+            ("1 | 2 ^ 3 & 4", 3),
+            ("[1 | 2] ^ 3", 0),
+            ("6 & 3 << 1", 6),
+            ("1 + 2 & 7", 3),
+            ("2 ** 3 ** 2", 512),
+            ("[2 ** 3] ** 2", 64),
+            ("-2 ** 2", -4),
+            ("2 ** -0 + 1", 2),
+            ("2 x 3 ** 2", 18),
+            ("~0 & 0xF", 0xF),
+            ("~1 + 1", -1),
+            ("- - 5", 5),
+            ("~~5", 5),
+            ("2 x -3", -6),
+            ("5 - -3", 8),
+            ("1 + 2 == 3", 1),
+            ("1 | 2 == 3", 1),
+            ("2 < 1 + 2", 1),
+            ("[1 < 2] + [3 > 4]", 1),
+            ("0x10 >= 16", 1),
+            ("3 != 1 + 2", 0),
+            ("1 + [2 x [3 + [4 - 5]]]", 5),
+            ("[((1 + 2) x 3)]", 9),
+        ];
+        for (expression, expected) in cases {
+            assert_eq!(calc(expression).unwrap(), *expected, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn comparisons_do_not_chain() {
+        assert!(calc("1 < 2 < 3").is_err());
+        assert!(calc("1 == 1 == 1").is_err());
+        assert_eq!(calc("[1 < 2] & [2 < 3]").unwrap(), 1);
+    }
+
+    /// 直接计算 token 序列，不经过 check_limits 的数量和深度限制
+    fn evaluate_unlimited(tokens: &[ExprToken]) -> i128 {
+        evaluate_statements::<i128>(
+            tokens,
+            &EvalOptions::default(),
+            &mut EvalContext::default(),
+            &mut Vec::new(),
+        )
+        .unwrap()
+    }
+
+    /// 嵌套深度只受内存限制，总的工作量与 token 数量成正比；时间上限留给较慢的调试构建
+    #[test]
+    fn deep_nesting_and_long_expressions() {
+        let depth = 100_000;
+        let mut tokens = vec![ExprToken::LeftParen; depth];
+        tokens.push(ExprToken::Number(1));
+        for _ in 0..depth {
+            tokens.push(ExprToken::Operator(Operator::Add));
+            tokens.push(ExprToken::Number(1));
+            tokens.push(ExprToken::RightParen);
+        }
+        let start = std::time::Instant::now();
+        assert!(check_limits(&tokens, usize::MAX, usize::MAX).is_ok());
+        assert_eq!(evaluate_unlimited(&tokens), depth as i128 + 1);
+        // 1 - 1 + 1 - 1 ... + 41，超过 1M 个 token，也检查了减法的左结合
+        let mut tokens = vec![ExprToken::Number(1)];
+        for i in 0..499_999 {
+            let op = if i % 2 == 0 {
+                Operator::Sub
+            } else {
+                Operator::Add
+            };
+            tokens.push(ExprToken::Operator(op));
+            tokens.push(ExprToken::Number(1));
+        }
+        tokens.push(ExprToken::Operator(Operator::Add));
+        tokens.push(ExprToken::Number(41));
+        assert!(tokens.len() >= 1_000_000);
+        assert_eq!(evaluate_unlimited(&tokens), 41);
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(20),
+            "{:?}",
+            start.elapsed()
+        );
+    }
+
+    #[test]
+    fn limits_are_checked_before_evaluation() {
+        let nested = format!("{}1{}", "[".repeat(300), "]".repeat(300));
+        let error = calc(&nested).unwrap_err();
+        assert!(matches!(
+            error,
+            EnjoyError::Parse {
+                message: Message::TooDeep { max: 256 },
+                ..
+            }
+        ));
+        let flat = vec!["1"; 6_000].join("+");
+        assert!(matches!(
+            calc(&flat).unwrap_err(),
+            EnjoyError::Parse {
+                message: Message::TooManyTokens { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn subtraction_is_left_associative() {
+        assert_eq!(calc("10 - 3 - 2").unwrap(), 5);
+        assert_eq!(calc("1 - 2 - 3").unwrap(), -4);
+        assert_eq!(calc("10 - 3 + 2").unwrap(), 9);
+        assert_eq!(calc("10 - [3 - 2]").unwrap(), 9);
+        assert_eq!(calc("0x1000 - 0x40").unwrap(), 0xFC0);
+        // 每个参数一个元素，- 单独作为一个参数
+        assert_eq!(calc_args(&["0x1000", "-", "0x40"]), 0xFC0);
+        assert_eq!(calc_args(&["10", "-", "3", "-", "2"]), 5);
+        assert_eq!(calc_args(&["10-3-2"]), 5);
+    }
+
+    /// 结果与 GCC 对同一个 C 表达式的求值一致；这几个操作符在 Rust 中的优先级与 C 相同
+    #[test]
+    fn xor_precedence_matches_c() {
+        let cases: [(&str, i64); 8] = [
+            ("0xFF ^ 0x0F", 0xFF ^ 0x0F),
+            ("0xF0 & 0x3C ^ 0x0F", (0xF0 & 0x3C) ^ 0x0F),
+            ("0x0F ^ 0xF0 & 0x3C", 0x0F ^ (0xF0 & 0x3C)),
+            ("0x01 | 0x03 ^ 0x02", 0x01 | (0x03 ^ 0x02)),
+            ("0x03 ^ 0x02 | 0x01", (0x03 ^ 0x02) | 0x01),
+            ("1 & 3 ^ 2 | 8", ((1 & 3) ^ 2) | 8),
+            ("6 ^ 3 ^ 5", (6 ^ 3) ^ 5),
+            ("1 + 2 ^ 3 x 4", (1 + 2) ^ (3 * 4)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(calc(input).unwrap(), expected, "{}", input);
+        }
+        assert_eq!(calc("[0x0F ^ 0xF0] & 0x3C").unwrap(), 0x3C);
+    }
+
+    #[test]
+    fn negative_literals_and_unary_minus() {
+        assert_eq!(parse_number("-0x80"), Ok(-0x80));
+        assert_eq!(parse_number("-0b101"), Ok(-5));
+        assert_eq!(parse_number("-42"), Ok(-42));
+        assert_eq!(calc("-0x80").unwrap(), -128);
+        assert_eq!(calc("0 - 0b101").unwrap(), -5);
+        assert_eq!(calc("-0x10 + 0x20").unwrap(), 0x10);
+        assert_eq!(calc("- [3 + 4]").unwrap(), -7);
+        assert_eq!(calc("- -5").unwrap(), 5);
+        assert_eq!(calc("- - 5").unwrap(), 5);
+        assert_eq!(calc("-[-[0x80]]").unwrap(), 0x80);
+        assert_eq!(calc("3 - -2").unwrap(), 5);
+        assert_eq!(calc_args(&["-5", "+", "3"]), -2);
+    }
+
+    #[test]
+    fn octal_literals() {
+        assert_eq!(parse_number("0o755"), Ok(0o755));
+        assert_eq!(parse_number("-0o10"), Ok(-8));
+        assert_eq!(calc("0o755 & 0o070").unwrap(), 0o050);
+        assert_eq!(calc("0o644 | 0o111").unwrap(), 0o755);
+        // 八进制的输出可以原样读回
+        for value in [0, 7, 0o755, 0o1777, i64::MAX as i128] {
+            let formatted =
+                crate::format::FormattedResult::new(value, &EvalOptions::default(), None, &[]);
+            assert_eq!(parse_number(&formatted.octal), Ok(value));
+            assert_eq!(
+                evaluate_unlimited(&parse_expression(&formatted.octal).unwrap()),
+                value
+            );
+        }
+        // 无效的八进制数与无效的十六进制数报告同一种错误
+        for literal in ["0o999", "0o78", "0xZZ"] {
+            assert_eq!(
+                parse_number(literal),
+                Err(Message::InvalidNumber {
+                    literal: literal.to_string()
+                })
+            );
+        }
+        // 不带前缀的 0755 仍按十进制理解
+        assert_eq!(parse_number("0755"), Ok(755));
+    }
+
+    #[test]
+    fn underscore_separators() {
+        assert_eq!(parse_number("0xFFFF_FFFF"), Ok(0xFFFF_FFFF));
+        assert_eq!(parse_number("0b1010_0000"), Ok(0b1010_0000));
+        assert_eq!(parse_number("0o7_5_5"), Ok(0o755));
+        assert_eq!(parse_number("1_000_000"), Ok(1_000_000));
+        assert_eq!(parse_number("-0x8000_0000"), Ok(-0x8000_0000));
+        assert_eq!(calc("0xFFFF_FFFF x 2").unwrap(), 0x1_FFFF_FFFE);
+        // 单个字符串中的表达式同样可以使用分隔符
+        assert_eq!(calc("0xFFFF_FFFFx2").unwrap(), 0x1_FFFF_FFFE);
+        assert_eq!(calc_args(&["1_000", "+", "0b1_0"]), 1002);
+        for literal in ["0x_", "0b_", "0x_FF", "0xFF_", "1_"] {
+            assert_eq!(
+                parse_number(literal),
+                Err(Message::SeparatorAtEdge {
+                    literal: literal.to_string()
+                }),
+                "{}",
+                literal
+            );
+        }
+        assert!(calc("0x_ + 1").is_err());
+        // 只有下划线或以下划线开头的是标识符，不是数字
+        assert!(parse_number("___").is_err());
+        assert!(calc("___").is_err());
+        assert!(calc("_1").is_err());
+    }
+
+    #[test]
+    fn overflow_is_reported_with_operands() {
+        let overflow = |input: &str| match calc_with(input, &EvalOptions::default()) {
+            Err(EnjoyError::Eval {
+                error: EvalError::Overflow(Message::Overflow { operation }),
+                token,
+            }) => (operation, token),
+            other => panic!("{}: {:?}", input, other),
+        };
+        let max = i128::MAX;
+        assert_eq!(
+            overflow("0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF + 1"),
+            (format!("{} + 1", max), Some(1))
+        );
+        // 括号中的子表达式溢出时指向其中的操作符
+        assert_eq!(
+            overflow("[0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF x 2] - 1"),
+            (format!("{} x 2", max), Some(2))
+        );
+        assert_eq!(
+            overflow("1 + [0xFFFFFFFFFFFFFFFF x 0xFFFFFFFFFFFFFFFF x 2]"),
+            (
+                "18446744073709551615 x 18446744073709551615".to_string(),
+                Some(4)
+            )
+        );
+        assert_eq!(
+            overflow("0 - 0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF - 2"),
+            (format!("{} - 2", -max), Some(3))
+        );
+        // 库接口的结果是 i64，放不下时报错而不是回绕
+        assert!(matches!(
+            calc("0x7FFFFFFFFFFFFFFF + 1"),
+            Err(EnjoyError::Eval {
+                error: EvalError::Overflow(Message::ResultOutOfI64 { .. }),
+                ..
+            })
+        ));
+        assert_eq!(calc("0xFFFFFFFF x 2 / 2").unwrap(), 0xFFFFFFFF);
+    }
+
+    #[test]
+    fn wrap_opts_into_twos_complement() {
+        let wrap = EvalOptions {
+            wrap: true,
+            ..EvalOptions::default()
+        };
+        assert_eq!(
+            calc_with("0x7FFFFFFFFFFFFFFF + 1", &wrap).unwrap(),
+            i64::MIN as i128
+        );
+        assert_eq!(
+            calc_with("0xFFFFFFFF x 0xFFFFFFFF", &wrap).unwrap(),
+            0xFFFFFFFFu64.wrapping_mul(0xFFFFFFFF) as i64 as i128
+        );
+        assert_eq!(
+            calc_with("[0x7FFFFFFFFFFFFFFF + 1] - 1", &wrap).unwrap(),
+            i64::MAX as i128
+        );
+        let wrap8 = EvalOptions {
+            width: Some(8),
+            ..wrap
+        };
+        assert_eq!(calc_with("200 + 100", &wrap8).unwrap(), 44);
+    }
+
+    #[test]
+    fn arithmetic_is_128_bit() {
+        assert_eq!(
+            calc("0xFFFFFFFFFFFF x 0x10000 / 0x10000").unwrap(),
+            0xFFFFFFFFFFFF
+        );
+        assert_eq!(
+            evaluate_unlimited(&parse_expression("0xFFFFFFFFFFFF x 0x10000").unwrap()),
+            0xFFFF_FFFF_FFFF_0000
+        );
+        // 中间结果超出 64 位，最终结果放得下
+        assert_eq!(
+            calc("0xFFFFFFFFFFFFFFFF x 0x100 >> 12").unwrap(),
+            0x0FFF_FFFF_FFFF_FFFF
+        );
+        assert_eq!(
+            parse_number("0x7FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"),
+            Ok(i128::MAX)
+        );
+        assert_eq!(parse_number("0x10000000000000000"), Ok(1 << 64));
+        assert_eq!(
+            parse_number(&format!("0b1{}", "0".repeat(100))),
+            Ok(1 << 100)
+        );
+        assert!(matches!(
+            parse_number("0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"),
+            Err(Message::NumberTooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn size_suffixes() {
+        assert_eq!(parse_number("4k"), Ok(4096));
+        assert_eq!(parse_number("4K"), Ok(4096));
+        assert_eq!(parse_number("16M"), Ok(16 << 20));
+        assert_eq!(parse_number("16m"), Ok(16 << 20));
+        assert_eq!(parse_number("1G"), Ok(1 << 30));
+        assert_eq!(parse_number("2T"), Ok(2 << 40));
+        assert_eq!(parse_number("1P"), Ok(1 << 50));
+        assert_eq!(parse_number("-4k"), Ok(-4096));
+        assert_eq!(calc("512M / 4k").unwrap(), 131_072);
+        assert_eq!(calc("1G == 0x40000000").unwrap(), 1);
+        // i64 的边界：7E 放得下，8E 即 2^63 超出 i64
+        assert_eq!(calc("7E").unwrap(), 7 << 60);
+        assert_eq!(calc("8E - 1").unwrap(), i64::MAX);
+        assert_eq!(calc("-8E").unwrap(), i64::MIN);
+        assert!(matches!(
+            calc("8E"),
+            Err(EnjoyError::Eval {
+                error: EvalError::Overflow(Message::ResultOutOfI64 { .. }),
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_number("1000000000000000000000E"),
+            Err(Message::NumberTooLarge { .. })
+        ));
+        assert_eq!(
+            parse_number("4q"),
+            Err(Message::UnknownSizeSuffix {
+                suffix: 'q',
+                literal: "4q".to_string()
+            })
+        );
+        // 十六进制数中的字母是数字，不能再带容量后缀
+        assert_eq!(
+            parse_number("0x10k"),
+            Err(Message::SizeSuffixNotDecimal {
+                literal: "0x10k".to_string()
+            })
+        );
+        assert_eq!(parse_number("0x1E"), Ok(0x1E));
+    }
+
+    #[test]
+    fn in_signed_literals() {
+        let in_signed = |input: &str| {
+            let (mut tokens, sources) = tokenize_args(&[input.to_string()]).unwrap();
+            sign_extend_literals(&mut tokens, &sources, Some(16))?;
+            Ok::<_, EnjoyError>(evaluate_unlimited(&tokens))
+        };
+        // 16 位时正好写满 16 位的字面量按补码理解
+        assert_eq!(in_signed("0x7FFF").unwrap(), 0x7FFF);
+        assert_eq!(in_signed("0x8000").unwrap(), -0x8000);
+        assert_eq!(in_signed("0xFFFF").unwrap(), -1);
+        assert_eq!(in_signed("0b1111111111111111").unwrap(), -1);
+        // 前导零也计入位宽，较窄的字面量仍为正数
+        assert_eq!(in_signed("0x00FF").unwrap(), 0xFF);
+        assert_eq!(in_signed("0xFFF").unwrap(), 0xFFF);
+        assert_eq!(in_signed("65535").unwrap(), 65535);
+        assert!(matches!(
+            in_signed("0x10000"),
+            Err(EnjoyError::Parse {
+                message: Message::LiteralTooWide {
+                    width: 20,
+                    bits: 16,
+                    ..
+                },
+                token: Some(0),
+            })
+        ));
+    }
+
+    #[test]
+    fn clock_math() {
+        assert_eq!(calc("cycles[1500, 48000000]").unwrap(), 72);
+        assert_eq!(calc("cycles[1.5us, 48MHz]").unwrap(), 72);
+        assert_eq!(calc("cycles[2s, 3]").unwrap(), 6);
+        // 周期和周期数都四舍五入到整数，正好是 .5 时向上舍入
+        assert_eq!(calc("period_ns[3]").unwrap(), 333_333_333);
+        assert_eq!(calc("period_ns[72MHz]").unwrap(), 14);
+        assert_eq!(calc("period_ns[2GHz]").unwrap(), 1);
+        assert_eq!(calc("period_ns[3GHz]").unwrap(), 0);
+        assert_eq!(calc("cycles[1, 3]").unwrap(), 0);
+        // 频率后缀按 10 的幂，容量后缀按 2 的幂
+        assert_eq!(calc("72MHz").unwrap(), 72_000_000);
+        assert_eq!(calc("72M").unwrap(), 72 << 20);
+        assert_eq!(calc("1kHz").unwrap(), 1000);
+        assert!(calc("period_ns[0]").is_err());
+        assert!(calc("cycles[1, 0]").is_err());
+        assert!(calc("cycles[0 - 1, 1]").is_err());
+    }
+
+    /// 带量纲计算一个表达式，返回结果、量纲和求值时的说明
+    fn calc_dimensioned(input: &str) -> Result<(i128, Dimension, Vec<EvalNote>), EnjoyError> {
+        let mut notes = Vec::new();
+        let result = evaluate_statements::<Dimensioned<i128>>(
+            &parse_expression(input)?,
+            &EvalOptions::default(),
+            &mut EvalContext::default(),
+            &mut notes,
+        )?;
+        Ok((result.value, result.dimension, notes))
+    }
+
+    #[test]
+    fn durations() {
+        let (value, dimension, notes) = calc_dimensioned("2s + 350ms + 20us").unwrap();
+        assert_eq!((value, dimension), (2_350_020_000, Dimension::Time));
+        assert!(notes.is_empty());
+        assert_eq!(calc_dimensioned("2s x 3").unwrap().1, Dimension::Time);
+        // 两个时间之比是普通的数，不能整除时给出余数
+        let (value, dimension, notes) = calc_dimensioned("90s / 1min").unwrap();
+        assert_eq!((value, dimension), (1, Dimension::Plain));
+        assert!(matches!(
+            notes[..],
+            [EvalNote::InexactDivision {
+                remainder: 30_000_000_000,
+                duration: Some(true),
+                ..
+            }]
+        ));
+        // 小时写作 hr，10h 仍是十六进制数
+        assert_eq!(calc_dimensioned("1hr").unwrap().0, 3_600_000_000_000);
+        let (value, dimension, _) = calc_dimensioned("10h").unwrap();
+        assert_eq!((value, dimension), (16, Dimension::Plain));
+        // 时间与普通的数相加、两个时间相乘都是错误
+        for (input, message) in [
+            ("1s + 1", Message::DurationMismatch { op: Operator::Add }),
+            ("1 - 5ms", Message::DurationMismatch { op: Operator::Sub }),
+            ("1s x 1s", Message::DurationProduct),
+        ] {
+            match calc_dimensioned(input) {
+                Err(EnjoyError::Eval {
+                    error: EvalError::Other(found),
+                    ..
+                }) => assert_eq!(found, message, "{}", input),
+                other => panic!("{}: {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn pasted_c_literals() {
+        // C 的整数后缀不区分大小写，只能有一个
+        assert_eq!(parse_number("0x00200000UL"), Ok(0x0020_0000));
+        assert_eq!(parse_number("4096U"), Ok(4096));
+        assert_eq!(parse_number("1ull"), Ok(1));
+        assert_eq!(parse_number("0x10LLU"), Ok(16));
+        assert_eq!(parse_number("0b101u"), Ok(5));
+        assert_eq!(parse_number("1_000u"), Ok(1000));
+        assert!(parse_number("0x10UU").is_err());
+        assert_eq!(calc("(0x1000UL + 4096U)").unwrap(), 8192);
+        // 千位分隔符只用于十进制数，除第一组外每组 3 位
+        assert_eq!(parse_number("1,000"), Ok(1000));
+        assert_eq!(parse_number("-1,000,000"), Ok(-1_000_000));
+        for literal in ["1,00", "12,34,567", "0x1,000", "1,,000", ",100"] {
+            assert!(
+                matches!(
+                    parse_number(literal),
+                    Err(Message::ThousandsSeparator { .. })
+                ),
+                "{}",
+                literal
+            );
+        }
+        assert_eq!(calc("1,000 + 1").unwrap(), 1001);
+        // 函数的方括号中逗号仍然分隔参数
+        assert_eq!(calc("min[1,2]").unwrap(), 1);
+    }
+
+    #[test]
+    fn math_helpers() {
+        assert_eq!(calc("abs[0 - 5]").unwrap(), 5);
+        assert_eq!(calc("min[3, 0 - 2]").unwrap(), -2);
+        assert_eq!(calc("max[3, 0 - 2]").unwrap(), 3);
+        // gcd 和 lcm 按绝对值计算，有 0 时 lcm 为 0
+        assert_eq!(calc("gcd[0 - 12, 18]").unwrap(), 6);
+        assert_eq!(calc("gcd[0, 0]").unwrap(), 0);
+        assert_eq!(calc("lcm[4, 6]").unwrap(), 12);
+        assert_eq!(calc("lcm[0 - 4, 6]").unwrap(), 12);
+        assert_eq!(calc("lcm[0, 5]").unwrap(), 0);
+        // 回绕模式下 abs[i64::MIN] 仍为 i64::MIN
+        let wrap = EvalOptions {
+            wrap: true,
+            ..EvalOptions::default()
+        };
+        assert_eq!(
+            calc_with("abs[0 - 0x7FFFFFFFFFFFFFFF - 1]", &wrap).unwrap(),
+            i64::MIN as i128
+        );
+        assert!(calc("min[1]").is_err());
+    }
+
+    #[test]
+    fn bit_queries() {
+        assert_eq!(calc("popcount[0xFF]").unwrap(), 8);
+        // 负数按 64 位补码计数
+        assert_eq!(calc("popcount[0 - 1]").unwrap(), 64);
+        assert_eq!(calc("clz[1]").unwrap(), 63);
+        assert_eq!(calc("clz[0 - 1]").unwrap(), 0);
+        assert_eq!(calc("ctz[8]").unwrap(), 3);
+        assert_eq!(calc("log2[1024]").unwrap(), 10);
+        // log2 向下取整
+        assert_eq!(calc("log2[1000]").unwrap(), 9);
+        for input in ["clz[0]", "ctz[0]", "log2[0]", "log2[0 - 4]"] {
+            assert!(calc(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn mask_and_bit() {
+        assert_eq!(calc("mask[4]").unwrap(), 0xF);
+        assert_eq!(calc("mask[0]").unwrap(), 0);
+        assert_eq!(
+            calc_with("mask[64]", &EvalOptions::default()).unwrap(),
+            u64::MAX as i128
+        );
+        assert_eq!(calc("bit[3]").unwrap(), 8);
+        assert_eq!(calc("bit[0]").unwrap(), 1);
+        assert_eq!(calc("mask[8] & ~bit[3]").unwrap(), 0xF7);
+        assert!(calc("bit[128]").is_err());
+    }
+
+    #[test]
+    fn alignment() {
+        assert_eq!(calc("align_up[0x1001, 0x1000]").unwrap(), 0x2000);
+        // 已经对齐时不变
+        assert_eq!(calc("align_up[0x1000, 0x1000]").unwrap(), 0x1000);
+        assert_eq!(calc("align_up[0, 8]").unwrap(), 0);
+        assert_eq!(calc("align_down[0x1FFF, 0x1000]").unwrap(), 0x1000);
+        assert_eq!(calc("align_down[7, 1]").unwrap(), 7);
+        // 对齐值必须是 2 的幂
+        assert!(calc("align_up[5, 3]").is_err());
+        assert!(calc("align_down[5, 0]").is_err());
+    }
+
+    #[test]
+    fn rotations() {
+        assert_eq!(calc("rotl[0x80000001, 1, 32]").unwrap(), 3);
+        assert_eq!(calc("rotr[1, 1, 8]").unwrap(), 0x80);
+        assert_eq!(calc("rotl[0x12, 4, 8]").unwrap(), 0x21);
+        assert_eq!(calc("rotl[1, 1, 64]").unwrap(), 2);
+        // 移位量对位宽取余
+        assert_eq!(calc("rotl[1, 9, 8]").unwrap(), 2);
+        assert_eq!(calc("rotr[0x12, 12, 8]").unwrap(), 0x21);
+        // 位宽只能是 8、16、32 或 64，且必须给出
+        assert!(calc("rotl[1, 1, 7]").is_err());
+        assert!(calc("rotl[1, 1]").is_err());
+    }
+
+    #[test]
+    fn byte_swaps() {
+        assert_eq!(calc("bswap16[0x1234]").unwrap(), 0x3412);
+        assert_eq!(calc("bswap32[0x12345678]").unwrap(), 0x7856_3412);
+        assert_eq!(
+            calc("bswap64[0x0102030405060708]").unwrap(),
+            0x0807_0605_0403_0201
+        );
+        // 负数按该位宽的补码
+        assert_eq!(calc("bswap32[0 - 1]").unwrap(), 0xFFFF_FFFF);
+        assert!(calc("bswap16[0x123456]").is_err());
+    }
+
+    #[test]
+    fn sign_and_zero_extension() {
+        assert_eq!(calc("sext[0x80, 8]").unwrap(), -128);
+        assert_eq!(calc("sext[0x7F, 8]").unwrap(), 127);
+        assert_eq!(calc("sext[0xFFFC, 16]").unwrap(), -4);
+        assert_eq!(calc("zext[0 - 1, 8]").unwrap(), 0xFF);
+        // 高于位宽的位被丢弃
+        assert_eq!(calc("zext[0x1FF, 8]").unwrap(), 0xFF);
+        assert_eq!(calc("sext[0x1FF, 8]").unwrap(), -1);
+        assert!(calc("sext[0xFF, 0]").is_err());
+        assert!(calc("zext[0xFF, 65]").is_err());
+    }
+
+    #[test]
+    fn star_multiplies() {
+        assert_eq!(calc("3 * 4").unwrap(), 12);
+        assert_eq!(calc("3 * 4 x 2").unwrap(), 24);
+        assert_eq!(calc_args(&["3", "*", "4"]), 12);
+        // ** 仍是乘方，* 与 x 优先级相同
+        assert_eq!(calc("3 ** 2").unwrap(), 9);
+        assert_eq!(calc("1 + 2 * 3").unwrap(), 7);
+    }
+
+    #[test]
+    fn ans_refers_to_the_previous_result() {
+        let with_ans = |input: &str, ans: Option<Saved>| -> Result<i128, EnjoyError> {
+            let mut context = EvalContext {
+                ans,
+                ..EvalContext::default()
+            };
+            Ok(evaluate_statements::<i128>(
+                &parse_expression(input)?,
+                &EvalOptions::default(),
+                &mut context,
+                &mut Vec::new(),
+            )?)
+        };
+        let ans = Some(Saved::Int(0x4002_1018));
+        assert_eq!(with_ans("ans & 0xFFFF", ans).unwrap(), 0x1018);
+        // 可以出现在括号和函数参数中
+        assert_eq!(with_ans("[ans + 8] >> 4", ans).unwrap(), 0x400_2102);
+        assert_eq!(with_ans("max[ans, 1]", ans).unwrap(), 0x4002_1018);
+        // 第一个表达式中没有上一个结果
+        assert!(with_ans("ans + 1", None).is_err());
+        // ans 不能被赋值
+        assert!(with_ans("ans = 1", ans).is_err());
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(
+            calc("base = 0x40000000 ; offset = 0x2000 ; base + offset x 4").unwrap(),
+            0x4000_8000
+        );
+        // 可以重新赋值
+        assert_eq!(calc("x = 1; x = x + 1; x").unwrap(), 2);
+        // 未赋值的变量、给内置函数或常量赋值都是错误
+        for input in ["y + 1", "abs = 3", "KiB = 1", "ans = 1"] {
+            assert!(calc(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn character_literals() {
+        assert_eq!(calc("'A'").unwrap(), 65);
+        assert_eq!(calc("'a' + 1").unwrap(), 98);
+        assert_eq!(calc("'\\n'").unwrap(), 10);
+        assert_eq!(calc("'\\''").unwrap(), 39);
+        // 多个字符按大端序组成一个数，最多 8 个
+        assert_eq!(calc("'AB'").unwrap(), 0x4142);
+        assert_eq!(calc("'ABCDEFGH'").unwrap(), 0x4142_4344_4546_4748);
+        for input in ["''", "'ABCDEFGHI'", "'\\x41'"] {
+            assert!(calc(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn gray_code() {
+        assert_eq!(calc("gray[5]").unwrap(), 7);
+        assert_eq!(calc("ungray[7]").unwrap(), 5);
+        assert_eq!(calc("gray[0]").unwrap(), 0);
+        // 相邻的数的格雷码只差一位，ungray 是 gray 的逆运算
+        for n in 0..256 {
+            let (code, next) = (
+                calc(&format!("gray[{}]", n)).unwrap(),
+                calc(&format!("gray[{}]", n + 1)).unwrap(),
+            );
+            assert_eq!((code ^ next).count_ones(), 1, "{}", n);
+            assert_eq!(calc(&format!("ungray[{}]", code)).unwrap(), n);
+        }
+        assert!(calc("gray[0 - 1]").is_err());
+    }
+
+    #[test]
+    fn parity() {
+        assert_eq!(calc("parity[7]").unwrap(), 1);
+        assert_eq!(calc("parity[3]").unwrap(), 0);
+        assert_eq!(calc("parity[0]").unwrap(), 0);
+        // 负数按 64 位补码，64 个 1 为偶数
+        assert_eq!(calc("parity[0 - 1]").unwrap(), 0);
+        assert_eq!(calc("parity[0x8000000000000000]").unwrap(), 1);
+    }
+
+    #[test]
+    fn bit_reverse() {
+        assert_eq!(calc("rbit[1, 8]").unwrap(), 0x80);
+        assert_eq!(calc("rbit[0x12, 8]").unwrap(), 0x48);
+        assert_eq!(calc("rbit[1, 32]").unwrap(), 0x8000_0000);
+        assert_eq!(calc("rbit[rbit[0xBEEF, 16], 16]").unwrap(), 0xBEEF);
+        // 位宽必须给出
+        assert!(calc("rbit[1]").is_err());
+    }
+
+    #[test]
+    fn byte_and_nibble() {
+        assert_eq!(calc("byte[0x12345678, 0]").unwrap(), 0x78);
+        assert_eq!(calc("byte[0x12345678, 1]").unwrap(), 0x56);
+        assert_eq!(calc("byte[0x12345678, 4]").unwrap(), 0);
+        assert_eq!(calc("nibble[0x1234, 0]").unwrap(), 4);
+        assert_eq!(calc("nibble[0x1234, 2]").unwrap(), 2);
+        // 序号超出 64 位寄存器时是错误
+        assert!(calc("byte[1, 8]").is_err());
+        assert!(calc("nibble[1, 16]").is_err());
+    }
+
+    #[test]
+    fn single_bit_updates() {
+        assert_eq!(calc("setbit[0, 4]").unwrap(), 0x10);
+        assert_eq!(calc("setbit[0x10, 4]").unwrap(), 0x10);
+        assert_eq!(calc("clrbit[0xFF, 0]").unwrap(), 0xFE);
+        assert_eq!(calc("clrbit[0xFE, 0]").unwrap(), 0xFE);
+        assert_eq!(calc("tglbit[0xF, 3]").unwrap(), 0x7);
+        assert_eq!(calc("tglbit[0x7, 3]").unwrap(), 0xF);
+        for input in ["setbit[0, 64]", "clrbit[0, 0 - 1]", "tglbit[0, 127]"] {
+            assert!(calc(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn modular_arithmetic() {
+        let modulo = |modulus| EvalOptions {
+            modulus: Some(modulus),
+            ..EvalOptions::default()
+        };
+        assert_eq!(calc_with("7 x 5", &modulo(11)).unwrap(), 2);
+        // 乘方按模计算，中间结果不会溢出
+        assert_eq!(
+            calc_with("2 ** 100", &modulo(1_000_000_007)).unwrap(),
+            976_371_285
+        );
+        // 结果规约到 0..N-1
+        assert_eq!(calc_with("0 - 1", &modulo(7)).unwrap(), 6);
+        let max = u64::MAX as i128;
+        assert_eq!(
+            calc_with("0xFFFFFFFFFFFFFFFE x 0xFFFFFFFFFFFFFFFE", &modulo(max)).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn saturating_arithmetic() {
+        let saturate = |width, unsigned| EvalOptions {
+            saturate: true,
+            unsigned,
+            width: Some(width),
+            ..EvalOptions::default()
+        };
+        let mut notes = Vec::new();
+        let result = evaluate_statements::<i128>(
+            &parse_expression("100 + 100").unwrap(),
+            &saturate(8, false),
+            &mut EvalContext::default(),
+            &mut notes,
+        );
+        assert_eq!(result.unwrap(), 127);
+        assert!(matches!(
+            &notes[..],
+            [EvalNote::Saturated {
+                exact: Some(200),
+                result: 127,
+                ..
+            }]
+        ));
+        assert_eq!(
+            calc_with("0 - 100 - 100", &saturate(8, false)).unwrap(),
+            -128
+        );
+        assert_eq!(calc_with("0 - 1", &saturate(16, true)).unwrap(), 0);
+        assert_eq!(
+            calc_with("0xFFFF + 1", &saturate(16, true)).unwrap(),
+            0xFFFF
+        );
+        // 没有溢出时与普通计算相同
+        assert_eq!(calc_with("100 + 27", &saturate(8, false)).unwrap(), 127);
+    }
+
+    #[test]
+    fn widening_multiply() {
+        let mut notes = Vec::new();
+        let result = evaluate_statements::<i128>(
+            &parse_expression("mulwide[0x7FFFFFFFFFFFFFFF, 4]").unwrap(),
+            &EvalOptions::default(),
+            &mut EvalContext::default(),
+            &mut notes,
+        );
+        assert_eq!(result.unwrap(), 0x1_FFFF_FFFF_FFFF_FFFC);
+        assert!(matches!(
+            notes[..],
+            [EvalNote::WideProduct {
+                high: 1,
+                low: 0xFFFF_FFFF_FFFF_FFFC,
+                unsigned: false,
+            }]
+        ));
+        assert_eq!(
+            calc_with(
+                "mulwide[0 - 0x7FFFFFFFFFFFFFFF - 1, 2]",
+                &EvalOptions::default()
+            )
+            .unwrap(),
+            -(1 << 64)
+        );
+        // 操作数必须在 i64 范围内，无符号乘积超出 i128 时是错误
+        assert!(calc("mulwide[0xFFFFFFFFFFFFFFFF, 2]").is_err());
+        let unsigned = EvalOptions {
+            unsigned: true,
+            ..EvalOptions::default()
+        };
+        assert!(calc_with("mulwide[0xFFFFFFFFFFFFFFFF, 0xFFFFFFFFFFFFFFFF]", &unsigned).is_err());
+    }
+
+    #[test]
+    fn bcd_conversion() {
+        assert_eq!(calc("tobcd[1234]").unwrap(), 0x1234);
+        assert_eq!(calc("tobcd[99]").unwrap(), 0x99);
+        assert_eq!(calc("frombcd[0x1234]").unwrap(), 1234);
+        assert_eq!(calc("frombcd[tobcd[9876543210]]").unwrap(), 9_876_543_210);
+        // 半字节大于 9 的不是 BCD 码
+        assert!(calc("frombcd[0x1A]").is_err());
+        assert!(calc("tobcd[0 - 1]").is_err());
+    }
+
+    #[test]
+    fn morton_order() {
+        // x 在偶数位，y 在奇数位
+        assert_eq!(calc("interleave[3, 0]").unwrap(), 0b0101);
+        assert_eq!(calc("interleave[0, 3]").unwrap(), 0b1010);
+        assert_eq!(calc("deinterleave_x[0xA]").unwrap(), 0);
+        assert_eq!(calc("deinterleave_y[0xA]").unwrap(), 3);
+        assert_eq!(
+            calc("deinterleave_x[interleave[0x1234, 0xABCD]]").unwrap(),
+            0x1234
+        );
+        assert_eq!(
+            calc("deinterleave_y[interleave[0xFFFFFFFF, 0xABCD]]").unwrap(),
+            0xABCD
+        );
+        // 坐标最多 32 位
+        assert!(calc("interleave[0x100000000, 0]").is_err());
+    }
+
+    #[test]
+    fn nibble_reverse() {
+        assert_eq!(calc("revnibbles[0x1234]").unwrap(), 0x4321);
+        assert_eq!(calc("revnibbles[0xABC]").unwrap(), 0xCBA);
+        assert_eq!(calc("revnibbles[0]").unwrap(), 0);
+        // 与按字节和按位反转不同
+        assert_eq!(calc("bswap16[0x1234]").unwrap(), 0x3412);
+        assert_eq!(calc("rbit[0x1234, 16]").unwrap(), 0x2C48);
+        // 给出位宽时前导零也参与反转
+        let width = |width| EvalOptions {
+            width: Some(width),
+            ..EvalOptions::default()
+        };
+        assert_eq!(calc_with("revnibbles[0x12]", &width(16)).unwrap(), 0x2100);
+        assert_eq!(
+            calc_with("revnibbles[0x1234]", &width(32)).unwrap(),
+            0x4321_0000
+        );
+        assert!(calc_with("revnibbles[0x10000]", &width(16)).is_err());
+        assert_eq!(
+            calc_with("revnibbles[0 - 2]", &EvalOptions::default()).unwrap(),
+            0xEFFF_FFFF_FFFF_FFFF_u64 as i128
+        );
+    }
+}
@@ -0,0 +1,1691 @@
+//! 结果的各种输出形式：各进制、带位索引的二进制、字节序、位段、时间戳等
+
+use crate::expr::{EvalNote, EvalOptions, Function, bit_field, sign_extend};
+use crate::i18n::tr;
+use crate::regmap;
+use crate::timezone;
+use serde::Serialize;
+use std::path::Path;
+
+/// --fields 中的一个位段，如 31:28=MODE；只写一位时 hi 与 lo 相同 (5=EN)
+/// values 为取值的名称，只有寄存器描述文件中的位段才有
+#[derive(Debug, Clone)]
+struct BitField {
+    name: String,
+    hi: u32,
+    lo: u32,
+    values: Vec<(i128, String)>,
+}
+
+/// 要解码的全部位段，按给出的顺序保存；来自 --decode 时 register 为寄存器的名称和地址
+#[derive(Debug, Clone)]
+pub(crate) struct FieldSpec {
+    register: Option<String>,
+    fields: Vec<BitField>,
+}
+
+impl FieldSpec {
+    /// --decode：从寄存器描述文件中取出寄存器的位段
+    pub(crate) fn from_regmap(path: &Path, register: &str) -> Result<Self, String> {
+        let map = regmap::RegisterMap::load(path)?;
+        let (name, definition) = map.register(register)?;
+        let fields = definition
+            .fields
+            .iter()
+            .map(|field| BitField {
+                name: field.name.clone(),
+                hi: field.msb,
+                lo: field.lsb,
+                values: field.values(),
+            })
+            .collect();
+        let register = match &definition.address {
+            Some(address) => format!("{} ({})", name, address),
+            None => name.to_string(),
+        };
+        Ok(FieldSpec {
+            register: Some(register),
+            fields,
+        })
+    }
+}
+
+/// 解析 --fields "31:28=MODE,27:16=DIV,15:0=ADDR"，位段必须在 0..=63 范围内且高位不小于低位
+/// 重叠的位段不算错误，在输出时给出警告
+pub(crate) fn parse_field_spec(s: &str) -> Result<FieldSpec, String> {
+    let bit = |text: &str, field: &str| -> Result<u32, String> {
+        match text.trim().parse::<u32>() {
+            Ok(bit) if bit < 64 => Ok(bit),
+            Ok(bit) => Err(tr!(
+                "位段 {} 的位 {} 超出范围 0..63",
+                "field {}: bit {} is out of range 0..63",
+                field,
+                bit
+            )),
+            Err(_) => Err(tr!(
+                "位段 {} 的位不是有效的数字: {}",
+                "field {}: bit is not a valid number: {}",
+                field,
+                text.trim()
+            )),
+        }
+    };
+    let mut fields = Vec::new();
+    for field in s
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+    {
+        let (range, name) = field.split_once('=').ok_or_else(|| {
+            tr!(
+                "位段应写作 HI:LO=NAME: {}",
+                "fields must be written as HI:LO=NAME: {}",
+                field
+            )
+        })?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(tr!(
+                "位段缺少名称: {}",
+                "field is missing a name: {}",
+                field
+            ));
+        }
+        let (hi, lo) = match range.split_once(':') {
+            Some((hi, lo)) => (bit(hi, field)?, bit(lo, field)?),
+            None => {
+                let bit = bit(range, field)?;
+                (bit, bit)
+            }
+        };
+        if hi < lo {
+            return Err(tr!(
+                "位段 {} 的高位不能小于低位",
+                "field {}: the high bit cannot be less than the low bit",
+                field
+            ));
+        }
+        fields.push(BitField {
+            name: name.to_string(),
+            hi,
+            lo,
+            values: Vec::new(),
+        });
+    }
+    if fields.is_empty() {
+        return Err(tr!(
+            "--fields 至少需要一个位段",
+            "--fields needs at least one field"
+        ));
+    }
+    Ok(FieldSpec {
+        register: None,
+        fields,
+    })
+}
+
+/// 文字在终端中占的列数，中文字符占两列
+pub(crate) fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+/// 按显示宽度左对齐，中文字符占两列
+pub(crate) fn pad_column(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(display_width(text)))
+    )
+}
+
+/// 二进制位串，至少有 min_width 位，并补零到 group 位的整数倍
+pub(crate) fn get_padded_binary(num: i128, min_width: u32, group: usize) -> String {
+    // 负数按 64 位补码显示，超出 i64 时按 128 位补码显示
+    let binary_str = if num < 0 && num >= i64::MIN as i128 {
+        format!("{:b}", num as i64)
+    } else {
+        format!("{:b}", num)
+    };
+    let len = binary_str.len().max(min_width as usize).div_ceil(group) * group;
+    format!("{:0>len$}", binary_str)
+}
+
+/// 二进制位串，按 group 位分组并用空格分隔，负数按 64 位补码（超出 i64 时按 128 位补码）给出
+///
+/// ```
+/// assert_eq!(enjoy::format_binary(0x1F, 4), "0001 1111");
+/// assert_eq!(enjoy::format_binary(5, 8), "00000101");
+/// ```
+pub fn format_binary(value: i128, group: usize) -> String {
+    split_into_groups(&get_padded_binary(value, 0, group), group).join(" ")
+}
+
+pub(crate) fn split_into_groups(s: &str, group: usize) -> Vec<String> {
+    s.chars()
+        .collect::<Vec<_>>() // 将字符转换为 Vec<char>
+        .chunks(group) // 按每 group 个字符分组
+        .map(|chunk| chunk.iter().collect::<String>()) // 每组转换回字符串
+        .collect()
+}
+
+/// 十进制数从低位起每 3 位加一个逗号，如 1,610,612,736
+///
+/// ```
+/// assert_eq!(enjoy::group_thousands(-1610612736), "-1,610,612,736");
+/// ```
+pub fn group_thousands(num: i128) -> String {
+    let digits = num.unsigned_abs().to_string();
+    let groups: Vec<&str> = digits
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .collect();
+    let sign = if num < 0 { "-" } else { "" };
+    format!("{}{}", sign, groups.join(","))
+}
+
+/// 十六进制数字从低位起每 4 位加一个下划线，如 5F5E_1000
+///
+/// ```
+/// assert_eq!(enjoy::group_hex_digits("5F5E1000"), "5F5E_1000");
+/// ```
+pub fn group_hex_digits(digits: &str) -> String {
+    let groups: Vec<&str> = digits
+        .as_bytes()
+        .rchunks(4)
+        .rev()
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .collect();
+    groups.join("_")
+}
+
+/// 输出样式：关闭颜色时原样返回文本，不产生任何转义序列
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Style {
+    pub(crate) color: bool,
+    /// 相邻的二进制分组交替使用不同的颜色 (--zebra)
+    pub(crate) zebra: bool,
+    /// 二进制输出每组的位数 (--group-bits)
+    pub(crate) group_bits: usize,
+    /// 十进制加千位分隔符，十六进制每 4 位加下划线 (--sep)
+    pub(crate) separators: bool,
+}
+
+impl Style {
+    /// 为 1 的位
+    const SET_BIT: &str = "1;32";
+    /// --zebra 时奇数分组中为 1 的位
+    const SET_BIT_ALTERNATE: &str = "1;36";
+    /// 为 0 的位
+    const CLEAR_BIT: &str = "2";
+    /// 位索引行
+    pub(crate) const INDEX: &str = "90";
+    /// 醒目的警告
+    pub(crate) const WARNING: &str = "1;31";
+
+    /// 十进制数，启用 --sep 时加千位分隔符
+    fn decimal(&self, num: i128) -> String {
+        if self.separators {
+            group_thousands(num)
+        } else {
+            num.to_string()
+        }
+    }
+
+    /// 带 0x 前缀的十六进制数，digits 不含前缀；启用 --sep 时每 4 位加下划线
+    fn hex(&self, digits: &str) -> String {
+        if self.separators {
+            format!("0x{}", group_hex_digits(digits))
+        } else {
+            format!("0x{}", digits)
+        }
+    }
+
+    pub(crate) fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// 一组二进制位，alternate 为从最低位数起的奇数分组；转义序列不占显示宽度，不影响索引行对齐
+    fn bit_group(&self, group: &str, alternate: bool) -> String {
+        let set = if self.zebra && alternate {
+            Self::SET_BIT_ALTERNATE
+        } else {
+            Self::SET_BIT
+        };
+        group
+            .chars()
+            .map(|bit| {
+                let code = if bit == '1' { set } else { Self::CLEAR_BIT };
+                self.paint(code, &bit.to_string())
+            })
+            .collect()
+    }
+}
+
+fn print_hex_info(num: i128, style: Style) {
+    let hex = |digits: String| style.hex(&digits);
+    if num < i64::MIN as i128 {
+        println!(
+            "{}",
+            tr!(
+                "十六进制: {} (128 位补码)",
+                "Hex: {} (128-bit two's complement)",
+                hex(format!("{:X}", num))
+            )
+        );
+    } else if num < 0 {
+        // 负数按 64 位补码显示，能放进 32 位时同时给出截断后的形式
+        println!(
+            "{}",
+            tr!(
+                "十六进制: {} (64 位补码)",
+                "Hex: {} (64-bit two's complement)",
+                hex(format!("{:X}", num as i64))
+            )
+        );
+        if num >= i32::MIN as i128 {
+            println!(
+                "{}",
+                tr!(
+                    "十六进制 (32 位): {}",
+                    "Hex (32-bit): {}",
+                    hex(format!("{:X}", num as i32))
+                )
+            );
+        }
+    } else {
+        println!(
+            "{}",
+            tr!("十六进制: {}", "Hex: {}", hex(format!("{:X}", num)))
+        );
+    }
+}
+
+/// 输出浮点数结果及其 IEEE 754 编码
+pub(crate) fn print_float_info(num: f64) {
+    println!("{}", tr!("十进制: {}", "Decimal: {}", num));
+    if num.is_nan() {
+        println!(
+            "{}",
+            tr!(
+                "警告: 结果为 NaN (如 0 / 0)",
+                "warning: result is NaN (e.g. 0 / 0)"
+            )
+        );
+    } else if num.is_infinite() {
+        println!(
+            "{}",
+            tr!(
+                "警告: 结果为无穷大 (除零或溢出)",
+                "warning: result is infinite (division by zero or overflow)"
+            )
+        );
+    }
+    let bits = num.to_bits();
+    println!("IEEE 754 (f64): 0x{:016X}", bits);
+    println!(
+        "{}",
+        tr!(
+            "符号 {} | 指数 {:011b} | 尾数 {:052b}",
+            "sign {} | exponent {:011b} | mantissa {:052b}",
+            bits >> 63,
+            (bits >> 52) & 0x7FF,
+            bits & ((1 << 52) - 1)
+        )
+    );
+}
+
+/// 二进制位行，分组之间用空格分隔
+pub(crate) fn binary_row(groups: &[String], style: Style) -> String {
+    // 从最低位的分组开始计数
+    let count = groups.len();
+    groups
+        .iter()
+        .enumerate()
+        .map(|(i, group)| style.bit_group(group, (count - 1 - i) % 2 == 1))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 位索引行，每个索引右对齐到所在分组的最低位下方
+pub(crate) fn index_row(total_bits: usize, group: usize) -> String {
+    (1..=total_bits / group)
+        .map(|i| format!("{:>group$}", total_bits - group * i))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 十六进制数字行，每组对应的十六进制数字右对齐到该组下方（如 1101 1110 下方为 D 和 E）
+fn hex_digit_row(groups: &[String], group: usize) -> String {
+    groups
+        .iter()
+        .map(|bits| {
+            let value = u16::from_str_radix(bits, 2).unwrap_or_default();
+            let hex = format!("{:0digits$X}", value, digits = group / 4);
+            format!("{:>group$}", hex)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn print_binary_info(num: i128, style: Style, min_width: u32) {
+    let group = style.group_bits;
+    let padded_binary = get_padded_binary(num, min_width, group);
+    let groups = split_into_groups(&padded_binary, group);
+
+    if num < i64::MIN as i128 {
+        println!(
+            "{}",
+            tr!(
+                "二进制 (128 位补码): ",
+                "Binary (128-bit two's complement): "
+            )
+        );
+    } else if num < 0 {
+        println!(
+            "{}",
+            tr!("二进制 (64 位补码): ", "Binary (64-bit two's complement): ")
+        );
+    } else {
+        println!("{}", tr!("二进制: ", "Binary: "));
+    }
+    println!("{}", binary_row(&groups, style));
+    println!(
+        "{}",
+        style.paint(Style::INDEX, &index_row(padded_binary.len(), group))
+    );
+    println!("{}", hex_digit_row(&groups, group));
+}
+
+/// 结果中输出的进制，按 --output 中的顺序逐行输出
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum Base {
+    Dec,
+    Hex,
+    Bin,
+    Oct,
+}
+
+/// 最高位为 1 时按补码换一种方式理解同一个 bits 位的位模式：负数给出无符号的值，
+/// 最高位为 1 的非负数给出有符号的值；最高位为 0 或超出 bits 位时返回 None
+fn reinterpret_sign(value: i128, bits: u32) -> Option<i128> {
+    let modulus = 1i128 << bits;
+    if (-(modulus >> 1)..0).contains(&value) {
+        Some(value + modulus)
+    } else if (modulus >> 1..modulus).contains(&value) {
+        Some(value - modulus)
+    } else {
+        None
+    }
+}
+
+/// 按 radix 进制 (2..=36) 输出，数字为 0-9a-z；负数输出负号加绝对值的各位
+///
+/// ```
+/// assert_eq!(enjoy::to_radix(35, 36), "z");
+/// assert_eq!(enjoy::to_radix(-8, 3), "-22");
+/// ```
+pub fn to_radix(value: i128, radix: u32) -> String {
+    let mut rest = value.unsigned_abs();
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((rest % radix as u128) as u32, radix).unwrap());
+        rest /= radix as u128;
+        if rest == 0 {
+            break;
+        }
+    }
+    if value < 0 {
+        digits.push('-');
+    }
+    digits.iter().rev().collect()
+}
+
+/// 字节序，用于 --endian 和 --emit bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum ByteOrder {
+    Le,
+    Be,
+}
+
+fn print_octal_info(num: i128) {
+    if num < i64::MIN as i128 {
+        println!(
+            "{}",
+            tr!(
+                "八进制: 0o{:o} (128 位补码)",
+                "Octal: 0o{:o} (128-bit two's complement)",
+                num
+            )
+        );
+    } else if num < 0 {
+        println!(
+            "{}",
+            tr!(
+                "八进制: 0o{:o} (64 位补码)",
+                "Octal: 0o{:o} (64-bit two's complement)",
+                num as i64
+            )
+        );
+    } else {
+        println!("{}", tr!("八进制: 0o{:o}", "Octal: 0o{:o}", num));
+    }
+}
+
+/// 输出整数结果：按 bases 的顺序输出各进制，function 为整个表达式调用的函数，notes 放在最后
+pub(crate) fn print_result(
+    result: i128,
+    bases: &[Base],
+    options: &EvalOptions,
+    style: Style,
+    function: Option<&Function>,
+    notes: &[EvalNote],
+) {
+    // 保存的终端日志中也要能看出结果是取模后的值
+    if let Some(modulus) = options.modulus {
+        println!(
+            "{}",
+            tr!(
+                "模数: {} (0x{:X})",
+                "Modulus: {} (0x{:X})",
+                modulus,
+                modulus
+            )
+        );
+    }
+    // 只按 --width 显示（不回绕也不饱和）时，结果可能放不下，此时各进制只显示低位
+    let truncated = options.width.filter(|&width| {
+        let min = if options.unsigned {
+            0
+        } else {
+            -(1i128 << (width - 1))
+        };
+        !(min..1i128 << width).contains(&result)
+    });
+    let out_of_range = || {
+        if let Some(width) = truncated {
+            let warning = tr!(
+                "警告: 结果超出 {} 位，十六进制、八进制和二进制只显示截断后的低 {} 位",
+                "warning: result exceeds {} bits, hex, octal and binary show only the truncated low {} bits",
+                width,
+                width
+            );
+            println!("{}", style.paint(Style::WARNING, &warning));
+        } else if !options.fits_register(result) {
+            println!(
+                "{}",
+                tr!(
+                    "注意: 结果超出 {} 范围",
+                    "note: result is out of {} range",
+                    options.register_name()
+                )
+            );
+        }
+    };
+    if !bases.contains(&Base::Dec) {
+        out_of_range();
+    }
+    // 指定了寄存器位宽时按该位宽的位模式显示，负数即其补码
+    let register = options
+        .width
+        .map(|width| (result as u128 & (u128::MAX >> (128 - width)), width));
+    // 按固定位宽计算的函数，二进制补零到该位宽，便于逐位对照
+    let min_width = function
+        .filter(|function| function.width_arg.is_some())
+        .and_then(|_| {
+            notes.iter().rev().find_map(|note| match note {
+                EvalNote::Width { width, .. } => Some(*width),
+                _ => None,
+            })
+        })
+        .unwrap_or(0);
+    for base in bases {
+        match (base, register) {
+            (Base::Dec, _) => {
+                match reinterpret_sign(result, options.register_bits()) {
+                    Some(other) if result < 0 => println!(
+                        "{}",
+                        tr!(
+                            "十进制: {} (无符号: {})",
+                            "Decimal: {} (unsigned: {})",
+                            style.decimal(result),
+                            style.decimal(other)
+                        )
+                    ),
+                    Some(other) => println!(
+                        "{}",
+                        tr!(
+                            "十进制: {} (有符号: {})",
+                            "Decimal: {} (signed: {})",
+                            style.decimal(result),
+                            style.decimal(other)
+                        )
+                    ),
+                    None => println!(
+                        "{}",
+                        tr!("十进制: {}", "Decimal: {}", style.decimal(result))
+                    ),
+                }
+                out_of_range();
+            }
+            (Base::Hex, Some((bits, width))) if truncated.is_some() => {
+                let full = if result < i64::MIN as i128 || result >= 0 {
+                    style.hex(&format!("{:X}", result))
+                } else {
+                    tr!(
+                        "{} (64 位补码)",
+                        "{} (64-bit two's complement)",
+                        style.hex(&format!("{:X}", result as i64))
+                    )
+                };
+                println!(
+                    "{}",
+                    tr!(
+                        "十六进制: {} ({} 位，已截断；完整值为 {})",
+                        "Hex: {} ({} bits, truncated; full value is {})",
+                        style.hex(&format!("{:0digits$X}", bits, digits = width as usize / 4)),
+                        width,
+                        full
+                    )
+                )
+            }
+            (Base::Hex, Some((bits, width))) => println!(
+                "{}",
+                tr!(
+                    "十六进制: {} ({} 位)",
+                    "Hex: {} ({} bits)",
+                    style.hex(&format!("{:0digits$X}", bits, digits = width as usize / 4)),
+                    width
+                )
+            ),
+            (Base::Hex, None) => print_hex_info(result, style),
+            // 每个八进制数字 3 位，补齐到能放下 width 位的位数，如 32 位为 11 位数字
+            (Base::Oct, Some((bits, width))) => println!(
+                "{}",
+                tr!(
+                    "八进制: 0o{} ({} 位)",
+                    "Octal: 0o{} ({} bits)",
+                    format!("{:0digits$o}", bits, digits = (width as usize).div_ceil(3)),
+                    width
+                )
+            ),
+            (Base::Oct, None) => print_octal_info(result),
+            (Base::Bin, Some((bits, width))) => {
+                print_binary_info(bits as i128, style, min_width.max(width))
+            }
+            (Base::Bin, None) => print_binary_info(result, style, min_width),
+        }
+    }
+    if function.is_some_and(|function| function.name == "parity") {
+        println!(
+            "{}",
+            tr!(
+                "奇偶性: {}",
+                "Parity: {}",
+                if result == 0 {
+                    tr!("偶 (even)", "even")
+                } else {
+                    tr!("奇 (odd)", "odd")
+                }
+            )
+        );
+    }
+    // 放在常规输出之后，不影响按行解析输出的脚本
+    for note in notes {
+        println!("{}", note);
+    }
+}
+
+/// 各进制形式的整数结果，用于 --json 和 --quiet，负数按与常规输出相同的补码形式给出
+#[derive(Serialize)]
+pub(crate) struct FormattedResult {
+    pub(crate) decimal: i128,
+    pub(crate) hex: String,
+    pub(crate) octal: String,
+    pub(crate) binary: String,
+    /// 二进制中为 1 的位，从低到高
+    pub(crate) bits_set: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modulus: Option<i128>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<String>,
+}
+
+impl FormattedResult {
+    pub(crate) fn new(
+        result: i128,
+        options: &EvalOptions,
+        function: Option<&Function>,
+        notes: &[EvalNote],
+    ) -> Self {
+        // 指定了寄存器位宽时按该位宽的位模式给出，与常规输出一致
+        let (shown, width) = match options.width {
+            Some(width) => (
+                (result as u128 & (u128::MAX >> (128 - width))) as i128,
+                width,
+            ),
+            None => (result, 0),
+        };
+        let min_width = function
+            .filter(|function| function.width_arg.is_some())
+            .and_then(|_| {
+                notes.iter().rev().find_map(|note| match note {
+                    EvalNote::Width { width, .. } => Some(*width),
+                    _ => None,
+                })
+            })
+            .unwrap_or(0);
+        let (hex, octal) = if shown < i64::MIN as i128 || shown >= 0 {
+            (
+                format!("0x{:0digits$X}", shown, digits = width as usize / 4),
+                format!(
+                    "0o{:0digits$o}",
+                    shown,
+                    digits = (width as usize).div_ceil(3)
+                ),
+            )
+        } else {
+            (
+                format!("0x{:X}", shown as i64),
+                format!("0o{:o}", shown as i64),
+            )
+        };
+        let binary = get_padded_binary(shown, width.max(min_width), 4);
+        let bits_set = binary
+            .bytes()
+            .rev()
+            .enumerate()
+            .filter(|(_, bit)| *bit == b'1')
+            .map(|(i, _)| i)
+            .collect();
+        FormattedResult {
+            decimal: result,
+            hex,
+            octal,
+            binary,
+            bits_set,
+            modulus: options.modulus,
+            notes: notes.iter().map(|note| note.to_string()).collect(),
+        }
+    }
+}
+
+impl FormattedResult {
+    /// --quiet 模式下只输出一个值，prefix 决定是否带 0x、0o、0b 前缀
+    pub(crate) fn quiet(&self, base: Base, prefix: bool) -> String {
+        let (text, prefix_len) = match base {
+            Base::Dec => return self.decimal.to_string(),
+            Base::Hex => (&self.hex, 2),
+            Base::Oct => (&self.octal, 2),
+            Base::Bin if prefix => return format!("0b{}", self.binary),
+            Base::Bin => return self.binary.clone(),
+        };
+        if prefix {
+            text.clone()
+        } else {
+            text[prefix_len..].to_string()
+        }
+    }
+}
+
+/// 二进制单位 (1024 进制) 和 SI 单位 (1000 进制)
+const BINARY_SIZE_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+const SI_SIZE_UNITS: [&str; 7] = ["B", "KB", "MB", "GB", "TB", "PB", "EB"];
+
+/// 按 base 进制把字节数换算为最大的不小于 1 的单位，保留两位小数；不足一个单位时直接给出字节数
+fn human_size(bytes: i128, base: u128, units: &[&str]) -> String {
+    let sign = if bytes < 0 { "-" } else { "" };
+    let magnitude = bytes.unsigned_abs();
+    if magnitude < base {
+        return format!("{}{} B", sign, magnitude);
+    }
+    let mut unit = 0;
+    let mut scale = 1u128;
+    while unit + 1 < units.len() && magnitude >= scale * base {
+        scale *= base;
+        unit += 1;
+    }
+    format!(
+        "{}{:.2} {}",
+        sign,
+        magnitude as f64 / scale as f64,
+        units[unit]
+    )
+}
+
+/// --human 的输出行，如 大小: 1.50 GiB / 1.61 GB (1610612736)
+pub(crate) fn human_size_line(bytes: i128) -> String {
+    if bytes.unsigned_abs() < 1024 {
+        return tr!("大小: {} B", "Size: {} B", bytes);
+    }
+    tr!(
+        "大小: {} / {} ({})",
+        "Size: {} / {} ({})",
+        human_size(bytes, 1024, &BINARY_SIZE_UNITS),
+        human_size(bytes, 1000, &SI_SIZE_UNITS),
+        bytes
+    )
+}
+
+/// 时间结果的输出行，如 时间: 2350350000 ns = 2.35035 s
+pub(crate) fn duration_line(nanos: i128) -> String {
+    tr!(
+        "时间: {} ns = {}",
+        "Time: {} ns = {}",
+        nanos,
+        human_duration(nanos)
+    )
+}
+
+/// 便于阅读的时间：不到 1 分钟时用最大的合适单位写成小数 (2.35035 s、350 ms)，
+/// 否则拆分为小时、分钟和秒 (1 h 30 min 5.5 s)
+pub(crate) fn human_duration(nanos: i128) -> String {
+    const SECOND: u128 = 1_000_000_000;
+    const UNITS: [(u128, &str); 4] = [(SECOND, "s"), (1_000_000, "ms"), (1_000, "us"), (1, "ns")];
+    let sign = if nanos < 0 { "-" } else { "" };
+    let nanos = nanos.unsigned_abs();
+    // 按 unit 写成小数，去掉末尾的 0
+    let decimal = |value: u128, unit: u128| {
+        let (whole, fraction) = (value / unit, value % unit);
+        if fraction == 0 {
+            return whole.to_string();
+        }
+        let digits = unit.ilog10() as usize;
+        let fraction = format!("{:0digits$}", fraction);
+        format!("{}.{}", whole, fraction.trim_end_matches('0'))
+    };
+    if nanos < 60 * SECOND {
+        let (unit, name) = UNITS
+            .into_iter()
+            .find(|(unit, _)| nanos >= *unit)
+            .unwrap_or((1, "ns"));
+        return format!("{}{} {}", sign, decimal(nanos, unit), name);
+    }
+    let (hours, rest) = (nanos / (3600 * SECOND), nanos % (3600 * SECOND));
+    let (minutes, rest) = (rest / (60 * SECOND), rest % (60 * SECOND));
+    let mut parts = Vec::new();
+    if hours > 0 {
+        parts.push(format!("{} h", hours));
+    }
+    if minutes > 0 {
+        parts.push(format!("{} min", minutes));
+    }
+    if rest > 0 {
+        parts.push(format!("{} s", decimal(rest, SECOND)));
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
+/// --float-bits 视图：把结果的低 32 或 64 位按 IEEE 754 解释，列出符号、指数、尾数和对应的浮点数
+pub(crate) fn print_ieee754_fields(raw: u64, bits: u32) {
+    for line in ieee754_lines(raw, bits) {
+        println!("{}", line);
+    }
+}
+
+/// --float-bits 视图的各行
+fn ieee754_lines(raw: u64, bits: u32) -> Vec<String> {
+    // 指数和尾数的位数：f32 为 8 和 23，f64 为 11 和 52
+    let (exponent_bits, mantissa_bits) = if bits == 32 { (8, 23) } else { (11, 52) };
+    let raw = raw & (u64::MAX >> (64 - bits));
+    let sign = raw >> (bits - 1);
+    let exponent = (raw >> mantissa_bits) & ((1 << exponent_bits) - 1);
+    let mantissa = raw & ((1 << mantissa_bits) - 1);
+    let bias = (1i64 << (exponent_bits - 1)) - 1;
+    let max_exponent = (1 << exponent_bits) - 1;
+    let value = if bits == 32 {
+        format!("{:?}", f32::from_bits(raw as u32))
+    } else {
+        format!("{:?}", f64::from_bits(raw))
+    };
+    // 尾数补齐到整数个十六进制位，按 C 的 %a 格式给出有效数
+    let fraction_digits = (mantissa_bits as usize).div_ceil(4);
+    let fraction = mantissa << (fraction_digits * 4 - mantissa_bits as usize);
+    let kind = match (exponent, mantissa) {
+        (0, 0) if sign == 1 => tr!("负零", "negative zero"),
+        (0, 0) => tr!("零", "zero"),
+        (0, _) => tr!(
+            "非规格化数，没有隐含的前导 1: 0x0.{:0fraction_digits$X}p{}",
+            "subnormal, no implicit leading 1: 0x0.{:0fraction_digits$X}p{}",
+            fraction,
+            1 - bias
+        ),
+        (e, 0) if e == max_exponent => tr!("无穷大", "infinity"),
+        (e, _) if e == max_exponent => "NaN".to_string(),
+        (e, _) => tr!(
+            "规格化数，隐含前导 1: 0x1.{:0fraction_digits$X}p{:+}",
+            "normal, implicit leading 1: 0x1.{:0fraction_digits$X}p{:+}",
+            fraction,
+            e as i64 - bias
+        ),
+    };
+
+    let mut lines = vec![format!(
+        "IEEE 754 (f{}): 0x{:0digits$X}",
+        bits,
+        raw,
+        digits = bits as usize / 4
+    )];
+    lines.push(tr!(
+        "符号: {} ({})",
+        "Sign: {} ({})",
+        sign,
+        if sign == 1 { "-" } else { "+" }
+    ));
+    if exponent == 0 {
+        lines.push(tr!(
+            "指数: 0x0 (全 0，零和非规格化数的实际指数按 {} 计算)",
+            "Exponent: 0x0 (all zeros, zero and subnormals use an actual exponent of {})",
+            1 - bias
+        ));
+    } else if exponent == max_exponent {
+        lines.push(tr!(
+            "指数: 0x{:X} (全 1)",
+            "Exponent: 0x{:X} (all ones)",
+            exponent
+        ));
+    } else {
+        lines.push(tr!(
+            "指数: 0x{:X} (偏移值 {}，实际指数 {})",
+            "Exponent: 0x{:X} (bias {}, actual exponent {})",
+            exponent,
+            bias,
+            exponent as i64 - bias
+        ));
+    }
+    lines.push(tr!(
+        "尾数: 0x{:X} ({})",
+        "Mantissa: 0x{:X} ({})",
+        mantissa,
+        kind
+    ));
+    let (ew, mw) = (exponent_bits as usize, mantissa_bits as usize);
+    lines.push(tr!(
+        "位段: {} | {:0ew$b} | {:0mw$b}",
+        "Fields: {} | {:0ew$b} | {:0mw$b}",
+        sign,
+        exponent,
+        mantissa
+    ));
+    lines.push(tr!("数值: {}", "Value: {}", value));
+    lines
+}
+
+/// --endian 视图的字节数：--width 指定时为 width / 8，否则为能放下结果的最小的 2 的幂（1、2、4、8 或 16）
+fn byte_order_size(value: i128, width: Option<u32>) -> usize {
+    if let Some(width) = width {
+        return width as usize / 8;
+    }
+    [1, 2, 4, 8]
+        .into_iter()
+        .find(|&nbytes| {
+            let bits = nbytes * 8;
+            (-(1i128 << (bits - 1))..1i128 << bits).contains(&value)
+        })
+        .unwrap_or(16)
+}
+
+/// 连续的前缀掩码（高位全 1、低位全 0）的前缀长度，如 0xFFFFFF00 为 24；不连续时返回 None
+fn prefix_length(mask: u32) -> Option<u32> {
+    (mask.leading_ones() + mask.trailing_zeros() == 32).then_some(mask.leading_ones())
+}
+
+/// --ipv4 视图：把结果的低 32 位显示为点分十进制地址，是连续的前缀掩码时给出 /CIDR 长度
+pub(crate) fn print_ipv4(value: i128) {
+    for line in ipv4_lines(value) {
+        println!("{}", line);
+    }
+}
+
+/// --ipv4 视图的各行
+fn ipv4_lines(value: i128) -> Vec<String> {
+    let mut lines = Vec::new();
+    // 负数按 32 位补码理解，如 ~0xFF 即 255.255.255.0
+    if !(i32::MIN as i128..=u32::MAX as i128).contains(&value) {
+        lines.push(tr!(
+            "注意: 结果超出 32 位，IPv4 只取低 32 位",
+            "note: result exceeds 32 bits, IPv4 uses only the low 32 bits"
+        ));
+    }
+    let address = value as u32;
+    let [a, b, c, d] = address.to_be_bytes();
+    lines.push(match prefix_length(address) {
+        Some(length) => format!("IPv4: {}.{}.{}.{} = /{}", a, b, c, d, length),
+        None => tr!(
+            "IPv4: {}.{}.{}.{} (作为掩码不连续，没有前缀长度)",
+            "IPv4: {}.{}.{}.{} (not a contiguous mask, no prefix length)",
+            a,
+            b,
+            c,
+            d
+        ),
+    });
+    lines
+}
+
+/// 9999-12-31T23:59:59Z 对应的 Unix 时间戳，超过它的时间不再换算
+const MAX_TIMESTAMP: i128 = 253_402_300_799;
+
+/// 秒和毫秒难以区分的数量级：1e10 秒已是 2286 年，1e12 毫秒才到 2001 年，此范围内的值两种解释都给出
+const AMBIGUOUS_TIMESTAMPS: std::ops::Range<i128> = 10_000_000_000..1_000_000_000_000;
+
+/// 从 1970-01-01 起的天数换算为公历日期 (年, 月, 日)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // 以 0000-03-01 为起点按 400 年周期计算，闰日落在每年的最后一天
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// 把 Unix 时间戳（秒）格式化为 UTC 的 ISO 8601 时间，超出 1970..9999 年时返回错误
+fn utc_iso8601(seconds: i128) -> Result<String, String> {
+    if seconds < 0 {
+        return Err(tr!("超出范围 (早于 1970 年)", "out of range (before 1970)"));
+    }
+    if seconds > MAX_TIMESTAMP {
+        return Err(tr!("超出范围 (晚于 9999 年)", "out of range (after 9999)"));
+    }
+    let seconds = seconds as i64;
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let rest = seconds.rem_euclid(86_400);
+    Ok(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest / 60 % 60,
+        rest % 60
+    ))
+}
+
+/// 本地时间，时区由 TZ 环境变量或 /etc/localtime 给出，找不到时区时返回 None
+fn local_time(seconds: i128) -> Option<String> {
+    let seconds = i64::try_from(seconds).ok()?;
+    let offset = timezone::local_offset(seconds)?;
+    let local = seconds + offset;
+    let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+    let rest = local.rem_euclid(86_400);
+    let sign = if offset < 0 { '-' } else { '+' };
+    Some(format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}{:02}{:02}",
+        year,
+        month,
+        day,
+        rest / 3600,
+        rest / 60 % 60,
+        rest % 60,
+        sign,
+        offset.abs() / 3600,
+        offset.abs() / 60 % 60
+    ))
+}
+
+/// --time 视图的一行：按 unit 解释时间戳，给出 UTC 和本地时间
+fn print_timestamp_as(unit: &str, seconds: i128, millis: Option<i128>) {
+    match utc_iso8601(seconds) {
+        Ok(utc) => {
+            let utc = match millis {
+                Some(millis) => utc.replace('Z', &format!(".{:03}Z", millis)),
+                None => utc,
+            };
+            match local_time(seconds) {
+                Some(local) => println!(
+                    "{}",
+                    tr!(
+                        "时间 (按{}): {} (本地 {})",
+                        "Time (as {}): {} (local {})",
+                        unit,
+                        utc,
+                        local
+                    )
+                ),
+                None => println!("{}", tr!("时间 (按{}): {}", "Time (as {}): {}", unit, utc)),
+            }
+        }
+        Err(err) => println!("{}", tr!("时间 (按{}): {}", "Time (as {}): {}", unit, err)),
+    }
+}
+
+/// --time 视图：把结果当作 Unix 时间戳，按数量级判断是秒还是毫秒，难以区分时两种都给出
+pub(crate) fn print_timestamp(value: i128) {
+    let as_seconds = || print_timestamp_as(&tr!("秒", "seconds"), value, None);
+    let as_millis = || {
+        print_timestamp_as(
+            &tr!("毫秒", "milliseconds"),
+            value.div_euclid(1000),
+            Some(value.rem_euclid(1000)),
+        )
+    };
+    if AMBIGUOUS_TIMESTAMPS.contains(&value) {
+        as_seconds();
+        as_millis();
+    } else if value >= AMBIGUOUS_TIMESTAMPS.end {
+        as_millis();
+    } else {
+        as_seconds();
+    }
+}
+
+/// 能放下 value 的最少字节数，负数按补码计算
+pub(crate) fn minimal_byte_count(value: i128) -> usize {
+    (1..16)
+        .find(|&nbytes| {
+            let bits = nbytes * 8;
+            (-(1i128 << (bits - 1))..1i128 << bits).contains(&value)
+        })
+        .unwrap_or(16)
+}
+
+/// 把字节按 ASCII 字符显示，可打印字符原样输出，其余字节（以及引号和反斜杠）转义为 \xNN
+fn escape_ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| match byte {
+            b'"' | b'\\' => format!("\\{}", byte as char),
+            0x20..=0x7E => (byte as char).to_string(),
+            _ => format!("\\x{:02X}", byte),
+        })
+        .collect()
+}
+
+/// --ascii 视图：按大端序和小端序把结果的各个字节显示为字符，字节数由 --width 决定，否则取能放下结果的最少字节数
+pub(crate) fn print_ascii(value: i128, width: Option<u32>) {
+    for line in ascii_lines(value, width) {
+        println!("{}", line);
+    }
+}
+
+/// --ascii 视图的各行
+fn ascii_lines(value: i128, width: Option<u32>) -> Vec<String> {
+    let nbytes = width.map_or_else(|| minimal_byte_count(value), |width| width as usize / 8);
+    let mut bytes = le_byte_sequence(value, nbytes);
+    let le = escape_ascii(&bytes);
+    bytes.reverse();
+    vec![
+        tr!(
+            "ASCII (大端序, {} 字节): \"{}\"",
+            "ASCII (big endian, {} bytes): \"{}\"",
+            nbytes,
+            escape_ascii(&bytes)
+        ),
+        tr!(
+            "ASCII (小端序, {} 字节): \"{}\"",
+            "ASCII (little endian, {} bytes): \"{}\"",
+            nbytes,
+            le
+        ),
+    ]
+}
+
+/// 按 nbytes 个字节取 value 的补码，按小端序排列（最低字节在前）
+pub(crate) fn le_byte_sequence(value: i128, nbytes: usize) -> Vec<u8> {
+    value.to_le_bytes()[..nbytes].to_vec()
+}
+
+/// --endian 视图：按小端序和大端序列出结果的各个字节，指定字节序时只列出该字节序，只影响显示，不影响计算
+pub(crate) fn print_byte_order(value: i128, width: Option<u32>, order: Option<ByteOrder>) {
+    for line in byte_order_lines(value, width, order) {
+        println!("{}", line);
+    }
+}
+
+/// --endian 视图的各行
+fn byte_order_lines(value: i128, width: Option<u32>, order: Option<ByteOrder>) -> Vec<String> {
+    let nbytes = byte_order_size(value, width);
+    let mut bytes: Vec<String> = le_byte_sequence(value, nbytes)
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect();
+    let mut lines = Vec::new();
+    if order != Some(ByteOrder::Be) {
+        lines.push(tr!(
+            "小端字节序 (LE, {} 字节): {}",
+            "Little endian (LE, {} bytes): {}",
+            nbytes,
+            bytes.join(" ")
+        ));
+    }
+    bytes.reverse();
+    if order != Some(ByteOrder::Le) {
+        lines.push(tr!(
+            "大端字节序 (BE, {} 字节): {}",
+            "Big endian (BE, {} bytes): {}",
+            nbytes,
+            bytes.join(" ")
+        ));
+    }
+    lines
+}
+
+/// --bits 视图：从高到低列出为 1 的位，每位一行给出索引和对应的值，名称一列留空便于标注
+pub(crate) fn print_set_bits(bits_set: &[usize]) {
+    if bits_set.is_empty() {
+        println!("{}", tr!("置位: 无", "Set bits: none"));
+        return;
+    }
+    let bits: Vec<String> = bits_set.iter().rev().map(usize::to_string).collect();
+    println!("{}", tr!("置位: {}", "Set bits: {}", bits.join(", ")));
+    let values: Vec<String> = bits_set
+        .iter()
+        .rev()
+        .map(|&bit| format!("0x{:X}", 1u128 << bit))
+        .collect();
+    let width = values.iter().map(String::len).max().unwrap_or(0);
+    println!(
+        "{}  {}  {}",
+        pad_column(&tr!("位", "Bit"), 4),
+        pad_column(&tr!("值", "Value"), width),
+        tr!("名称", "Name")
+    );
+    for (bit, value) in bits.iter().zip(&values) {
+        println!("{}  {}", pad_column(bit, 4), value);
+    }
+}
+
+/// --fields 和 --decode 视图：按位段从高到低逐行输出名称、位范围、十六进制和十进制值以及该位段的二进制，
+/// 取值有名称时接在最后；reserved 为真时，最高位段以下没有命名的位作为 RESERVED 一并列出
+pub(crate) fn print_fields(value: i128, spec: &FieldSpec, reserved: bool, style: Style) {
+    let mut fields = spec.fields.clone();
+    fields.sort_by(|a, b| b.hi.cmp(&a.hi).then(b.lo.cmp(&a.lo)));
+    for (i, field) in fields.iter().enumerate() {
+        for other in &fields[i + 1..] {
+            if other.hi >= field.lo {
+                let warning = tr!(
+                    "警告: 位段 {} [{}:{}] 与 {} [{}:{}] 重叠",
+                    "warning: field {} [{}:{}] overlaps {} [{}:{}]",
+                    field.name,
+                    field.hi,
+                    field.lo,
+                    other.name,
+                    other.hi,
+                    other.lo
+                );
+                println!("{}", style.paint(Style::WARNING, &warning));
+            }
+        }
+    }
+    if reserved {
+        // 从最高位往下找出没有被任何位段覆盖的连续区间
+        let mut gaps = Vec::new();
+        let mut next = fields[0].hi as i64;
+        for field in &fields {
+            if (field.hi as i64) < next {
+                gaps.push(BitField {
+                    name: "RESERVED".to_string(),
+                    hi: next as u32,
+                    lo: field.hi + 1,
+                    values: Vec::new(),
+                });
+            }
+            next = next.min(field.lo as i64 - 1);
+        }
+        if next >= 0 {
+            gaps.push(BitField {
+                name: "RESERVED".to_string(),
+                hi: next as u32,
+                lo: 0,
+                values: Vec::new(),
+            });
+        }
+        fields.extend(gaps);
+        fields.sort_by(|a, b| b.hi.cmp(&a.hi).then(b.lo.cmp(&a.lo)));
+    }
+
+    let rows: Vec<(&BitField, i128)> = fields
+        .iter()
+        .map(|field| (field, bit_field(value, field.hi, field.lo)))
+        .collect();
+    let name_width = rows.iter().map(|(f, _)| f.name.len()).max().unwrap_or(0);
+    let hex_width = rows
+        .iter()
+        .map(|(_, v)| format!("0x{:X}", v).len())
+        .max()
+        .unwrap_or(0);
+    let dec_width = rows
+        .iter()
+        .map(|(_, v)| v.to_string().len())
+        .max()
+        .unwrap_or(0);
+    // 二进制一列显示为 bits 加上组间的空格
+    let bin_width = rows
+        .iter()
+        .map(|(f, _)| {
+            let bits = (f.hi - f.lo + 1) as usize;
+            bits + (bits - 1) / style.group_bits
+        })
+        .max()
+        .unwrap_or(0);
+    match &spec.register {
+        Some(register) => println!("{}", tr!("{} 位段:", "{} fields:", register)),
+        None => println!("{}", tr!("位段:", "Fields:")),
+    }
+    for (field, value) in rows {
+        let range = if field.hi == field.lo {
+            format!("[{}]", field.hi)
+        } else {
+            format!("[{}:{}]", field.hi, field.lo)
+        };
+        let bits = format!(
+            "{:0width$b}",
+            value,
+            width = (field.hi - field.lo + 1) as usize
+        );
+        // 二进制从最低位起按 --group-bits 分组
+        let groups: Vec<String> = bits
+            .as_bytes()
+            .rchunks(style.group_bits)
+            .rev()
+            .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+            .collect();
+        let name = field
+            .values
+            .iter()
+            .find(|(known, _)| *known == value)
+            .map_or(String::new(), |(_, name)| {
+                let shown = bits.len() + (bits.len() - 1) / style.group_bits;
+                format!("{}  = {}", " ".repeat(bin_width - shown), name)
+            });
+        println!(
+            "  {:<name_width$}  {:<7}  {:<hex_width$}  {:<dec_width$}  {}{}",
+            field.name,
+            range,
+            format!("0x{:X}", value),
+            value,
+            binary_row(&groups, style),
+            name
+        );
+    }
+}
+
+/// 按 8/16/32/64 位理解 value 的位模式，分别给出有符号和无符号的值；放不下 value 的位宽不列出
+fn print_sign_readings(value: i128, style: Style) {
+    let rows: Vec<(u32, i128, i128)> = [8, 16, 32, 64]
+        .into_iter()
+        .filter(|&bits| (-(1i128 << (bits - 1))..1i128 << bits).contains(&value))
+        .map(|bits| {
+            let unsigned = (value as u128 & (u128::MAX >> (128 - bits))) as i128;
+            (bits, sign_extend(value, bits), unsigned)
+        })
+        .collect();
+    if rows.is_empty() {
+        return;
+    }
+    let signed_title = tr!("有符号", "Signed");
+    let signed_width = rows
+        .iter()
+        .map(|(_, signed, _)| style.decimal(*signed).len())
+        .max()
+        .unwrap_or(0)
+        .max(display_width(&signed_title));
+    println!(
+        "{}  {}  {}",
+        pad_column(&tr!("位宽", "Bits"), 4),
+        pad_column(&signed_title, signed_width),
+        tr!("无符号", "Unsigned")
+    );
+    for (bits, signed, unsigned) in rows {
+        println!(
+            "{:>4}  {:<signed_width$}  {}",
+            bits,
+            style.decimal(signed),
+            style.decimal(unsigned)
+        );
+    }
+}
+
+/// enjoy conv 的完整输出：各进制、带位索引的二进制、字节序，以及各位宽下的有符号和无符号值
+pub(crate) fn print_conversion(value: i128, style: Style) {
+    println!("{}", tr!("十进制: {}", "Decimal: {}", style.decimal(value)));
+    print_hex_info(value, style);
+    print_octal_info(value);
+    print_binary_info(value, style, 0);
+    print_byte_order(value, None, None);
+    print_sign_readings(value, style);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_in_utc() {
+        assert_eq!(utc_iso8601(0).unwrap(), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_iso8601(1_700_000_000).unwrap(), "2023-11-14T22:13:20Z");
+        // 闰日
+        assert_eq!(utc_iso8601(1_709_208_000).unwrap(), "2024-02-29T12:00:00Z");
+        assert_eq!(utc_iso8601(951_825_600).unwrap(), "2000-02-29T12:00:00Z");
+        assert_eq!(utc_iso8601(1_709_251_199).unwrap(), "2024-02-29T23:59:59Z");
+        assert_eq!(utc_iso8601(1_709_251_200).unwrap(), "2024-03-01T00:00:00Z");
+        assert_eq!(utc_iso8601(MAX_TIMESTAMP).unwrap(), "9999-12-31T23:59:59Z");
+        assert!(utc_iso8601(-1).is_err());
+        assert!(utc_iso8601(MAX_TIMESTAMP + 1).is_err());
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(19_783), (2024, 3, 1));
+        // 1900 年不是闰年
+        assert_eq!(civil_from_days(-25_509), (1900, 2, 28));
+        assert_eq!(civil_from_days(-25_508), (1900, 3, 1));
+    }
+
+    #[test]
+    fn width_pads_hex_octal_and_binary() {
+        let formatted = |value: i128, width: u32| {
+            let options = EvalOptions {
+                width: Some(width),
+                ..EvalOptions::default()
+            };
+            FormattedResult::new(value, &options, None, &[])
+        };
+        let result = formatted(1, 32);
+        assert_eq!(result.hex, "0x00000001");
+        assert_eq!(result.octal, "0o00000000001");
+        assert_eq!(result.binary.len(), 32);
+        assert_eq!(formatted(1, 8).octal, "0o001");
+        assert_eq!(formatted(-1, 64).octal, "0o1777777777777777777777");
+        // 超出位宽时只显示低位
+        assert_eq!(formatted(0x1FF, 8).hex, "0xFF");
+        assert_eq!(formatted(0x1FF, 8).quiet(Base::Oct, false), "377");
+    }
+
+    #[test]
+    fn binary_wider_than_64_bits() {
+        let binary = get_padded_binary(1 << 64 | 5, 0, 4);
+        assert_eq!(binary.len(), 68);
+        assert!(binary.starts_with("0001"));
+        assert!(binary.ends_with("0101"));
+        let groups = split_into_groups(&binary, 4);
+        assert_eq!(groups.len(), 17);
+        let index = index_row(binary.len(), 4);
+        assert!(index.starts_with("  64   60"), "{}", index);
+        assert!(index.ends_with("   4    0"), "{}", index);
+        assert_eq!(get_padded_binary(1 << 70, 0, 8).len(), 72);
+        // i64 以内的负数按 64 位补码，更小的负数按 128 位补码
+        assert_eq!(get_padded_binary(-1, 0, 4).len(), 64);
+        let binary = get_padded_binary(-(1 << 64), 0, 4);
+        assert_eq!(binary.len(), 128);
+        assert_eq!(binary, format!("{}{}", "1".repeat(64), "0".repeat(64)));
+        assert!(index_row(128, 4).starts_with(" 124"));
+        assert_eq!(
+            format_binary(i128::MAX, 8),
+            format!("0{} {}", "1".repeat(7), vec!["1".repeat(8); 15].join(" "))
+        );
+    }
+
+    #[test]
+    fn byte_order_view() {
+        assert_eq!(
+            byte_order_lines(0x12345678, None, None),
+            [
+                "小端字节序 (LE, 4 字节): 78 56 34 12",
+                "大端字节序 (BE, 4 字节): 12 34 56 78"
+            ]
+        );
+        // 不指定 --width 时取能放下结果的最小的 2 的幂个字节
+        assert_eq!(byte_order_size(0x7F, None), 1);
+        assert_eq!(byte_order_size(0x100, None), 2);
+        assert_eq!(byte_order_size(0x1_0000, None), 4);
+        assert_eq!(byte_order_size(0x1_0000_0000, None), 8);
+        assert_eq!(byte_order_size(-129, None), 2);
+        assert_eq!(
+            byte_order_lines(0xAB, None, Some(ByteOrder::Le)),
+            ["小端字节序 (LE, 1 字节): AB"]
+        );
+        // 前导的 0 字节按 --width 保留
+        assert_eq!(
+            byte_order_lines(0x1234, Some(32), Some(ByteOrder::Be)),
+            ["大端字节序 (BE, 4 字节): 00 00 12 34"]
+        );
+        assert_eq!(
+            byte_order_lines(1, Some(64), Some(ByteOrder::Le)),
+            ["小端字节序 (LE, 8 字节): 01 00 00 00 00 00 00 00"]
+        );
+        // 负数按该宽度的补码
+        assert_eq!(
+            byte_order_lines(-2, Some(16), Some(ByteOrder::Be)),
+            ["大端字节序 (BE, 2 字节): FF FE"]
+        );
+    }
+
+    #[test]
+    fn human_size_view() {
+        assert_eq!(human_size_line(0), "大小: 0 B");
+        assert_eq!(human_size_line(1023), "大小: 1023 B");
+        assert_eq!(human_size_line(-1023), "大小: -1023 B");
+        assert_eq!(human_size_line(1024), "大小: 1.00 KiB / 1.02 KB (1024)");
+        assert_eq!(human_size_line(1025), "大小: 1.00 KiB / 1.02 KB (1025)");
+        assert_eq!(
+            human_size_line(1_610_612_736),
+            "大小: 1.50 GiB / 1.61 GB (1610612736)"
+        );
+        assert_eq!(
+            human_size_line(-(1 << 20)),
+            "大小: -1.00 MiB / -1.05 MB (-1048576)"
+        );
+        // SI 单位在 1000 处进位，二进制单位在 1024 处进位
+        assert_eq!(human_size(999, 1000, &SI_SIZE_UNITS), "999 B");
+        assert_eq!(human_size(1000, 1000, &SI_SIZE_UNITS), "1.00 KB");
+        assert_eq!(human_size(1000, 1024, &BINARY_SIZE_UNITS), "1000 B");
+        assert_eq!(human_size(1 << 20, 1024, &BINARY_SIZE_UNITS), "1.00 MiB");
+        // 超出最大的单位时仍用 EiB
+        assert_eq!(human_size(1 << 70, 1024, &BINARY_SIZE_UNITS), "1024.00 EiB");
+    }
+
+    #[test]
+    fn ascii_view() {
+        assert_eq!(
+            ascii_lines(0x41424344, None),
+            [
+                "ASCII (大端序, 4 字节): \"ABCD\"",
+                "ASCII (小端序, 4 字节): \"DCBA\""
+            ]
+        );
+        // 不可打印的字节、引号和反斜杠转义
+        assert_eq!(escape_ascii(b"a\0\"\\\x7F~"), "a\\x00\\\"\\\\\\x7F~");
+        // 最高字节为 0 时不计入最少字节数，负数按补码
+        assert_eq!(minimal_byte_count(0x00FF), 1);
+        assert_eq!(minimal_byte_count(0x0100), 2);
+        assert_eq!(minimal_byte_count(-1), 1);
+        assert_eq!(
+            ascii_lines(0x4869, Some(32)),
+            [
+                "ASCII (大端序, 4 字节): \"\\x00\\x00Hi\"",
+                "ASCII (小端序, 4 字节): \"iH\\x00\\x00\""
+            ]
+        );
+        assert_eq!(
+            ascii_lines(0, None),
+            [
+                "ASCII (大端序, 1 字节): \"\\x00\"",
+                "ASCII (小端序, 1 字节): \"\\x00\""
+            ]
+        );
+    }
+
+    #[test]
+    fn ipv4_view() {
+        assert_eq!(ipv4_lines(0xFFFFFF00), ["IPv4: 255.255.255.0 = /24"]);
+        assert_eq!(ipv4_lines(0), ["IPv4: 0.0.0.0 = /0"]);
+        assert_eq!(ipv4_lines(0xFFFFFFFF), ["IPv4: 255.255.255.255 = /32"]);
+        // 负数按 32 位补码
+        assert_eq!(ipv4_lines(!0xFF), ["IPv4: 255.255.255.0 = /24"]);
+        assert_eq!(
+            ipv4_lines(0xC0A80001),
+            ["IPv4: 192.168.0.1 (作为掩码不连续，没有前缀长度)"]
+        );
+        assert_eq!(
+            ipv4_lines(0x1_FFFF_0000),
+            [
+                "注意: 结果超出 32 位，IPv4 只取低 32 位",
+                "IPv4: 255.255.0.0 = /16"
+            ]
+        );
+    }
+
+    #[test]
+    fn digit_separators() {
+        assert_eq!(group_thousands(1_610_612_736), "1,610,612,736");
+        assert_eq!(group_thousands(-1_000), "-1,000");
+        // 不足或恰好 3 位时不加逗号
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(-999), "-999");
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(
+            group_thousands(i128::MIN),
+            "-170,141,183,460,469,231,731,687,303,715,884,105,728"
+        );
+        assert_eq!(group_hex_digits("5F5E1000"), "5F5E_1000");
+        assert_eq!(group_hex_digits("12345"), "1_2345");
+        // 不足或恰好 4 位时不加下划线
+        assert_eq!(group_hex_digits("FF"), "FF");
+        assert_eq!(group_hex_digits("FFFF"), "FFFF");
+        let style = Style {
+            color: false,
+            zebra: false,
+            group_bits: 8,
+            separators: true,
+        };
+        assert_eq!(style.decimal(-1_234_567), "-1,234,567");
+        assert_eq!(style.hex("DEADBEEF"), "0xDEAD_BEEF");
+        let plain = Style {
+            separators: false,
+            ..style
+        };
+        assert_eq!(plain.decimal(-1_234_567), "-1234567");
+        assert_eq!(plain.hex("DEADBEEF"), "0xDEADBEEF");
+    }
+
+    #[test]
+    fn float_bits_view() {
+        assert_eq!(
+            ieee754_lines(0x3F80_0000, 32),
+            [
+                "IEEE 754 (f32): 0x3F800000",
+                "符号: 0 (+)",
+                "指数: 0x7F (偏移值 127，实际指数 0)",
+                "尾数: 0x0 (规格化数，隐含前导 1: 0x1.000000p+0)",
+                "位段: 0 | 01111111 | 00000000000000000000000",
+                "数值: 1.0"
+            ]
+        );
+        // 只比较符号、指数、尾数和数值四行
+        let fields = |raw, bits| {
+            let lines = ieee754_lines(raw, bits);
+            [
+                lines[1].clone(),
+                lines[2].clone(),
+                lines[3].clone(),
+                lines[5].clone(),
+            ]
+        };
+        assert_eq!(
+            fields(0x8000_0000_0000_0000, 64),
+            [
+                "符号: 1 (-)",
+                "指数: 0x0 (全 0，零和非规格化数的实际指数按 -1022 计算)",
+                "尾数: 0x0 (负零)",
+                "数值: -0.0"
+            ]
+        );
+        // 最小的 f32 非规格化数 2^-149
+        assert_eq!(
+            fields(1, 32),
+            [
+                "符号: 0 (+)",
+                "指数: 0x0 (全 0，零和非规格化数的实际指数按 -126 计算)",
+                "尾数: 0x1 (非规格化数，没有隐含的前导 1: 0x0.000002p-126)",
+                "数值: 1e-45"
+            ]
+        );
+        assert_eq!(
+            fields(0xFFF0_0000_0000_0000, 64),
+            [
+                "符号: 1 (-)",
+                "指数: 0x7FF (全 1)",
+                "尾数: 0x0 (无穷大)",
+                "数值: -inf"
+            ]
+        );
+        assert_eq!(
+            fields(0x7FC0_0000, 32),
+            [
+                "符号: 0 (+)",
+                "指数: 0xFF (全 1)",
+                "尾数: 0x400000 (NaN)",
+                "数值: NaN"
+            ]
+        );
+        // 32 位时忽略高 32 位
+        assert_eq!(
+            ieee754_lines(0xFFFF_FFFF_3F80_0000, 32)[0],
+            "IEEE 754 (f32): 0x3F800000"
+        );
+    }
+
+    #[test]
+    fn radix_round_trip() {
+        assert_eq!(to_radix(255, 2), "11111111");
+        assert_eq!(to_radix(0, 36), "0");
+        assert_eq!(to_radix(-35, 36), "-z");
+        // 按 N 进制输出后再作为 N#digits 读入应得到原值
+        for radix in [2, 8, 16, 36] {
+            for value in [
+                0,
+                1,
+                -1,
+                35,
+                36,
+                255,
+                -4096,
+                i64::MAX as i128,
+                i128::MAX,
+                i128::MIN,
+            ] {
+                let digits = to_radix(value, radix);
+                let literal = match digits.strip_prefix('-') {
+                    Some(digits) => format!("-{}#{}", radix, digits),
+                    None => format!("{}#{}", radix, digits),
+                };
+                assert_eq!(
+                    crate::expr::parse_number(&literal),
+                    Ok(value),
+                    "{}",
+                    literal
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn other_signedness() {
+        // 最高位为 1 时给出另一种读法
+        assert_eq!(reinterpret_sign(0xFF, 8), Some(-1));
+        assert_eq!(reinterpret_sign(0x80, 8), Some(-128));
+        assert_eq!(reinterpret_sign(-1, 8), Some(0xFF));
+        assert_eq!(reinterpret_sign(-128, 8), Some(0x80));
+        assert_eq!(reinterpret_sign(0x8000_0000, 32), Some(i32::MIN as i128));
+        assert_eq!(reinterpret_sign(-1, 64), Some(u64::MAX as i128));
+        // 最高位为 0 或超出位宽时没有另一种读法
+        assert_eq!(reinterpret_sign(0x7F, 8), None);
+        assert_eq!(reinterpret_sign(0, 8), None);
+        assert_eq!(reinterpret_sign(0x100, 8), None);
+        assert_eq!(reinterpret_sign(-129, 8), None);
+    }
+}
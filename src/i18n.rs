@@ -0,0 +1,458 @@
+//! 输出语言：中文 (zh-CN，默认) 和英文 (en-US)
+//! 由 --lang 指定，没有指定时按 LC_ALL、LC_MESSAGES、LANG 环境变量选择，C 和 POSIX 为英文，
+//! 都不是中文或英文时为中文
+//! 表达式的错误在出错时只记录为 Message，输出时才按当前语言生成文字
+
+use crate::expr::Operator;
+use clap::ValueEnum;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 输出语言，--lang 的取值为 zh-CN (zh) 或 en-US (en)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Lang {
+    #[value(name = "zh-CN", alias = "zh")]
+    ZhCn,
+    #[value(name = "en-US", alias = "en")]
+    EnUs,
+}
+
+/// 当前的输出语言，按 Lang 的顺序保存
+static LANG: AtomicU8 = AtomicU8::new(Lang::ZhCn as u8);
+
+/// 设置之后所有输出和错误信息使用的语言
+pub(crate) fn set_lang(lang: Lang) {
+    LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub(crate) fn lang() -> Lang {
+    match LANG.load(Ordering::Relaxed) {
+        0 => Lang::ZhCn,
+        _ => Lang::EnUs,
+    }
+}
+
+impl Lang {
+    /// 按 locale 的写法选择语言，如 zh_CN.UTF-8、en_US；C 和 POSIX 的消息本来就是英文，
+    /// 其他语言返回 None
+    fn from_locale(locale: &str) -> Option<Lang> {
+        let language = locale.split(['_', '-', '.', '@']).next()?;
+        match language.to_ascii_lowercase().as_str() {
+            "zh" => Some(Lang::ZhCn),
+            "en" | "c" | "posix" => Some(Lang::EnUs),
+            _ => None,
+        }
+    }
+
+    /// 没有 --lang 时按环境变量选择，与 gettext 一样 LC_ALL 优先，其次是 LC_MESSAGES 和 LANG
+    pub(crate) fn from_env() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Lang::from_locale(&value))
+            .unwrap_or(Lang::ZhCn)
+    }
+}
+
+/// 按当前语言格式化文字，参数与 format! 相同：tr!("十进制: {}", "Decimal: {}", n)
+macro_rules! tr {
+    ($zh:literal, $en:literal $(, $arg:expr)* $(,)?) => {
+        match $crate::i18n::lang() {
+            $crate::i18n::Lang::ZhCn => format!($zh $(, $arg)*),
+            $crate::i18n::Lang::EnUs => format!($en $(, $arg)*),
+        }
+    };
+}
+pub(crate) use tr;
+
+/// 定义 Message 的各个变体及其中英文的写法，写法中可以直接引用变体的字段
+macro_rules! messages {
+    ($(
+        $(#[$meta:meta])*
+        $name:ident $({ $($field:ident: $ty:ty),* $(,)? })? => $zh:literal, $en:literal;
+    )*) => {
+        /// 表达式的错误：解析数字和拆分元素、检查结构以及计算时出错的原因
+        #[derive(Debug, Clone, PartialEq)]
+        #[non_exhaustive]
+        pub enum Message {
+            $($(#[$meta])* $name $({ $($field: $ty),* })?,)*
+        }
+
+        impl fmt::Display for Message {
+            #[allow(unused_variables)]
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    $(Message::$name $({ $($field),* })? => match lang() {
+                        Lang::ZhCn => write!(f, $zh),
+                        Lang::EnUs => write!(f, $en),
+                    },)*
+                }
+            }
+        }
+    };
+}
+
+messages! {
+    // 数字和字符字面量
+    BinarySuffix { literal: String, digits: String } =>
+        "b 后缀表示二进制，只能用于由 0 和 1 组成的数: {literal} (十六进制数请写作 0x{digits}B 或 {digits}Bh)",
+        "the b suffix means binary and only applies to numbers made of 0 and 1: {literal} (write hex numbers as 0x{digits}B or {digits}Bh)";
+    UnknownSizeSuffix { suffix: char, literal: String } =>
+        "未知的容量后缀 '{suffix}': {literal} (支持 k M G T P E)",
+        "unknown size suffix '{suffix}': {literal} (supported: k M G T P E)";
+    SizeSuffixNotDecimal { literal: String } =>
+        "容量后缀只能用于十进制数: {literal}",
+        "size suffixes only apply to decimal numbers: {literal}";
+    ThousandsSeparator { literal: String } =>
+        "千位分隔符 , 只能用于十进制数，且除第一组外每组必须是 3 位数字: {literal}",
+        "the thousands separator , only applies to decimal numbers, and every group but the first must have 3 digits: {literal}";
+    SeparatorAtEdge { literal: String } =>
+        "数字分隔符 _ 不能出现在数字开头或结尾: {literal}",
+        "the digit separator _ cannot start or end a number: {literal}";
+    ScientificNegativeExponent { literal: String } =>
+        "科学计数法只能表示整数，指数不能为负: {literal} (1E 容量后缀减去一个数请加上空格，如 1E - 3)",
+        "scientific notation only writes integers here, the exponent cannot be negative: {literal} (to subtract from a 1E size suffix, add spaces like 1E - 3)";
+    ScientificNotInteger { literal: String } =>
+        "科学计数法的结果不是整数: {literal} (小数部分的位数不能多于指数)",
+        "scientific notation does not give an integer: {literal} (the fraction cannot have more digits than the exponent)";
+    ScientificRange { literal: String } =>
+        "科学计数法的结果超出 64 位有符号整数的范围: {literal}",
+        "scientific notation result does not fit a signed 64-bit integer: {literal}";
+    InvalidRadix { radix: String, literal: String } =>
+        "进制必须是 2 到 36 的十进制数，而不是 {radix}: {literal}",
+        "the radix must be a decimal number from 2 to 36, not {radix}: {literal}";
+    InvalidRadixDigit { digit: char, radix: u32, literal: String } =>
+        "数字 '{digit}' 不能用于 {radix} 进制: {literal}",
+        "digit '{digit}' invalid for base {radix}: {literal}";
+    PrefixWithoutDigits { prefix: &'static str, literal: String } =>
+        "{prefix} 后面缺少数字: {literal}",
+        "{prefix} is not followed by any digits: {literal}";
+    NumberTooLarge { literal: String } =>
+        "数字过大: {literal}",
+        "number too large: {literal}";
+    InvalidNumber { literal: String } =>
+        "无效的数字: {literal}",
+        "invalid number: {literal}";
+    InvalidDecimal { literal: String } =>
+        "无效的小数: {literal}",
+        "invalid decimal number: {literal}";
+    LiteralTooWide { literal: String, width: u32, bits: u32 } =>
+        "字面量 {literal} 有 {width} 位，超出 --in-signed 指定的 {bits} 位",
+        "literal {literal} has {width} bits, more than the {bits} bits given by --in-signed";
+    UnterminatedChar { literal: String } =>
+        "字符字面量缺少结尾的 ': {literal}",
+        "character literal is missing the closing ': {literal}";
+    UnquotedChar { literal: String } =>
+        "字符字面量需要用单引号括起: {literal}",
+        "character literals must be enclosed in single quotes: {literal}";
+    UnknownEscape { escape: char, literal: String } =>
+        "未知的转义字符 '\\{escape}' (支持 \\n \\t \\r \\0 \\\\ \\' \\\"): {literal}",
+        "unknown escape '\\{escape}' (supported: \\n \\t \\r \\0 \\\\ \\' \\\"): {literal}";
+    UnescapedQuote { literal: String } =>
+        "字符字面量中的 ' 需要写作 \\': {literal}",
+        "a ' inside a character literal must be written as \\': {literal}";
+    NonAsciiChar { c: char, literal: String } =>
+        "字符字面量只支持 ASCII 字符，'{c}' 不是: {literal}",
+        "character literals only support ASCII, '{c}' is not: {literal}";
+    EmptyChar { literal: String } =>
+        "字符字面量为空: {literal}",
+        "empty character literal: {literal}";
+    CharTooLong { max: usize, literal: String } =>
+        "字符字面量最多 {max} 个字符: {literal}",
+        "character literals have at most {max} characters: {literal}";
+
+    // 拆分表达式
+    InvalidToken { input: String } =>
+        "无效的表达式部分: {input}",
+        "invalid expression part: {input}";
+    InvalidChar { c: char } =>
+        "无效的字符 '{c}'",
+        "invalid character '{c}'";
+    MissingCharQuote =>
+        "字符字面量缺少结尾的 '",
+        "character literal is missing the closing '";
+    /// 拆分表达式字符串时出错的列（从 1 开始）
+    AtColumn { column: usize, error: Box<Message> } =>
+        "第 {column} 列: {error}",
+        "column {column}: {error}";
+    InvalidExpression { expression: String, error: Box<Message> } =>
+        "无效的表达式 '{expression}': {error}",
+        "invalid expression '{expression}': {error}";
+    /// 没加引号的 * 被 shell 展开成了文件名
+    UnquotedGlob { expression: String, error: Box<Message> } =>
+        "无效的表达式 '{expression}': {error} (这看起来是文件名，是否忘了给 * 加引号?)",
+        "invalid expression '{expression}': {error} (this looks like a file name, did you forget to quote *?)";
+    ReadFailed { path: String, error: String } =>
+        "无法读取 {path}: {error}",
+        "cannot read {path}: {error}";
+    InvalidTokenJson { path: String, error: String } =>
+        "无效的 token JSON ({path}): {error}",
+        "invalid token JSON ({path}): {error}";
+    EmptyTokenList { path: String } =>
+        "token 列表为空: {path}",
+        "empty token list: {path}";
+    TooManyTokens { count: usize, max: usize } =>
+        "表达式过长: 共 {count} 个 token，上限为 {max}",
+        "expression too long: {count} tokens, the limit is {max}";
+    TooDeep { max: usize } =>
+        "括号嵌套过深: 超过 {max} 层",
+        "brackets nested too deeply: more than {max} levels";
+    ConvNotInteger { value: String } =>
+        "enjoy conv 只支持整数: {value}",
+        "enjoy conv only supports integers: {value}";
+    ConvNotLiteral { value: String } =>
+        "不是数字字面量: {value}，计算表达式请使用 enjoy calc",
+        "not a number literal: {value}, use enjoy calc to evaluate expressions";
+
+    // 表达式的结构
+    MissingOperator =>
+        "缺少操作符",
+        "missing operator";
+    MissingOperand =>
+        "缺少操作数",
+        "missing operand";
+    MissingLeftOperand =>
+        "缺少左操作数",
+        "missing left operand";
+    MissingRightOperand =>
+        "缺少右操作数",
+        "missing right operand";
+    TrailingOperator =>
+        "表达式末尾缺少操作数",
+        "missing operand at the end of the expression";
+    EvaluationFailed =>
+        "表达式计算失败",
+        "failed to evaluate the expression";
+    OperatorExpected { token: String } =>
+        "{token} 前缺少操作符",
+        "missing operator before {token}";
+    BitRangeOperatorExpected { open: char } =>
+        "{open} 前缺少操作符 (位段提取写作 v[hi:lo])",
+        "missing operator before {open} (bit ranges are written as v[hi:lo])";
+    UnaryAfterOperand { op: Operator } =>
+        "一元操作符 {op} 不能跟在操作数后面",
+        "unary operator {op} cannot follow an operand";
+    OperatorWithoutLeft { op: Operator } =>
+        "操作符 {op} 缺少左操作数",
+        "operator {op} is missing its left operand";
+    ChainedComparison { op: Operator } =>
+        "比较操作符不能连用: {op} 前面已经有比较，请拆开写作 [a < b] & [b < c]",
+        "comparisons cannot be chained: there is already a comparison before {op}, write [a < b] & [b < c] instead";
+    EmptyStatement =>
+        "; 前后缺少语句",
+        "missing statement around ;";
+    MissingAssignedValue { name: String } =>
+        "变量 {name} 缺少要赋的值",
+        "variable {name} is missing the value to assign";
+    AssignToNonVariable =>
+        "= 左边必须是变量名 (不能给数字或常量赋值)",
+        "the left side of = must be a variable name (numbers and constants cannot be assigned)";
+    AssignConstant { name: String } =>
+        "不能给内置常量 {name} 赋值",
+        "cannot assign to the built-in constant {name}";
+    AssignFunction { name: String } =>
+        "不能给内置函数 {name} 赋值",
+        "cannot assign to the built-in function {name}";
+    AssignAns =>
+        "ans 表示上一个结果，不能赋值",
+        "ans is the previous result and cannot be assigned";
+    MisplacedColon =>
+        ": 只能用于位段提取，如 v[27:24]",
+        ": is only used in bit ranges, such as v[27:24]";
+    MisplacedComma =>
+        ", 只能用于分隔函数参数，如 min[a, b]",
+        ", only separates function arguments, such as min[a, b]";
+    MisplacedAssign =>
+        "= 只能用于语句开头的变量赋值，如 base = 0x1000",
+        "= is only used to assign a variable at the start of a statement, such as base = 0x1000";
+    MisplacedSemicolon =>
+        "; 只能用于分隔语句，不能出现在括号中",
+        "; only separates statements and cannot appear inside brackets";
+    UnexpectedClosingBracket { close: char } =>
+        "多余的右括号 {close}",
+        "unexpected closing bracket {close}";
+    MismatchedBrackets { open: char, close: char } =>
+        "括号不匹配: {open} 与 {close} 不成对",
+        "mismatched brackets: {open} does not pair with {close}";
+    UnclosedBracket { open: char } =>
+        "括号 {open} 没有闭合",
+        "bracket {open} is not closed";
+    EmptyBrackets { open: char, close: char } =>
+        "括号 {open}{close} 内没有表达式",
+        "no expression inside {open}{close}";
+    BitRangeMissingBound =>
+        "位段提取缺少高位或低位，应写作 v[hi:lo]",
+        "bit range is missing its high or low bit, write v[hi:lo]";
+    MissingArguments { function: String } =>
+        "函数 {function} 后面需要参数列表，如 {function}[...]",
+        "function {function} needs an argument list, such as {function}[...]";
+    EmptyArgument { function: String, index: usize } =>
+        "函数 {function} 的第 {index} 个参数为空",
+        "argument {index} of function {function} is empty";
+    ArgumentCount { function: String, expected: usize, count: usize } =>
+        "函数 {function} 需要 {expected} 个参数，实际为 {count} 个",
+        "function {function} takes {expected} arguments, got {count}";
+    TooFewArguments { function: String, min: usize, count: usize } =>
+        "函数 {function} 至少需要 {min} 个参数，实际为 {count} 个",
+        "function {function} takes at least {min} arguments, got {count}";
+
+    // 变量
+    UnassignedVariable { name: String } =>
+        "变量 '{name}' 未赋值 (--list-constants 查看所有常量)",
+        "variable '{name}' is not assigned (see --list-constants for all constants)";
+    UnknownIdentifier { name: String, suggestions: String } =>
+        "未知的标识符 '{name}'，是否是: {suggestions}",
+        "unknown identifier '{name}', did you mean: {suggestions}";
+    NoPreviousResult =>
+        "没有上一个结果可供 ans 引用",
+        "there is no previous result for ans";
+
+    // 计算
+    DivisionByZero =>
+        "除零错误",
+        "division by zero";
+    /// 结果超出当前模式的范围，operation 为出错的运算，如 1 << 64
+    Overflow { operation: String } =>
+        "溢出: {operation}",
+        "overflow: {operation}";
+    WideProductOverflow { a: i128, b: i128 } =>
+        "溢出: mulwide[{a}, {b}] (乘积超出 i128，可使用 --wrap 查看高低 64 位)",
+        "overflow: mulwide[{a}, {b}] (the product exceeds i128, use --wrap to see the high and low 64 bits)";
+    ResultOutOfI64 { value: i128 } =>
+        "结果超出 i64 范围: {value}",
+        "result out of i64 range: {value}";
+    NegativeUnsigned { value: i128 } =>
+        "无符号模式下不能使用负数: {value}",
+        "negative numbers are not allowed in unsigned mode: {value}";
+    OutOfWidth { width: u32, value: i128 } =>
+        "数字超出 {width} 位范围: {value}",
+        "number out of {width}-bit range: {value}";
+    OutOfRegister { register: String, value: i128 } =>
+        "数字超出 {register} 范围: {value}",
+        "number out of {register} range: {value}";
+    OutOfSignedRegister { register: String, value: i128 } =>
+        "数字超出 {register} 范围: {value} (无符号数请使用 --unsigned)",
+        "number out of {register} range: {value} (use --unsigned for unsigned numbers)";
+    FloatInIntegerMode { value: f64 } =>
+        "整数模式下不能使用小数: {value} (--wrap、--unsigned 和 --mod 只支持整数)",
+        "decimals are not allowed in integer mode: {value} (--wrap, --unsigned and --mod only support integers)";
+    UnsignedNotTooWide { value: i128 } =>
+        "无符号模式下 ~ 只支持 64 位数值: {value}",
+        "~ only supports 64-bit values in unsigned mode: {value}";
+    UnsignedNegate { value: i128 } =>
+        "无符号模式下不能取负: -({value})",
+        "cannot negate in unsigned mode: -({value})";
+    ShiftOutOfRange { shift: i128, max: u32 } =>
+        "移位量 {shift} 超出范围 0..{max}",
+        "shift amount {shift} out of range 0..{max}";
+    NegativeExponent { exponent: i128 } =>
+        "指数不能为负数: {exponent}",
+        "the exponent cannot be negative: {exponent}";
+    ExponentTooLarge { exponent: i128 } =>
+        "指数过大: {exponent}",
+        "exponent too large: {exponent}";
+    BitRangeOutOfRange { hi: i128, lo: i128, max: i128 } =>
+        "位段 [{hi}:{lo}] 超出范围 0..{max}",
+        "bit range [{hi}:{lo}] out of range 0..{max}";
+    BitRangeReversed { hi: i128, lo: i128 } =>
+        "位段 [{hi}:{lo}] 的高位不能小于低位",
+        "the high bit of bit range [{hi}:{lo}] cannot be below the low bit";
+    FloatOperator { op: Operator } =>
+        "浮点模式下不支持操作符 {op}",
+        "operator {op} is not supported in floating-point mode";
+    FloatFunction { function: &'static str } =>
+        "浮点模式下不支持函数 {function}",
+        "function {function} is not supported in floating-point mode";
+    FloatBitRange =>
+        "浮点模式下不支持位段提取",
+        "bit ranges are not supported in floating-point mode";
+
+    // 时间长度的量纲
+    DurationTooPrecise { literal: String } =>
+        "时间只能精确到纳秒: {literal}",
+        "durations are precise to the nanosecond only: {literal}";
+    DurationMismatch { op: Operator } =>
+        "{op} 的两边必须都是时间或都不是时间，普通的数请加上单位 (如 5ms)",
+        "both sides of {op} must be durations or both plain numbers, give plain numbers a unit (e.g. 5ms)";
+    DurationArgumentsMismatch { function: &'static str } =>
+        "{function} 的参数必须都是时间或都不是时间",
+        "the arguments of {function} must all be durations or all plain numbers";
+    DurationProduct =>
+        "两个时间不能相乘",
+        "cannot multiply two durations";
+    DivideByDuration =>
+        "普通的数不能除以时间",
+        "cannot divide a plain number by a duration";
+    DurationOperator { op: Operator } =>
+        "时间不能参与 {op} 运算",
+        "durations cannot be used with {op}";
+    DurationArgument { function: &'static str } =>
+        "{function} 的参数不能是时间",
+        "the arguments of {function} cannot be durations";
+    DurationBitRange =>
+        "不能从时间中提取位段",
+        "cannot extract bits from a duration";
+
+    // 函数的参数
+    NoBitsSet { function: &'static str } =>
+        "{function}[0] 没有置位的位",
+        "{function}[0] has no set bits";
+    Log2NotPositive { value: i128 } =>
+        "log2 的参数必须为正数: {value}",
+        "the argument of log2 must be positive: {value}";
+    MaskOutOfRange { n: i128, max: i128 } =>
+        "mask 的位数 {n} 超出范围 0..{max}",
+        "mask width {n} out of range 0..{max}";
+    WideOperandRange { domain: &'static str, value: i128 } =>
+        "mulwide 的操作数必须在 {domain} 范围内: {value}",
+        "the operands of mulwide must be in {domain} range: {value}";
+    ByteCountRange { function: &'static str, count: i128 } =>
+        "{function} 的字节数 {count} 超出范围 1..8",
+        "byte count {count} of {function} out of range 1..8";
+    NegativeArgument { function: &'static str, value: i128 } =>
+        "{function} 的参数不能为负数: {value}",
+        "the argument of {function} cannot be negative: {value}";
+    MortonRange { function: &'static str, value: i128 } =>
+        "{function} 的坐标必须在 0..=0xFFFFFFFF 范围内（最多 32 位）: {value}",
+        "the coordinates of {function} must be in 0..=0xFFFFFFFF (at most 32 bits): {value}";
+    BcdTooLarge { value: i128 } =>
+        "tobcd 的参数超过 16 位十进制数，压缩 BCD 码放不进 64 位: {value}",
+        "the argument of tobcd has more than 16 decimal digits and does not fit in 64-bit packed BCD: {value}";
+    NotBcd { value: u64, nibble: u32, hi: u32, lo: u32, digit: u64 } =>
+        "frombcd 的参数 0x{value:X} 不是 BCD 码: 第 {nibble} 个半字节 (位 [{hi}:{lo}]) 为 0x{digit:X}",
+        "the argument of frombcd 0x{value:X} is not BCD: nibble {nibble} (bits [{hi}:{lo}]) is 0x{digit:X}";
+    FieldIndexRange { function: &'static str, index: i128, max: i128 } =>
+        "{function} 的序号 {index} 超出范围 0..{max}",
+        "index {index} of {function} out of range 0..{max}";
+    BitIndexRange { function: &'static str, bit: i128 } =>
+        "{function} 的位序号 {bit} 超出范围 0..63",
+        "bit {bit} of {function} out of range 0..63";
+    ExtendWidthRange { width: i128 } =>
+        "扩展的位宽 {width} 超出范围 1..64",
+        "extension width {width} out of range 1..64";
+    ArgumentTooWide { function: &'static str, width: u32, value: String } =>
+        "{function} 的参数超出 {width} 位: {value}",
+        "the argument of {function} exceeds {width} bits: {value}";
+    RegisterWidth { function: &'static str, width: i128 } =>
+        "{function} 的位宽只能是 8、16、32 或 64: {width}",
+        "the width of {function} must be 8, 16, 32 or 64: {width}";
+    AlignmentNotPowerOfTwo { align: i128 } =>
+        "对齐值必须是 2 的幂: {align}",
+        "the alignment must be a power of 2: {align}";
+    FrequencyNotPositive { function: &'static str, value: i128 } =>
+        "{function} 的频率必须为正数: {value}",
+        "the frequency of {function} must be positive: {value}";
+}
+
+impl Message {
+    /// 是否是计算结果超出范围的错误
+    pub(crate) fn is_overflow(&self) -> bool {
+        matches!(
+            self,
+            Message::Overflow { .. }
+                | Message::WideProductOverflow { .. }
+                | Message::ResultOutOfI64 { .. }
+        )
+    }
+}
@@ -0,0 +1,34 @@
+//! 程序员计算器的表达式求值和结果格式化，可以嵌入到其他工具中使用
+//!
+//! ```
+//! let tokens = enjoy::parse_expression("0x1000 + 4 x 1024").unwrap();
+//! assert_eq!(enjoy::evaluate(&tokens).unwrap(), 0x2000);
+//! ```
+//!
+//! 错误信息（Message 和各个错误类型的 Display）使用中文
+
+mod changes;
+mod cli;
+mod completions;
+mod config;
+mod crc;
+mod debug;
+mod error;
+mod expr;
+mod format;
+mod gerrit;
+mod i18n;
+mod line_editor;
+mod lint;
+mod regmap;
+mod timezone;
+
+// 公开的 API 只包括下面导出的类型和函数
+pub use error::{EnjoyError, EvalError, GerritError};
+pub use expr::{ExprToken, Operator, evaluate, parse_expression};
+pub use format::{format_binary, group_hex_digits, group_thousands, to_radix};
+pub use i18n::Message;
+
+/// enjoy 命令行的入口，供 src/main.rs 使用：解析进程的命令行参数并执行，返回进程的退出状态；
+/// 参数的定义 (clap) 留在私有的 cli 模块中，不属于公开的 API
+pub use cli::run_cli;
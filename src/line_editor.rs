@@ -0,0 +1,472 @@
+//! 交互模式的行编辑：左右移动光标、上下翻阅历史记录、Tab 补全，以及历史记录文件的读写
+//! 通过 stty 把终端切换到原始模式逐个读取按键；标准输入不是终端或没有 stty 时按行读取
+
+use crate::i18n::tr;
+use std::io::{self, BufRead, Read, Write};
+use std::panic;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, Once, TryLockError};
+
+/// 读取一行的结果
+pub enum Input {
+    Line(String),
+    /// 按了 Ctrl-C，放弃正在输入的内容
+    Interrupted,
+    /// 读到文件结尾，或在空行上按了 Ctrl-D
+    Eof,
+}
+
+pub struct LineEditor {
+    history: Vec<String>,
+    /// 标准输入是终端时才显示提示符并尝试原始模式
+    terminal: bool,
+}
+
+/// 终端的原始模式，离开作用域时恢复原来的设置
+struct RawMode;
+
+/// 进入原始模式前的终端设置 (stty -g 的输出)，不在原始模式时为 None
+static SAVED_MODE: Mutex<Option<String>> = Mutex::new(None);
+
+/// 原始模式下发生 panic 时，先恢复终端再输出 panic 信息，
+/// 否则信息没有回车、输入没有回显，终端在程序退出后也无法正常使用
+fn install_panic_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_mode();
+            previous(info);
+        }));
+    });
+}
+
+/// 恢复进入原始模式前的终端设置，已经恢复过时不做任何事
+fn restore_mode() {
+    let saved = match SAVED_MODE.try_lock() {
+        Ok(mut saved) => saved.take(),
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().take(),
+        Err(TryLockError::WouldBlock) => None,
+    };
+    if let Some(saved) = saved {
+        stty(&[saved.as_str()]);
+    }
+}
+
+impl RawMode {
+    fn enable() -> Option<Self> {
+        let saved = stty(&["-g"])?;
+        install_panic_hook();
+        stty(&["raw", "-echo"])?;
+        *SAVED_MODE
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(saved);
+        Some(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    /// 正常返回、出错和 panic 展开时都会恢复终端
+    fn drop(&mut self) {
+        restore_mode();
+    }
+}
+
+/// 对当前终端执行 stty，成功时返回其输出
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// 光标前正在输入的单词：标识符由字母、数字和下划线组成，行首的 : 开始一个命令，
+/// 因此 0x10+po 中补全的是 po；以数字开头的是数字字面量（如 0x10），不补全
+fn word_before(buffer: &[char], cursor: usize) -> Option<String> {
+    let start = buffer[..cursor]
+        .iter()
+        .rposition(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+        .map_or(0, |i| i + 1);
+    let start = if start == 1 && buffer[0] == ':' {
+        0
+    } else {
+        start
+    };
+    let word: String = buffer[start..cursor].iter().collect();
+    (!word.starts_with(|c: char| c.is_ascii_digit())).then_some(word)
+}
+
+/// 所有候选项共同的前缀
+fn common_prefix<'a>(candidates: &[&'a str]) -> &'a str {
+    let first = candidates[0];
+    let len = candidates[1..].iter().fold(first.len(), |len, candidate| {
+        first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((i, a), b)| *i < len && a == b)
+            .count()
+    });
+    let end = first
+        .char_indices()
+        .nth(len)
+        .map_or(first.len(), |(i, _)| i);
+    &first[..end]
+}
+
+/// 按 Tab 的结果
+#[derive(Debug, PartialEq)]
+enum Completion<'a> {
+    /// 没有可以补全的单词或候选项
+    None,
+    /// 在光标处插入候选项共同前缀中还没有输入的部分
+    Insert(&'a str),
+    /// 已经输入了所有候选项的共同前缀，连按两次 Tab 时列出这些候选项
+    Ambiguous(Vec<&'a str>),
+}
+
+/// 用 words 补全光标前的单词
+fn complete<'a>(buffer: &[char], cursor: usize, words: &'a [String]) -> Completion<'a> {
+    let Some(word) = word_before(buffer, cursor) else {
+        return Completion::None;
+    };
+    let candidates: Vec<&str> = words
+        .iter()
+        .map(String::as_str)
+        .filter(|candidate| candidate.starts_with(&word))
+        .collect();
+    if candidates.is_empty() {
+        return Completion::None;
+    }
+    let prefix = common_prefix(&candidates);
+    if prefix.len() > word.len() {
+        Completion::Insert(&prefix[word.len()..])
+    } else if candidates.len() > 1 {
+        Completion::Ambiguous(candidates)
+    } else {
+        Completion::None
+    }
+}
+
+/// 字符在终端中占的列数，中文字符占两列
+fn display_width(chars: &[char]) -> usize {
+    chars.iter().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+fn read_byte() -> io::Result<Option<u8>> {
+    let mut byte = [0u8];
+    match io::stdin().read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+impl LineEditor {
+    pub fn new(history: Vec<String>, terminal: bool) -> Self {
+        LineEditor { history, terminal }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// 记录一条历史，忽略空行和与上一条相同的内容
+    pub fn add_history(&mut self, entry: &str) {
+        let entry = entry.trim();
+        if !entry.is_empty() && self.history.last().is_none_or(|last| last != entry) {
+            self.history.push(entry.to_string());
+        }
+    }
+
+    /// 显示提示符并读取一行，按 Tab 时用 words 补全光标前的单词
+    pub fn read_line(&mut self, prompt: &str, words: &[String]) -> io::Result<Input> {
+        if self.terminal {
+            print!("{}", prompt);
+            io::stdout().flush()?;
+            if let Some(raw) = RawMode::enable() {
+                let input = self.edit(prompt, words);
+                drop(raw);
+                return input;
+            }
+        }
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            // 在提示符后按 Ctrl-D 时换行，避免 shell 提示符接在后面
+            if self.terminal {
+                println!();
+            }
+            return Ok(Input::Eof);
+        }
+        Ok(Input::Line(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    /// 原始模式下逐个处理按键，支持 Emacs 风格的快捷键、方向键和 Tab 补全
+    fn edit(&mut self, prompt: &str, words: &[String]) -> io::Result<Input> {
+        let mut buffer: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // 正在查看的历史记录，等于 history.len() 时为正在输入的新行
+        let mut index = self.history.len();
+        let mut draft: Vec<char> = Vec::new();
+        let mut stdout = io::stdout();
+        // 连续按两次 Tab 且不能再补全时列出所有候选项
+        let mut last_tab = false;
+        loop {
+            let Some(byte) = read_byte()? else {
+                write!(stdout, "\r\n")?;
+                return Ok(Input::Eof);
+            };
+            let tab = byte == b'\t';
+            match byte {
+                b'\t' => match complete(&buffer, cursor, words) {
+                    Completion::Insert(rest) => {
+                        let rest: Vec<char> = rest.chars().collect();
+                        buffer.splice(cursor..cursor, rest.iter().copied());
+                        cursor += rest.len();
+                    }
+                    Completion::Ambiguous(candidates) if last_tab => {
+                        write!(stdout, "\r\n{}\r\n", candidates.join("  "))?;
+                    }
+                    _ => {}
+                },
+                b'\r' | b'\n' => {
+                    write!(stdout, "\r\n")?;
+                    stdout.flush()?;
+                    return Ok(Input::Line(buffer.iter().collect()));
+                }
+                // Ctrl-C
+                3 => {
+                    write!(stdout, "^C\r\n")?;
+                    stdout.flush()?;
+                    return Ok(Input::Interrupted);
+                }
+                // Ctrl-D：空行时退出，否则删除光标处的字符
+                4 if buffer.is_empty() => {
+                    write!(stdout, "\r\n")?;
+                    stdout.flush()?;
+                    return Ok(Input::Eof);
+                }
+                4 if cursor < buffer.len() => {
+                    buffer.remove(cursor);
+                }
+                // Ctrl-A / Ctrl-E 行首、行尾，Ctrl-B / Ctrl-F 左右移动
+                1 => cursor = 0,
+                5 => cursor = buffer.len(),
+                2 => cursor = cursor.saturating_sub(1),
+                6 => cursor = (cursor + 1).min(buffer.len()),
+                // Ctrl-U 删除光标之前的内容，Ctrl-K 删除光标之后的内容
+                21 => {
+                    buffer.drain(..cursor);
+                    cursor = 0;
+                }
+                11 => buffer.truncate(cursor),
+                // Backspace
+                0x7F | 8 if cursor > 0 => {
+                    cursor -= 1;
+                    buffer.remove(cursor);
+                }
+                0x1B => {
+                    let Some(kind @ (b'[' | b'O')) = read_byte()? else {
+                        continue;
+                    };
+                    let mut code = read_byte()?.unwrap_or(0);
+                    // ESC [ 3 ~ 之类的序列，数字之后以 ~ 结尾
+                    let mut number = None;
+                    if kind == b'[' && code.is_ascii_digit() {
+                        number = Some(code);
+                        while code != b'~' && code.is_ascii_digit() {
+                            code = read_byte()?.unwrap_or(b'~');
+                        }
+                    }
+                    match (code, number) {
+                        (b'A', None) | (b'B', None) => {
+                            let target = if code == b'A' {
+                                index.checked_sub(1)
+                            } else {
+                                (index < self.history.len()).then_some(index + 1)
+                            };
+                            if let Some(target) = target {
+                                if index == self.history.len() {
+                                    draft = buffer.clone();
+                                }
+                                index = target;
+                                buffer = match self.history.get(index) {
+                                    Some(entry) => entry.chars().collect(),
+                                    None => draft.clone(),
+                                };
+                                cursor = buffer.len();
+                            }
+                        }
+                        (b'C', None) => cursor = (cursor + 1).min(buffer.len()),
+                        (b'D', None) => cursor = cursor.saturating_sub(1),
+                        (b'H', None) | (b'~', Some(b'1' | b'7')) => cursor = 0,
+                        (b'F', None) | (b'~', Some(b'4' | b'8')) => cursor = buffer.len(),
+                        (b'~', Some(b'3')) if cursor < buffer.len() => {
+                            buffer.remove(cursor);
+                        }
+                        _ => {}
+                    }
+                }
+                byte if byte >= 0x20 && byte != 0x7F => {
+                    // UTF-8 多字节字符按首字节读出剩余的字节
+                    let len = match byte {
+                        0xF0.. => 4,
+                        0xE0.. => 3,
+                        0xC0.. => 2,
+                        _ => 1,
+                    };
+                    let mut bytes = vec![byte];
+                    for _ in 1..len {
+                        bytes.extend(read_byte()?);
+                    }
+                    if let Ok(text) = std::str::from_utf8(&bytes) {
+                        for c in text.chars() {
+                            buffer.insert(cursor, c);
+                            cursor += 1;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            last_tab = tab;
+            // 重画整行，再把光标移回到正确的位置
+            write!(
+                stdout,
+                "\r{}{}\x1b[K",
+                prompt,
+                buffer.iter().collect::<String>()
+            )?;
+            let back = display_width(&buffer[cursor..]);
+            if back > 0 {
+                write!(stdout, "\x1b[{}D", back)?;
+            }
+            stdout.flush()?;
+        }
+    }
+}
+
+/// 读取历史记录文件，每行一条，只保留最近的 limit 条；文件不存在时为空
+/// 文件无法读取（如不是 UTF-8）时给出警告并从空的历史记录开始
+pub fn load_history(path: &Path, limit: usize) -> Vec<String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => {
+            let entries: Vec<String> = content
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::to_string)
+                .collect();
+            let skip = entries.len().saturating_sub(limit);
+            entries.into_iter().skip(skip).collect()
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => {
+            eprintln!(
+                "{}",
+                tr!(
+                    "警告: 无法读取历史记录 {}: {}，将使用空的历史记录",
+                    "warning: cannot read history {}: {}, starting with an empty history",
+                    path.display(),
+                    err
+                )
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// 写入历史记录文件，只保留最近的 limit 条，需要时创建所在的目录
+pub fn save_history(path: &Path, history: &[String], limit: usize) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let skip = history.len().saturating_sub(limit);
+    let mut content = history[skip..].join("\n");
+    content.push('\n');
+    std::fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &[&str]) -> Vec<String> {
+        list.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn complete_at_end<'a>(line: &str, words: &'a [String]) -> Completion<'a> {
+        let buffer: Vec<char> = line.chars().collect();
+        complete(&buffer, buffer.len(), words)
+    }
+
+    #[test]
+    fn word_before_cursor() {
+        let buffer: Vec<char> = "0x10+po".chars().collect();
+        assert_eq!(word_before(&buffer, 7).as_deref(), Some("po"));
+        assert_eq!(word_before(&buffer, 6).as_deref(), Some("p"));
+        assert_eq!(word_before(&buffer, 5).as_deref(), Some(""));
+        // 数字字面量不补全
+        assert_eq!(word_before(&buffer, 4), None);
+        // 行首的 : 属于命令
+        let buffer: Vec<char> = ":he".chars().collect();
+        assert_eq!(word_before(&buffer, 3).as_deref(), Some(":he"));
+        let buffer: Vec<char> = "a :he".chars().collect();
+        assert_eq!(word_before(&buffer, 5).as_deref(), Some("he"));
+    }
+
+    #[test]
+    fn common_prefix_of_candidates() {
+        assert_eq!(common_prefix(&["popcount"]), "popcount");
+        assert_eq!(common_prefix(&["popcount", "pow"]), "po");
+        assert_eq!(common_prefix(&["abs", "bits"]), "");
+        assert_eq!(common_prefix(&["变量一", "变量二"]), "变量");
+    }
+
+    #[test]
+    fn tab_completion() {
+        let words = words(&["align", "ans", "popcount", "pow", ":help", ":hex"]);
+        assert_eq!(
+            complete_at_end("1 + pop", &words),
+            Completion::Insert("count")
+        );
+        assert_eq!(complete_at_end("al", &words), Completion::Insert("ign"));
+        // 共同前缀已经输入完，列出候选项
+        assert_eq!(
+            complete_at_end("po", &words),
+            Completion::Ambiguous(vec!["popcount", "pow"])
+        );
+        assert_eq!(complete_at_end(":h", &words), Completion::Insert("e"));
+        assert_eq!(complete_at_end("popcount", &words), Completion::None);
+        assert_eq!(complete_at_end("xyz", &words), Completion::None);
+        assert_eq!(complete_at_end("0x1", &words), Completion::None);
+        // 光标在行中间时只看光标前的部分
+        let buffer: Vec<char> = "pop + 1".chars().collect();
+        assert_eq!(complete(&buffer, 3, &words), Completion::Insert("count"));
+    }
+
+    #[test]
+    fn add_history_skips_blank_and_repeated_entries() {
+        let mut editor = LineEditor::new(vec!["1 + 1".to_string()], false);
+        editor.add_history("  1 + 1 ");
+        editor.add_history("   ");
+        editor.add_history("2 x 3");
+        editor.add_history("1 + 1");
+        assert_eq!(editor.history(), ["1 + 1", "2 x 3", "1 + 1"]);
+    }
+
+    #[test]
+    fn history_file_keeps_the_latest_entries() {
+        let dir = std::env::temp_dir().join(format!("enjoy-history-{}", std::process::id()));
+        let path = dir.join("state").join("history");
+        let history = words(&["1", "2", "3", "4"]);
+        save_history(&path, &history, 3).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "2\n3\n4\n");
+        assert_eq!(load_history(&path, 2), ["3", "4"]);
+        assert_eq!(load_history(&dir.join("missing"), 10), Vec::<String>::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
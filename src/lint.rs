@@ -0,0 +1,461 @@
+//! 推送到 Gerrit 之前检查提交信息：标题长度、第二行为空、Change-Id，
+//! 以及 git config 的 enjoy.gerrit.subjectPattern 给出的标题格式
+//! 标题格式是正则表达式的一个子集：字面量、. [...] [^...] \d \w \s (及大写的取反)、
+//! ^ $、(...) (?:...) 与 |、* + ? {n} {n,} {n,m}，在标题中的任意位置匹配
+
+use crate::i18n::tr;
+use std::fmt;
+
+/// Gerrit 接受的标题最大长度（字符数）
+const MAX_SUBJECT: usize = 72;
+
+/// 提交信息不符合要求的一处
+#[derive(Debug)]
+pub(crate) enum Violation {
+    SubjectTooLong {
+        subject: String,
+        len: usize,
+    },
+    /// 标题和正文之间没有空行，带有第二行的内容
+    SecondLineNotBlank(String),
+    MissingChangeId,
+    SubjectPattern {
+        subject: String,
+        pattern: String,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Violation::SubjectTooLong { subject, len } => tr!(
+                "标题有 {} 个字符，超过了 {} 个: \"{}\"",
+                "subject is {} characters, longer than {}: \"{}\"",
+                len,
+                MAX_SUBJECT,
+                subject
+            ),
+            Violation::SecondLineNotBlank(line) => tr!(
+                "第二行应为空行: \"{}\"",
+                "second line should be blank: \"{}\"",
+                line
+            ),
+            Violation::MissingChangeId => tr!(
+                "最后一段中没有 Change-Id: 行",
+                "no Change-Id: line in the last paragraph"
+            ),
+            Violation::SubjectPattern { subject, pattern } => tr!(
+                "标题不符合格式 {}: \"{}\"",
+                "subject does not match {}: \"{}\"",
+                pattern,
+                subject
+            ),
+        };
+        f.write_str(&text)
+    }
+}
+
+/// 检查提交信息 (git log -1 --format=%B)，返回所有不符合要求的地方
+pub(crate) fn lint(message: &str, pattern: Option<&Pattern>) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut lines = message.trim().lines();
+    let subject = lines.next().unwrap_or_default().trim_end();
+    let len = subject.chars().count();
+    if len > MAX_SUBJECT {
+        violations.push(Violation::SubjectTooLong {
+            subject: subject.to_string(),
+            len,
+        });
+    }
+    if let Some(line) = lines.next().filter(|line| !line.trim().is_empty()) {
+        violations.push(Violation::SecondLineNotBlank(line.to_string()));
+    }
+    if !has_change_id(message) {
+        violations.push(Violation::MissingChangeId);
+    }
+    if let Some(pattern) = pattern
+        && !pattern.is_match(subject)
+    {
+        violations.push(Violation::SubjectPattern {
+            subject: subject.to_string(),
+            pattern: pattern.source.clone(),
+        });
+    }
+    violations
+}
+
+/// 提交信息的最后一段（脚注）中是否有 Change-Id: 行
+pub(crate) fn has_change_id(message: &str) -> bool {
+    let footer = message.trim().rsplit("\n\n").next().unwrap_or_default();
+    footer.lines().any(|line| {
+        line.strip_prefix("Change-Id:")
+            .is_some_and(|id| !id.trim().is_empty())
+    })
+}
+
+/// 编译好的标题格式
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern {
+    /// 原文，用于错误信息和 --show-config
+    pub(crate) source: String,
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    /// 括号中用 | 分隔的各个分支
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit,
+    Word,
+    Space,
+}
+
+impl ClassItem {
+    fn contains(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Range(low, high) => (low..=high).contains(&c),
+            ClassItem::Digit => c.is_ascii_digit(),
+            ClassItem::Word => c.is_ascii_alphanumeric() || c == '_',
+            ClassItem::Space => c.is_whitespace(),
+        }
+    }
+}
+
+impl Pattern {
+    /// 编译标题格式，不支持的写法返回错误
+    pub(crate) fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err(tr!("多余的右括号 )", "unexpected closing parenthesis )"));
+        }
+        Ok(Pattern {
+            source: source.to_string(),
+            nodes: vec![Node::Group(alternatives)],
+        })
+    }
+
+    /// 在 text 的任意位置能否匹配
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        (0..=chars.len()).any(|start| match_seq(&self.nodes, &chars, start, &|_| true))
+    }
+}
+
+/// 从 pos 开始匹配 nodes，每种匹配到的结束位置交给 next，next 返回 true 时停止回溯
+fn match_seq(nodes: &[Node], text: &[char], pos: usize, next: &dyn Fn(usize) -> bool) -> bool {
+    match nodes.split_first() {
+        None => next(pos),
+        Some((node, rest)) => match_node(node, text, pos, &|end| match_seq(rest, text, end, next)),
+    }
+}
+
+fn match_node(node: &Node, text: &[char], pos: usize, next: &dyn Fn(usize) -> bool) -> bool {
+    let single = |matches: bool| matches && next(pos + 1);
+    match node {
+        Node::Char(c) => single(text.get(pos) == Some(c)),
+        Node::Any => single(pos < text.len()),
+        Node::Class { items, negated } => single(
+            text.get(pos)
+                .is_some_and(|&c| items.iter().any(|item| item.contains(c)) != *negated),
+        ),
+        Node::Start => pos == 0 && next(pos),
+        Node::End => pos == text.len() && next(pos),
+        Node::Group(alternatives) => alternatives
+            .iter()
+            .any(|nodes| match_seq(nodes, text, pos, next)),
+        Node::Repeat { node, min, max } => match_repeat(node, *min, *max, 0, text, pos, next),
+    }
+}
+
+/// 贪婪地重复 node，已经匹配了 count 次；不计空匹配，避免无限循环
+fn match_repeat(
+    node: &Node,
+    min: usize,
+    max: Option<usize>,
+    count: usize,
+    text: &[char],
+    pos: usize,
+    next: &dyn Fn(usize) -> bool,
+) -> bool {
+    if max.is_none_or(|max| count < max)
+        && match_node(node, text, pos, &|end| {
+            end != pos && match_repeat(node, min, max, count + 1, text, end, next)
+        })
+    {
+        return true;
+    }
+    count >= min && next(pos)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    /// 用 | 分隔的分支，到 ) 或结尾为止
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantifier(atom)?);
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        let c = self.next().unwrap_or_default();
+        Ok(match c {
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '(' => {
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let alternatives = self.alternatives()?;
+                if self.next() != Some(')') {
+                    return Err(tr!("括号 ( 没有闭合", "parenthesis ( is not closed"));
+                }
+                Node::Group(alternatives)
+            }
+            '[' => self.class()?,
+            '\\' => self.escape()?,
+            '*' | '+' | '?' | '{' => {
+                return Err(tr!(
+                    "{} 前面没有可以重复的内容",
+                    "nothing to repeat before {}",
+                    c
+                ));
+            }
+            c => Node::Char(c),
+        })
+    }
+
+    /// \ 之后的转义：\d \w \s 及大写的取反，其他字符按字面量处理
+    fn escape(&mut self) -> Result<Node, String> {
+        let c = self.next().ok_or_else(|| tr!("\\ 在结尾", "trailing \\"))?;
+        let class = |item, negated| Node::Class {
+            items: vec![item],
+            negated,
+        };
+        Ok(match c {
+            'd' | 'D' => class(ClassItem::Digit, c == 'D'),
+            'w' | 'W' => class(ClassItem::Word, c == 'W'),
+            's' | 'S' => class(ClassItem::Space, c == 'S'),
+            'n' => Node::Char('\n'),
+            't' => Node::Char('\t'),
+            c if c.is_ascii_alphanumeric() => {
+                return Err(tr!("不支持的转义 \\{}", "unsupported escape \\{}", c));
+            }
+            c => Node::Char(c),
+        })
+    }
+
+    /// [ 之后的字符类，到 ] 为止；开头的 ] 和结尾的 - 按字面量处理
+    fn class(&mut self) -> Result<Node, String> {
+        let unclosed = || tr!("[ 没有闭合", "[ is not closed");
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().ok_or_else(unclosed)?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = match c {
+                '\\' => match self.escape()? {
+                    Node::Char(c) => c,
+                    Node::Class {
+                        items: escaped,
+                        negated: false,
+                    } => {
+                        items.extend(escaped);
+                        continue;
+                    }
+                    _ => return Err(tr!("字符类中不支持取反的转义", "negated escape in [...]")),
+                },
+                c => c,
+            };
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&c| c != ']') {
+                self.pos += 1;
+                let high = self.next().ok_or_else(unclosed)?;
+                if high < low {
+                    return Err(tr!(
+                        "范围 {}-{} 的顺序不对",
+                        "range {}-{} is out of order",
+                        low,
+                        high
+                    ));
+                }
+                items.push(ClassItem::Range(low, high));
+            } else {
+                items.push(ClassItem::Range(low, low));
+            }
+        }
+        Ok(Node::Class { items, negated })
+    }
+
+    /// atom 之后的 * + ? {n} {n,} {n,m}
+    fn quantifier(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => return self.braces(node),
+            _ => return Ok(node),
+        };
+        self.pos += 1;
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    fn braces(&mut self, node: Node) -> Result<Node, String> {
+        let start = self.pos;
+        let end = self.chars[start..]
+            .iter()
+            .position(|&c| c == '}')
+            .map(|offset| start + offset)
+            .ok_or_else(|| tr!("{{ 没有闭合", "{{ is not closed"))?;
+        let inner: String = self.chars[start + 1..end].iter().collect();
+        let bad = || tr!("无效的重复次数 {{{}}}", "invalid repetition {{{}}}", inner);
+        let number = |text: &str| text.trim().parse::<usize>().map_err(|_| bad());
+        let (min, max) = match inner.split_once(',') {
+            None => {
+                let n = number(&inner)?;
+                (n, Some(n))
+            }
+            Some((min, "")) => (number(min)?, None),
+            Some((min, max)) => (number(min)?, Some(number(max)?)),
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(bad());
+        }
+        self.pos = end + 1;
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGE_ID: &str = "Change-Id: I0123456789abcdef0123456789abcdef01234567";
+
+    fn check(message: &str, pattern: Option<&str>) -> Vec<Violation> {
+        let pattern = pattern.map(|source| Pattern::parse(source).unwrap());
+        lint(message, pattern.as_ref())
+    }
+
+    #[test]
+    fn commit_messages() {
+        let good = format!("uart: fix baud rate\n\nBody text.\n\n{}\n", CHANGE_ID);
+        assert!(check(&good, None).is_empty());
+        let long = format!("{}\n\n{}", "x".repeat(MAX_SUBJECT + 1), CHANGE_ID);
+        assert!(matches!(
+            check(&long, None).as_slice(),
+            [Violation::SubjectTooLong { len: 73, .. }]
+        ));
+        // 长度按字符而不是字节计算
+        let wide = format!("{}\n\n{}", "中".repeat(MAX_SUBJECT), CHANGE_ID);
+        assert!(check(&wide, None).is_empty());
+        assert!(matches!(
+            check("fix\nmore\n\nbody", None).as_slice(),
+            [Violation::SecondLineNotBlank(line), Violation::MissingChangeId] if line == "more"
+        ));
+        assert!(check(&good, Some(r"^\w+: [a-z]")).is_empty());
+        assert!(matches!(
+            check(&good, Some(r"^\[\w+\] ")).as_slice(),
+            [Violation::SubjectPattern { subject, .. }] if subject == "uart: fix baud rate"
+        ));
+    }
+
+    #[test]
+    fn change_id_must_be_in_the_footer() {
+        assert!(has_change_id(&format!("fix\n\n{}", CHANGE_ID)));
+        assert!(has_change_id(&format!(
+            "fix\n\nbody\n\nSigned-off-by: A <a@example.com>\n{}\n",
+            CHANGE_ID
+        )));
+        assert!(!has_change_id(&format!(
+            "fix\n\n{}\n\nmore body",
+            CHANGE_ID
+        )));
+        assert!(!has_change_id("fix\n\nChange-Id:   "));
+        assert!(!has_change_id(""));
+    }
+
+    #[test]
+    fn subject_patterns() {
+        let matches = |source: &str, text: &str| Pattern::parse(source).unwrap().is_match(text);
+        assert!(matches(
+            r"^(feat|fix)(\(\w+\))?: .+",
+            "fix(uart): baud rate"
+        ));
+        assert!(matches(r"^(feat|fix)(\(\w+\))?: .+", "feat: spi"));
+        assert!(!matches(r"^(feat|fix)(\(\w+\))?: .+", "chore: bump"));
+        assert!(matches(r"[A-Z]{2,4}-\d+", "Fix JIRA-123 crash"));
+        assert!(!matches(r"^[A-Z]{2,4}-\d+$", "ABCDE-1"));
+        assert!(matches(r"^[^a-z]", "Fix"));
+        assert!(matches(r"a*$", "anything"));
+        assert!(matches(r"(?:x|y)+z", "xyxz"));
+        for bad in ["(", ")", "[a", "*a", "a{3,1}", r"\q", "[z-a]", "a{", r"a\"] {
+            assert!(Pattern::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
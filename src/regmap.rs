@@ -0,0 +1,204 @@
+//! 寄存器描述文件 (--regmap)，描述各个寄存器的位段以及位段取值的名称
+//! 文件为 JSON 格式，示例见 regmaps/stm32f1_rcc.json：
+//!
+//! ```json
+//! {
+//!   "registers": {
+//!     "RCC_CFGR": {
+//!       "fields": [
+//!         { "name": "SW", "msb": 1, "lsb": 0, "values": { "0": "HSI", "1": "HSE", "2": "PLL" } }
+//!       ]
+//!     }
+//!   }
+//! }
+//! ```
+
+use crate::i18n::tr;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+/// 一个位段，values 为取值到名称的映射，键可以写作十进制或 0x/0b 前缀的数字
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Field {
+    pub name: String,
+    pub msb: u32,
+    pub lsb: u32,
+    #[serde(default)]
+    values: BTreeMap<String, String>,
+}
+
+/// 一个寄存器，地址只用于显示，不参与解码
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Register {
+    #[serde(default)]
+    pub address: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterMap {
+    registers: BTreeMap<String, Register>,
+}
+
+/// 解析取值名称的键，允许 0x、0b 前缀
+fn parse_key(key: &str) -> Option<i128> {
+    let key = key.trim();
+    if let Some(hex) = key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+        i128::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = key.strip_prefix("0b").or_else(|| key.strip_prefix("0B")) {
+        i128::from_str_radix(bin, 2).ok()
+    } else {
+        key.parse().ok()
+    }
+}
+
+/// 从 after 行开始查找第一处 "needle"，返回行号（从 1 开始），用于在错误信息中指出位置
+fn locate(content: &str, after: usize, needle: &str) -> Option<usize> {
+    let quoted = format!("\"{}\"", needle);
+    content
+        .lines()
+        .enumerate()
+        .skip(after.saturating_sub(1))
+        .find(|(_, line)| line.contains(&quoted))
+        .map(|(i, _)| i + 1)
+}
+
+impl Field {
+    /// 位段的取值及其名称，键在读取文件时已经检查过
+    pub fn values(&self) -> Vec<(i128, String)> {
+        self.values
+            .iter()
+            .filter_map(|(key, name)| Some((parse_key(key)?, name.clone())))
+            .collect()
+    }
+}
+
+impl RegisterMap {
+    /// 读取并检查寄存器描述文件，错误信息中带有文件路径，能确定位置时带有行号
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| tr!("无法读取 {}: {}", "cannot read {}: {}", path.display(), e))?;
+        let map: RegisterMap = serde_json::from_str(&content).map_err(|e| {
+            tr!(
+                "无效的寄存器描述文件 {}: {}",
+                "invalid register map {}: {}",
+                path.display(),
+                e
+            )
+        })?;
+        map.validate(&content)
+            .map_err(|(line, message)| match line {
+                Some(line) => format!("{}:{}: {}", path.display(), line, message),
+                None => format!("{}: {}", path.display(), message),
+            })?;
+        Ok(map)
+    }
+
+    /// 检查位段范围、重名、重叠以及取值名称的键
+    fn validate(&self, content: &str) -> Result<(), (Option<usize>, String)> {
+        for (register, definition) in &self.registers {
+            let register_line = locate(content, 1, register).unwrap_or(1);
+            let at = |field: &str| locate(content, register_line, field);
+            if definition.fields.is_empty() {
+                return Err((
+                    Some(register_line),
+                    tr!("寄存器 {} 没有位段", "register {} has no fields", register),
+                ));
+            }
+            let mut names = HashSet::new();
+            for (i, field) in definition.fields.iter().enumerate() {
+                let line = at(&field.name);
+                if field.msb >= 64 || field.lsb >= 64 {
+                    return Err((
+                        line,
+                        tr!(
+                            "{}.{} 的位段 [{}:{}] 超出范围 0..63",
+                            "{}.{}: bits [{}:{}] are out of range 0..63",
+                            register,
+                            field.name,
+                            field.msb,
+                            field.lsb
+                        ),
+                    ));
+                }
+                if field.msb < field.lsb {
+                    return Err((
+                        line,
+                        tr!(
+                            "{}.{} 的高位 {} 小于低位 {}",
+                            "{}.{}: high bit {} is less than low bit {}",
+                            register,
+                            field.name,
+                            field.msb,
+                            field.lsb
+                        ),
+                    ));
+                }
+                if !names.insert(field.name.as_str()) {
+                    return Err((
+                        locate(content, line.unwrap_or(register_line) + 1, &field.name),
+                        tr!(
+                            "寄存器 {} 中的位段 {} 重名",
+                            "register {} has more than one field named {}",
+                            register,
+                            field.name
+                        ),
+                    ));
+                }
+                if let Some(key) = field.values.keys().find(|key| parse_key(key).is_none()) {
+                    return Err((
+                        line,
+                        tr!(
+                            "{}.{} 的取值 \"{}\" 不是有效的数字",
+                            "{}.{}: value \"{}\" is not a valid number",
+                            register,
+                            field.name,
+                            key
+                        ),
+                    ));
+                }
+                if let Some(other) = definition.fields[..i]
+                    .iter()
+                    .find(|other| other.lsb <= field.msb && field.lsb <= other.msb)
+                {
+                    return Err((
+                        line,
+                        tr!(
+                            "{}.{} [{}:{}] 与 {} [{}:{}] 重叠",
+                            "{}.{} [{}:{}] overlaps {} [{}:{}]",
+                            register,
+                            field.name,
+                            field.msb,
+                            field.lsb,
+                            other.name,
+                            other.msb,
+                            other.lsb
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 按名称查找寄存器，名称不区分大小写，返回文件中的名称和寄存器定义
+    pub fn register(&self, register: &str) -> Result<(&str, &Register), String> {
+        self.registers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(register))
+            .map(|(name, definition)| (name.as_str(), definition))
+            .ok_or_else(|| {
+                let known: Vec<&str> = self.registers.keys().map(String::as_str).collect();
+                tr!(
+                    "未知的寄存器 {}，可用的寄存器: {}",
+                    "unknown register {}, available registers: {}",
+                    register,
+                    known.join(", ")
+                )
+            })
+    }
+}
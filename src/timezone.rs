@@ -0,0 +1,395 @@
+//! 本地时区：按 TZ 环境变量或 /etc/localtime 找到时区，换算某个时刻本地时间与 UTC 的偏移
+//! TZ 可以是时区名 (Asia/Shanghai，在 $TZDIR 或 /usr/share/zoneinfo 中查找)、以 : 开头的文件路径，
+//! 或者 POSIX 的写法 (CST-8、EST5EDT,M3.2.0,M11.1.0)；时区文件为 TZif 格式 (RFC 8536)，
+//! 最后一次跳变之后按文件末尾的 POSIX 规则计算，这样才能正确处理以后的夏令时
+
+use crate::format::civil_from_days;
+use std::path::{Path, PathBuf};
+
+/// POSIX TZ 写法中的夏令时规则
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    /// 标准时间与 UTC 的偏移
+    standard: i64,
+    /// 夏令时与 UTC 的偏移和开始、结束的日期，没有夏令时为 None
+    daylight: Option<(i64, RuleDate, RuleDate)>,
+}
+
+/// 夏令时开始或结束的日期和当天的时刻，时刻按切换前的本地时间计算（从 0 点起的秒数）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RuleDate {
+    day: RuleDay,
+    time: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RuleDay {
+    /// Jn：一年中的第 n 天 (1..=365)，不计 2 月 29 日
+    Julian(i64),
+    /// n：一年中的第 n 天 (0..=365)，从 0 开始，计入 2 月 29 日
+    Ordinal(i64),
+    /// Mm.w.d：m 月第 w 个星期 d (0 为星期日)，w 为 5 时是最后一个
+    Weekday { month: u32, week: i64, weekday: i64 },
+}
+
+/// 时区：按时间排列的跳变和之后的规则，偏移都以秒为单位、东边为正
+#[derive(Debug, Default)]
+struct Zone {
+    /// (跳变的时刻, 之后本地时间与 UTC 的偏移)
+    transitions: Vec<(i64, i64)>,
+    /// 第一次跳变之前的偏移
+    initial: Option<i64>,
+    /// 最后一次跳变之后的规则
+    rule: Option<Rule>,
+}
+
+/// Unix 时间戳 seconds 对应的本地时间与 UTC 的偏移（秒），找不到或无法读取时区时返回 None
+pub(crate) fn local_offset(seconds: i64) -> Option<i64> {
+    let tz = std::env::var("TZ").ok();
+    load_zone(tz.as_deref())?.offset_at(seconds)
+}
+
+/// 按 TZ 的值找到时区：没有设置时为 /etc/localtime，为空时为 UTC
+fn load_zone(tz: Option<&str>) -> Option<Zone> {
+    let Some(tz) = tz else {
+        return std::fs::read("/etc/localtime")
+            .ok()
+            .and_then(|data| parse_tzif(&data));
+    };
+    if tz.is_empty() {
+        return Some(Zone {
+            rule: Some(Rule {
+                standard: 0,
+                daylight: None,
+            }),
+            ..Zone::default()
+        });
+    }
+    let name = tz.strip_prefix(':').unwrap_or(tz);
+    let path = if Path::new(name).is_absolute() {
+        PathBuf::from(name)
+    } else {
+        let dir = std::env::var_os("TZDIR").unwrap_or_else(|| "/usr/share/zoneinfo".into());
+        PathBuf::from(dir).join(name)
+    };
+    match std::fs::read(path) {
+        Ok(data) => parse_tzif(&data),
+        Err(_) if !tz.starts_with(':') => parse_rule(tz).map(|rule| Zone {
+            rule: Some(rule),
+            ..Zone::default()
+        }),
+        Err(_) => None,
+    }
+}
+
+impl Zone {
+    fn offset_at(&self, seconds: i64) -> Option<i64> {
+        let after = self.transitions.partition_point(|(at, _)| *at <= seconds);
+        match (after, &self.rule) {
+            // 最后一次跳变之后有规则时按规则计算
+            (after, Some(rule)) if after == self.transitions.len() => Some(rule.offset_at(seconds)),
+            (0, _) => self
+                .initial
+                .or_else(|| self.transitions.first().map(|(_, offset)| *offset)),
+            (after, _) => Some(self.transitions[after - 1].1),
+        }
+    }
+}
+
+impl Rule {
+    fn offset_at(&self, seconds: i64) -> i64 {
+        let Some((daylight, start, end)) = self.daylight else {
+            return self.standard;
+        };
+        // 按本地标准时间所在的年份计算当年的切换时刻
+        let (year, _, _) = civil_from_days((seconds + self.standard).div_euclid(86_400));
+        let start = start.utc_in(year, self.standard);
+        let end = end.utc_in(year, daylight);
+        // 南半球的夏令时跨过新年，结束早于开始
+        let in_daylight = if start < end {
+            start <= seconds && seconds < end
+        } else {
+            !(end <= seconds && seconds < start)
+        };
+        if in_daylight { daylight } else { self.standard }
+    }
+}
+
+impl RuleDate {
+    /// year 年中这一时刻的 Unix 时间戳，offset 为切换前的本地时间与 UTC 的偏移
+    fn utc_in(self, year: i64, offset: i64) -> i64 {
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let new_year = days_from_civil(year, 1, 1);
+        let days = match self.day {
+            RuleDay::Julian(n) => new_year + n - 1 + (leap && n >= 60) as i64,
+            RuleDay::Ordinal(n) => new_year + n,
+            RuleDay::Weekday {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = days_from_civil(year, month, 1);
+                let next_month = if month == 12 {
+                    days_from_civil(year + 1, 1, 1)
+                } else {
+                    days_from_civil(year, month + 1, 1)
+                };
+                // 1970-01-01 是星期四
+                let mut day = first + (weekday - (first + 4)).rem_euclid(7) + (week - 1) * 7;
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            }
+        };
+        days * 86_400 + self.time - offset
+    }
+}
+
+/// 公历日期换算为从 1970-01-01 起的天数，与 format.rs 中的 civil_from_days 相反
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// 读取 TZif 数据，版本 2 以上使用 64 位的第二部分和末尾的 POSIX 规则
+fn parse_tzif(data: &[u8]) -> Option<Zone> {
+    if data.get(..4)? != b"TZif" {
+        return None;
+    }
+    let version = *data.get(4)?;
+    let (block, zone) = parse_tzif_block(data, 4)?;
+    if version < b'2' {
+        return Some(zone);
+    }
+    let rest = data.get(block..)?;
+    if rest.get(..4)? != b"TZif" {
+        return None;
+    }
+    let (end, mut zone) = parse_tzif_block(rest, 8)?;
+    // 末尾为 \n规则\n，规则为空时表示最后一次跳变之后不再变化
+    let footer = rest.get(end..)?;
+    let footer = std::str::from_utf8(footer).ok()?.trim_matches('\n');
+    if !footer.is_empty() {
+        zone.rule = Some(parse_rule(footer)?);
+    }
+    Some(zone)
+}
+
+/// 解析以 TZif 头开始的一部分，time_size 为跳变时刻的字节数；返回这部分结束的位置
+fn parse_tzif_block(data: &[u8], time_size: usize) -> Option<(usize, Zone)> {
+    let count = |index: usize| -> Option<usize> {
+        let bytes = data.get(20 + index * 4..24 + index * 4)?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?) as usize)
+    };
+    let (isutcnt, isstdcnt, leapcnt) = (count(0)?, count(1)?, count(2)?);
+    let (timecnt, typecnt, charcnt) = (count(3)?, count(4)?, count(5)?);
+    let times = 44;
+    let indices = times + timecnt * time_size;
+    let types = indices + timecnt;
+    let end = types + typecnt * 6 + charcnt + leapcnt * (time_size + 4) + isstdcnt + isutcnt;
+    if data.len() < end || typecnt == 0 {
+        return None;
+    }
+    // 每种本地时间为 6 字节：偏移 (i32)、是否夏令时和缩写的位置，只用到偏移
+    let offsets: Vec<i64> = (0..typecnt)
+        .map(|i| {
+            let at = types + i * 6;
+            i32::from_be_bytes(data[at..at + 4].try_into().unwrap()) as i64
+        })
+        .collect();
+    let transitions = (0..timecnt)
+        .map(|i| {
+            let at = times + i * time_size;
+            let time = if time_size == 8 {
+                i64::from_be_bytes(data[at..at + 8].try_into().unwrap())
+            } else {
+                i32::from_be_bytes(data[at..at + 4].try_into().unwrap()) as i64
+            };
+            let offset = *offsets.get(data[indices + i] as usize)?;
+            Some((time, offset))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let zone = Zone {
+        transitions,
+        // 第一次跳变之前使用第 0 种本地时间
+        initial: Some(offsets[0]),
+        rule: None,
+    };
+    Some((end, zone))
+}
+
+/// 解析 POSIX TZ 写法：std offset [dst [offset] [,start[/time],end[/time]]]
+/// 偏移按 POSIX 的习惯以西边为正 (CST-8 为东八区)，没有给出夏令时规则时使用美国的规则
+fn parse_rule(text: &str) -> Option<Rule> {
+    let mut rest = skip_name(text)?;
+    let (standard, after) = parse_hms(rest)?;
+    rest = after;
+    if rest.is_empty() {
+        return Some(Rule {
+            standard: -standard,
+            daylight: None,
+        });
+    }
+    rest = skip_name(rest)?;
+    let mut daylight = standard - 3600;
+    if !rest.is_empty() && !rest.starts_with(',') {
+        let (offset, after) = parse_hms(rest)?;
+        daylight = offset;
+        rest = after;
+    }
+    let rules = rest.strip_prefix(',').unwrap_or("M3.2.0,M11.1.0");
+    let (start, end) = rules.split_once(',')?;
+    Some(Rule {
+        standard: -standard,
+        daylight: Some((-daylight, parse_rule_date(start)?, parse_rule_date(end)?)),
+    })
+}
+
+/// 跳过时区的缩写：至少三个字母，或者用 <> 括起（可以包含数字和正负号，如 <+08>）
+fn skip_name(text: &str) -> Option<&str> {
+    if let Some(quoted) = text.strip_prefix('<') {
+        let end = quoted.find('>')?;
+        return Some(&quoted[end + 1..]);
+    }
+    let end = text
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    (end >= 3).then(|| &text[end..])
+}
+
+/// 解析 [+-]hh[:mm[:ss]]，返回秒数和剩下的部分
+fn parse_hms(text: &str) -> Option<(i64, &str)> {
+    let (sign, text) = match text.as_bytes().first()? {
+        b'-' => (-1, &text[1..]),
+        b'+' => (1, &text[1..]),
+        _ => (1, text),
+    };
+    let end = text
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or(text.len());
+    let mut seconds = 0;
+    let mut parts = 0;
+    for (part, scale) in text[..end].split(':').zip([3600, 60, 1]) {
+        seconds += part.parse::<i64>().ok()? * scale;
+        parts += 1;
+    }
+    (parts > 0 && text[..end].split(':').count() == parts).then_some((sign * seconds, &text[end..]))
+}
+
+/// 解析 Jn、n 或 Mm.w.d，后面可以跟 /time，默认为 02:00:00
+fn parse_rule_date(text: &str) -> Option<RuleDate> {
+    let (day, time) = match text.split_once('/') {
+        Some((day, time)) => {
+            let (time, rest) = parse_hms(time)?;
+            (day, rest.is_empty().then_some(time)?)
+        }
+        None => (text, 7200),
+    };
+    let day = if let Some(n) = day.strip_prefix('J') {
+        RuleDay::Julian(n.parse().ok().filter(|n| (1..=365).contains(n))?)
+    } else if let Some(spec) = day.strip_prefix('M') {
+        let mut fields = spec.split('.').map(str::parse::<i64>);
+        let (month, week, weekday) = (
+            fields.next()?.ok()?,
+            fields.next()?.ok()?,
+            fields.next()?.ok()?,
+        );
+        let valid = fields.next().is_none()
+            && (1..=12).contains(&month)
+            && (1..=5).contains(&week)
+            && (0..=6).contains(&weekday);
+        valid.then_some(RuleDay::Weekday {
+            month: month as u32,
+            week,
+            weekday,
+        })?
+    } else {
+        RuleDay::Ordinal(day.parse().ok().filter(|n| (0..=365).contains(n))?)
+    };
+    Some(RuleDate { day, time })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offset(tz: &str, seconds: i64) -> Option<i64> {
+        load_zone(Some(tz))?.offset_at(seconds)
+    }
+
+    #[test]
+    fn days_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+        for days in [-1, 0, 59, 365, 11_016, 19_782, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn posix_rules() {
+        assert_eq!(offset("", 1_700_000_000), Some(0));
+        assert_eq!(offset("CST-8", 1_700_000_000), Some(8 * 3600));
+        assert_eq!(offset("<+0530>-5:30", 0), Some(5 * 3600 + 1800));
+        assert_eq!(offset("UTC0", 0), Some(0));
+        // 美国东部：2023-03-12 02:00 EST (07:00 UTC) 开始夏令时，2023-11-05 02:00 EDT (06:00 UTC) 结束
+        let eastern = "EST5EDT,M3.2.0,M11.1.0";
+        assert_eq!(offset(eastern, 1_678_604_399), Some(-5 * 3600));
+        assert_eq!(offset(eastern, 1_678_604_400), Some(-4 * 3600));
+        assert_eq!(offset(eastern, 1_699_163_999), Some(-4 * 3600));
+        assert_eq!(offset(eastern, 1_699_164_000), Some(-5 * 3600));
+        // 没有给出规则时使用美国的规则
+        assert_eq!(offset("EST5EDT", 1_689_000_000), Some(-4 * 3600));
+        // 南半球跨过新年的夏令时：1 月为夏令时，7 月为标准时间
+        let sydney = "AEST-10AEDT,M10.1.0,M4.1.0/3";
+        assert_eq!(offset(sydney, 1_704_067_200), Some(11 * 3600));
+        assert_eq!(offset(sydney, 1_719_792_000), Some(10 * 3600));
+        // 无效的写法
+        assert_eq!(parse_rule("X-8"), None);
+        assert_eq!(parse_rule("CST"), None);
+        assert_eq!(parse_rule("EST5EDT,M13.1.0,M11.1.0"), None);
+    }
+
+    #[test]
+    fn rule_dates() {
+        // 2024 是闰年：J60 是 3 月 1 日，59 (从 0 开始) 是 2 月 29 日
+        let at = |text: &str| parse_rule_date(text).unwrap().utc_in(2024, 0);
+        assert_eq!(at("J60/0"), days_from_civil(2024, 3, 1) * 86_400);
+        assert_eq!(at("59/0"), days_from_civil(2024, 2, 29) * 86_400);
+        // 2024 年 3 月的第二个星期日是 10 日，10 月的最后一个星期日是 27 日
+        assert_eq!(at("M3.2.0"), days_from_civil(2024, 3, 10) * 86_400 + 7200);
+        assert_eq!(
+            at("M10.5.0/-1"),
+            days_from_civil(2024, 10, 27) * 86_400 - 3600
+        );
+    }
+
+    /// 系统中的时区文件，没有安装 tzdata 时跳过
+    #[test]
+    fn zoneinfo_files() {
+        if !Path::new("/usr/share/zoneinfo/Asia/Shanghai").exists() {
+            return;
+        }
+        assert_eq!(offset("Asia/Shanghai", 1_700_000_000), Some(8 * 3600));
+        // 1988 年中国实行夏令时
+        assert_eq!(
+            offset(":/usr/share/zoneinfo/Asia/Shanghai", 584_000_000),
+            Some(9 * 3600)
+        );
+        if Path::new("/usr/share/zoneinfo/America/New_York").exists() {
+            assert_eq!(offset("America/New_York", 1_689_000_000), Some(-4 * 3600));
+            // 超过文件中最后一次跳变，按末尾的规则计算
+            assert_eq!(offset("America/New_York", 4_102_444_800), Some(-5 * 3600));
+            assert_eq!(offset("America/New_York", 4_118_000_000), Some(-4 * 3600));
+        }
+        assert!(parse_tzif(b"not a tzif file").is_none());
+    }
+}
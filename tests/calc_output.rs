@@ -0,0 +1,313 @@
+//! enjoy calc 的输出格式
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+/// 运行 enjoy，不读取用户的配置文件，返回标准输出；输出使用中文
+fn stdout(args: &[&str]) -> String {
+    let output = enjoy(args);
+    assert!(output.status.success(), "{:?}: {:?}", args, output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn enjoy(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_enjoy"))
+        .arg("--no-config")
+        .args(args)
+        .env("LC_ALL", "zh_CN.UTF-8")
+        .env_remove("TZ")
+        .output()
+        .expect("无法运行 enjoy")
+}
+
+/// 标准输出的每一行都是一个 JSON 对象
+fn json_lines(output: &Output) -> Vec<serde_json::Value> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {}", line, e)))
+        .collect()
+}
+
+#[test]
+fn negative_numbers() {
+    // 负数参数不会被当作选项
+    assert_eq!(stdout(&["-q", "--calc", "-5", "+", "3"]), "-2\n");
+    assert_eq!(stdout(&["-q", "calc", "-0x10", "+", "1"]), "-15\n");
+    assert_eq!(stdout(&["-q", "calc", "-", "[3", "+", "4]"]), "-7\n");
+    // 负数的二进制按 64 位补码显示，并且写明了这一点
+    let output = stdout(&["calc", "-0b101"]);
+    assert!(output.contains("二进制 (64 位补码)"), "{}", output);
+    assert!(output.contains("1111 1011"), "{}", output);
+}
+
+#[test]
+fn results_wider_than_64_bits() {
+    let output = stdout(&["calc", "0xFFFFFFFFFFFF x 0x10000"]);
+    assert!(
+        output.contains("十六进制: 0xFFFFFFFFFFFF0000"),
+        "{}",
+        output
+    );
+    assert!(output.contains("结果超出 i64 范围"), "{}", output);
+    let output = stdout(&["calc", "1 << 70 | 5"]);
+    assert!(
+        output.contains("十进制: 1180591620717411303429"),
+        "{}",
+        output
+    );
+    assert!(output.contains("0100 0000"), "{}", output);
+    assert!(output.contains("  68   64"), "{}", output);
+}
+
+#[test]
+fn json_output() {
+    let output = enjoy(&["--json", "calc", "0x1000 + 0x10"]);
+    assert!(output.status.success());
+    let [result] = json_lines(&output).try_into().unwrap();
+    assert_eq!(result["decimal"], 4112);
+    assert_eq!(result["hex"], "0x1010");
+    assert_eq!(result["bits_set"], serde_json::json!([4, 12]));
+
+    // 出错时同样是一行 JSON，message 中没有回显的表达式和 ^
+    let output = enjoy(&["--json", "calc", "1 + 2 / 0"]);
+    assert_eq!(output.status.code(), Some(3));
+    let [error] = json_lines(&output).try_into().unwrap();
+    assert_eq!(
+        error,
+        serde_json::json!({"message": "除零错误", "token": 3})
+    );
+    let output = enjoy(&["--json", "calc", "1 +"]);
+    assert_eq!(output.status.code(), Some(2));
+    let [error] = json_lines(&output).try_into().unwrap();
+    assert!(!error["message"].as_str().unwrap().contains('\n'));
+    assert_eq!(error["token"], 1);
+
+    // 多个表达式时每行一个对象，--stdin 的错误带有行号
+    let output = Command::new(env!("CARGO_BIN_EXE_enjoy"))
+        .args(["--no-config", "--json", "--stdin"])
+        .env("LC_ALL", "zh_CN.UTF-8")
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            use std::io::Write;
+            child.stdin.take().unwrap().write_all(b"1 + 1\n2 / 0\n")?;
+            child.wait_with_output()
+        })
+        .unwrap();
+    let lines = json_lines(&output);
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["decimal"], 2);
+    assert_eq!(lines[1]["line"], 2);
+    assert_eq!(lines[1]["token"], 1);
+}
+
+#[test]
+fn decode_with_sample_regmap() {
+    let regmap = concat!(env!("CARGO_MANIFEST_DIR"), "/regmaps/stm32f1_rcc.json");
+    let output = stdout(&[
+        "calc",
+        "--regmap",
+        regmap,
+        "--decode",
+        "RCC_CFGR",
+        "0x001D040A",
+    ]);
+    assert!(output.contains("RCC_CFGR (0x40021004) 位段:"), "{}", output);
+    // 取值有名称的位段在最后给出名称
+    for line in [
+        "  PLLMUL    [21:18]  0x7  7  0111  = x9",
+        "  PLLSRC    [16]     0x1  1  1     = HSE",
+        "  PPRE1     [10:8]   0x4  4  100   = HCLK/2",
+        "  SW        [1:0]    0x2  2  10    = PLL",
+    ] {
+        assert!(output.lines().any(|l| l == line), "{}", output);
+    }
+    let output = enjoy(&["calc", "--regmap", regmap, "--decode", "RCC_XX", "1"]);
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("可用的寄存器: RCC_CFGR, RCC_CR"),
+        "{:?}",
+        output
+    );
+}
+
+/// --shell 的输出可以直接交给 sh 的 eval
+#[cfg(unix)]
+#[test]
+fn shell_output_evals() {
+    let output = stdout(&["calc", "--shell", "--shell-prefix", "ADDR_", "-255"]);
+    let echoed = Command::new("sh")
+        .arg("-c")
+        .arg("eval \"$1\"; echo \"$ADDR_DEC|$ADDR_HEX|$ADDR_OCT|$ADDR_BIN\"")
+        .arg("sh")
+        .arg(&output)
+        .output()
+        .unwrap();
+    assert!(echoed.status.success(), "{:?}", echoed);
+    let fields: Vec<String> = output
+        .lines()
+        .map(|line| line.split_once('=').unwrap().1.to_string())
+        .collect();
+    assert_eq!(
+        String::from_utf8(echoed.stdout).unwrap(),
+        format!("{}\n", fields.join("|"))
+    );
+    assert!(output.starts_with("ADDR_DEC=-255\n"), "{}", output);
+}
+
+#[test]
+fn diff_view() {
+    let output = enjoy(&["calc", "--diff", "0xF0", "0x3C"]);
+    // 两个值不同时退出状态为 1
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .collect::<Vec<_>>(),
+        [
+            "A:    1111 0000  0xF0",
+            "B:    0011 1100  0x3C",
+            "      ^^   ^^",
+            "         4    0",
+            "异或: 0xCC",
+            "不同的位 (4 位): 7, 6, 3, 2"
+        ]
+    );
+    assert_eq!(
+        stdout(&["calc", "--diff", "5", "0x5"]),
+        "两个值相同: 5 (0x5)\n"
+    );
+}
+
+#[test]
+fn other_signedness_reading() {
+    let output = stdout(&["calc", "--width", "8", "0xFF"]);
+    assert!(
+        output.starts_with("十进制: 255 (有符号: -1)\n"),
+        "{}",
+        output
+    );
+    let output = stdout(&["calc", "--width", "16", "-2"]);
+    assert!(
+        output.starts_with("十进制: -2 (无符号: 65534)\n"),
+        "{}",
+        output
+    );
+    let output = stdout(&["calc", "--width", "8", "0x7F"]);
+    assert!(output.starts_with("十进制: 127\n"), "{}", output);
+}
+
+/// --stdin 逐行计算，不为每一行启动进程或重新解析参数，10 万行也很快
+#[test]
+fn stdin_throughput() {
+    let input: String = (1..=100_000)
+        .map(|i| format!("{} x 2\n", i))
+        .chain(["\n".to_string(), "# 注释\n".to_string()])
+        .collect();
+    let start = Instant::now();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_enjoy"))
+        .args(["--no-config", "-q", "calc", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("无法运行 enjoy");
+    // 另起线程写入，避免双方都等待管道
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap().unwrap();
+    let elapsed = start.elapsed();
+    assert!(output.status.success(), "{:?}", output.status);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // 空行和注释不输出
+    assert_eq!(stdout.lines().count(), 100_000);
+    assert_eq!(stdout.lines().last(), Some("200000"));
+    // release 构建不到 1 秒，这里按未优化的构建留出余量
+    assert!(elapsed < Duration::from_secs(10), "{:?}", elapsed);
+}
+
+/// 调试输出只写到标准错误：-d 列出表达式元素，-dd 另外跟踪每一步运算
+#[test]
+fn debug_levels() {
+    let expression = ["-q", "calc", "(1 + 2) x 3"];
+    let output = enjoy(&expression);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "9\n");
+    assert!(output.stderr.is_empty(), "{:?}", output);
+
+    let output = enjoy(&[&["-d"], &expression[..]].concat());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "9\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("[debug] 元素 6: Operator(Mul) ('x')"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("1 + 2 = 3"), "{}", stderr);
+
+    let output = enjoy(&[&["-dd"], &expression[..]].concat());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "9\n");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("[debug] 元素 1: LeftRoundParen ('(')"),
+        "{}",
+        stderr
+    );
+    for step in [
+        "[debug] 进入括号 ( (第 1 个元素，第 1 层)",
+        "[debug] 1 + 2 = 3",
+        "[debug] 离开括号 () (第 5 个元素，第 1 层) = 3",
+        "[debug] 3 x 3 = 9",
+    ] {
+        assert!(stderr.lines().any(|line| line == step), "{}", stderr);
+    }
+}
+
+#[test]
+fn repeated_calc_flags() {
+    // 每个 --calc 单独计算，按输入顺序输出，并回显表达式
+    let output = enjoy(&["--calc", "1", "+", "2", "--calc", "0x10", "x", "3"]);
+    assert!(output.status.success(), "{:?}", output);
+    let text = String::from_utf8(output.stdout).unwrap();
+    let first = text.find("表达式 1: 1 + 2\n十进制: 3\n").expect(&text);
+    let second = text.find("表达式 2: 0x10 x 3\n十进制: 48\n").expect(&text);
+    assert!(first < second, "{}", text);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--calc 已弃用"));
+    // ans 指前一个表达式的结果
+    assert_eq!(
+        stdout(&["-q", "--calc", "1", "+", "2", "--calc", "ans", "x", "2"]),
+        "3\n6\n"
+    );
+    // 一个表达式出错不影响其余的，但退出码不为零
+    let output = enjoy(&["-q", "--calc", "1", "/", "0", "--calc", "2"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("除零错误"));
+}
+
+#[test]
+fn default_calc_from_config() {
+    let home = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("default_calc");
+    std::fs::create_dir_all(home.join("enjoy")).unwrap();
+    std::fs::write(
+        home.join("enjoy").join("config.toml"),
+        "default_calc = \"BASE + 0x1234\"\n[constants]\nBASE = 0x40000000\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_enjoy"))
+            .args(args)
+            .env("XDG_CONFIG_HOME", &home)
+            .env("LC_ALL", "zh_CN.UTF-8")
+            .output()
+            .expect("无法运行 enjoy");
+        assert!(output.status.success(), "{:?}: {:?}", args, output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    // 不带表达式时计算 default_calc，给出表达式时不计算
+    assert_eq!(run(&["-q"]), "1073746484\n");
+    assert_eq!(run(&["-q", "calc", "1"]), "1\n");
+    assert_eq!(run(&["-q", "--calc", "2"]), "2\n");
+    assert_eq!(run(&["-q", "--no-config"]), "");
+}
@@ -0,0 +1,128 @@
+//! 进程的退出状态：成功为 0，比较为假或断言失败为 1，解析错误为 2，计算错误为 3，
+//! 推送到 Gerrit 时 git 没有执行推送为 4，git 失败时为 git 的退出码，git 被信号终止为 125，找不到 git 为 127
+
+use std::process::{Command, Output};
+
+/// 运行 enjoy，不读取用户的配置文件，输出使用中文
+fn enjoy(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_enjoy"))
+        .arg("--no-config")
+        .args(args)
+        .env("LC_ALL", "zh_CN.UTF-8")
+        .env_remove("TZ")
+        .output()
+        .expect("无法运行 enjoy")
+}
+
+fn status(args: &[&str]) -> i32 {
+    enjoy(args).status.code().expect("enjoy 被信号终止")
+}
+
+#[test]
+fn success() {
+    assert_eq!(status(&["calc", "0x1000 + 4 x 1024"]), 0);
+    assert_eq!(status(&["calc", "1 < 2"]), 0);
+    assert_eq!(
+        status(&["--assert", "0x2000", "calc", "0x1000 + 4 x 1024"]),
+        0
+    );
+    assert_eq!(status(&["--assert", "-1", "calc", "0 - 1"]), 0);
+    assert_eq!(status(&["--check", "calc", "1 / 0"]), 0);
+    let output = enjoy(&["-q", "calc", "4 x 1024"]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4096\n");
+}
+
+#[test]
+fn false_comparison_and_failed_assertion() {
+    assert_eq!(status(&["calc", "1 == 2"]), 1);
+    assert_eq!(status(&["--assert", "3", "calc", "1 + 1"]), 1);
+    // --diff 与 diff、cmp 一致：相同为 0，不同为 1，出错为 2
+    assert_eq!(status(&["--diff", "0xFF", "255"]), 0);
+    assert_eq!(status(&["--diff", "1", "2"]), 1);
+    assert_eq!(status(&["--diff", "1", "2 +"]), 2);
+}
+
+#[test]
+fn parse_errors() {
+    for expression in ["1 +", "0b", "1e19", "0x1G", "1 2"] {
+        assert_eq!(status(&["calc", expression]), 2, "{}", expression);
+    }
+    // 不成对的括号在计算之前就报告为解析错误
+    for expression in ["1+2x[3+4", "1 / 0 + [", "1 ]", "[1)"] {
+        assert_eq!(status(&["calc", expression]), 2, "{}", expression);
+        assert_eq!(
+            status(&["--check", "calc", expression]),
+            2,
+            "{}",
+            expression
+        );
+    }
+    let output = enjoy(&["calc", "1+2x[3+4"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("括号 [ 没有闭合"));
+    // 命令行参数错误与 clap 一致
+    assert_eq!(status(&["--no-such-option"]), 2);
+    assert_eq!(status(&["--assert", "3"]), 2);
+}
+
+#[test]
+fn eval_errors() {
+    assert_eq!(status(&["calc", "1 / 0"]), 3);
+    assert_eq!(status(&["calc", "2 ** 200"]), 3);
+    assert_eq!(status(&["calc", "undefined_name + 1"]), 3);
+    assert_eq!(status(&["calc", "1s + 1"]), 3);
+    assert_eq!(status(&["calc", "1s x 1s"]), 3);
+    assert_eq!(status(&["--wrap", "--unsigned", "calc", "-1"]), 3);
+}
+
+#[test]
+fn first_failing_expression_decides() {
+    assert_eq!(status(&["-c", "1 / 0", "-c", "1 +"]), 3);
+    assert_eq!(status(&["-c", "1 +", "-c", "1 / 0"]), 2);
+}
+
+#[test]
+fn gerrit_without_git() {
+    let empty = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-git");
+    std::fs::create_dir_all(&empty).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_enjoy"))
+        .args(["--no-config", "gerrit", "--yes"])
+        .env("PATH", &empty)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&empty).unwrap();
+    assert_eq!(output.status.code(), Some(127));
+}
+
+/// 在 PATH 中只放一个假的 git：push 时执行 on_push，log 时给出一个提交，config 没有设置，其他命令什么也不做
+#[cfg(unix)]
+fn gerrit_with_stub_git(name: &str, on_push: &str) -> Option<i32> {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("stub-git")
+        .join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    let git = dir.join("git");
+    let script = format!(
+        "#!/bin/sh\ncase \"$1\" in\n  push) {} ;;\n  log) echo 'abc1234 uart: fix baud rate' ;;\n  config) exit 1 ;;\nesac\nexit 0\n",
+        on_push
+    );
+    std::fs::write(&git, script).unwrap();
+    std::fs::set_permissions(&git, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_enjoy"))
+        .args(["--no-config", "gerrit", "--yes", "--force", "--no-verify"])
+        .args(["--branch", "main"])
+        .current_dir(&dir)
+        .env("PATH", &dir)
+        .output()
+        .unwrap();
+    output.status.code()
+}
+
+#[cfg(unix)]
+#[test]
+fn gerrit_passes_git_exit_status_through() {
+    assert_eq!(gerrit_with_stub_git("exit-7", "exit 7"), Some(7));
+    assert_eq!(gerrit_with_stub_git("exit-0", "exit 0"), Some(0));
+    // git 被信号终止时没有退出码
+    assert_eq!(gerrit_with_stub_git("killed", "kill -9 $$"), Some(125));
+}
@@ -0,0 +1,288 @@
+//! enjoy gerrit --dry-run 输出的推送命令；在临时目录中运行，不读取用户和系统的 git 配置
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// 不在任何 git 仓库中的目录，同时作为 HOME；gitconfig 为写入 ~/.gitconfig 的内容
+/// 测试并行运行，每个 name 使用单独的目录
+fn workdir(name: &str, gitconfig: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("gerrit")
+        .join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(".gitconfig"), gitconfig).unwrap();
+    dir
+}
+
+/// 运行 enjoy gerrit --dry-run，返回标准输出中的命令和标准错误
+fn dry_run(args: &[&str]) -> (String, String) {
+    dry_run_with_config("plain", "", args)
+}
+
+/// 在 ~/.gitconfig 为 gitconfig 时运行 enjoy gerrit --dry-run，name 为目录名
+fn dry_run_with_config(name: &str, gitconfig: &str, args: &[&str]) -> (String, String) {
+    let output = run(name, gitconfig, args);
+    assert!(output.status.success(), "{:?}: {:?}", args, output);
+    (
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .trim_end()
+            .to_string(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+/// 参数有误、没有执行推送时的退出状态
+fn rejected(args: &[&str]) -> i32 {
+    let output = run("plain", "", args);
+    assert!(output.stdout.is_empty(), "{:?}: {:?}", args, output);
+    output.status.code().unwrap()
+}
+
+fn run(name: &str, gitconfig: &str, args: &[&str]) -> Output {
+    let mut dry_run = vec!["--dry-run"];
+    dry_run.extend(args);
+    gerrit(name, gitconfig, &dry_run)
+}
+
+/// 在 workdir(name, gitconfig) 中运行 enjoy gerrit，标准输入不是终端
+fn gerrit(name: &str, gitconfig: &str, args: &[&str]) -> Output {
+    let dir = workdir(name, gitconfig);
+    Command::new(env!("CARGO_BIN_EXE_enjoy"))
+        .args(["--no-config", "gerrit"])
+        .args(args)
+        .stdin(Stdio::null())
+        .current_dir(&dir)
+        .env("HOME", &dir)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .env("GIT_CEILING_DIRECTORIES", dir.parent().unwrap())
+        .env("LC_ALL", "zh_CN.UTF-8")
+        .output()
+        .expect("无法运行 enjoy")
+}
+
+#[test]
+fn branch() {
+    let (command, stderr) = dry_run(&[]);
+    assert_eq!(command, "git push origin HEAD:refs/for/develop");
+    assert!(stderr.contains("没有上游分支"), "{}", stderr);
+    let (command, stderr) = dry_run(&["--branch", "main"]);
+    assert_eq!(command, "git push origin HEAD:refs/for/main");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn reviewers() {
+    let (command, _) = dry_run(&[
+        "--branch",
+        "main",
+        "--reviewer",
+        "alice",
+        "--reviewer",
+        "bob",
+    ]);
+    assert_eq!(command, "git push origin HEAD:refs/for/main%r=alice,r=bob");
+}
+
+#[test]
+fn topic() {
+    let (command, _) = dry_run(&["--branch", "main", "--topic", "uart"]);
+    assert_eq!(command, "git push origin HEAD:refs/for/main%topic=uart");
+    // git config 中的前缀加在 --topic 之前
+    let (command, _) = dry_run_with_config(
+        "topic-prefix",
+        "[enjoy \"gerrit\"]\n\ttopicPrefix = bsp-\n",
+        &["--branch", "main", "--topic", "uart"],
+    );
+    assert_eq!(command, "git push origin HEAD:refs/for/main%topic=bsp-uart");
+}
+
+#[test]
+fn change_state() {
+    let (command, _) = dry_run(&["--branch", "main", "--wip", "--private"]);
+    assert_eq!(command, "git push origin HEAD:refs/for/main%wip,private");
+    let (command, _) = dry_run(&["--branch", "main", "--ready", "--remove-private"]);
+    assert_eq!(
+        command,
+        "git push origin HEAD:refs/for/main%ready,remove-private"
+    );
+    assert_eq!(rejected(&["--wip", "--ready"]), 2);
+    assert_eq!(rejected(&["--private", "--remove-private"]), 2);
+}
+
+#[test]
+fn hashtags() {
+    let (command, _) = dry_run(&["--branch", "main", "--hashtag", "bsp", "--hashtag", "uart"]);
+    assert_eq!(command, "git push origin HEAD:refs/for/main%t=bsp,t=uart");
+    assert_eq!(rejected(&["--hashtag", "a,b"]), 2);
+}
+
+#[test]
+fn cc() {
+    let (command, stderr) = dry_run(&[
+        "--branch",
+        "main",
+        "--reviewer",
+        "alice",
+        "--cc",
+        "alice",
+        "--cc",
+        "bob",
+        "--cc",
+        "bob",
+    ]);
+    // 已经是评审人的不再抄送，重复的抄送只保留一个
+    assert_eq!(command, "git push origin HEAD:refs/for/main%r=alice,cc=bob");
+    assert!(stderr.contains("alice 已经是评审人"), "{}", stderr);
+}
+
+#[test]
+fn remote() {
+    let (command, _) = dry_run(&["--branch", "main", "--remote", "gerrit"]);
+    assert_eq!(command, "git push gerrit HEAD:refs/for/main");
+    assert_eq!(rejected(&["--remote", ""]), 2);
+}
+
+#[test]
+fn dry_run_and_server_dry_run_conflict() {
+    // --server-dry-run 会执行 git push --dry-run，与只输出命令的 --dry-run 不能同时使用
+    assert_eq!(rejected(&["--server-dry-run"]), 2);
+}
+
+/// 在 workdir(name, "") 中创建一个有一个提交的仓库，当前分支为 work
+fn repository(name: &str) -> PathBuf {
+    let dir = workdir(name, "");
+    let _ = std::fs::remove_dir_all(dir.join(".git"));
+    for args in [
+        &["init", "-q", "-b", "work"][..],
+        &["commit", "-q", "--allow-empty", "-m", "init"],
+    ] {
+        git(&dir, args);
+    }
+    dir
+}
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args([
+            "-c",
+            "user.name=enjoy",
+            "-c",
+            "user.email=enjoy@example.com",
+        ])
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env_remove("GIT_CONFIG_GLOBAL")
+        .status()
+        .expect("无法运行 git");
+    assert!(status.success(), "git {:?}", args);
+}
+
+#[test]
+fn target_branch_from_upstream() {
+    let dir = repository("upstream");
+    let (command, stderr) = dry_run_with_config("upstream", "", &[]);
+    assert_eq!(command, "git push origin HEAD:refs/for/develop");
+    assert!(stderr.contains("没有上游分支"), "{}", stderr);
+    git(&dir, &["config", "branch.work.merge", "refs/heads/release"]);
+    let (command, stderr) = dry_run_with_config("upstream", "", &[]);
+    assert_eq!(command, "git push origin HEAD:refs/for/release");
+    assert_eq!(stderr, "");
+    // --branch 优先于上游分支
+    let (command, _) = dry_run_with_config("upstream", "", &["--branch", "main"]);
+    assert_eq!(command, "git push origin HEAD:refs/for/main");
+    // HEAD 分离时无法确定目标分支
+    git(&dir, &["checkout", "-q", "--detach"]);
+    let output = run("upstream", "", &[]);
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+}
+
+#[test]
+fn defaults_from_git_config() {
+    let gitconfig =
+        "[enjoy \"gerrit\"]\n\tremote = gerrit\n\tbranch = main\n\treviewers = alice, bob\n";
+    let (command, _) = dry_run_with_config("git-config", gitconfig, &[]);
+    assert_eq!(command, "git push gerrit HEAD:refs/for/main%r=alice,r=bob");
+    // 命令行优先于 git config
+    let (command, _) = dry_run_with_config(
+        "git-config",
+        gitconfig,
+        &["--remote", "origin", "--reviewer", "carol"],
+    );
+    assert_eq!(command, "git push origin HEAD:refs/for/main%r=carol");
+    let (settings, _) = dry_run_with_config("git-config", gitconfig, &["--show-config"]);
+    assert!(
+        settings.contains("远程仓库: gerrit (git config)"),
+        "{}",
+        settings
+    );
+    assert!(settings.contains("主题前缀: (无) (默认)"), "{}", settings);
+    // git config 中不合法的值报告出错的键
+    let output = run(
+        "bad-git-config",
+        "[enjoy \"gerrit\"]\n\tbranch = \"a b\"\n",
+        &[],
+    );
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("enjoy.gerrit.branch"), "{}", stderr);
+}
+
+#[test]
+fn commits_to_push() {
+    let dir = repository("commits");
+    git(
+        &dir,
+        &["remote", "add", "origin", "/nonexistent/kernel.git"],
+    );
+    git(&dir, &["update-ref", "refs/remotes/origin/develop", "HEAD"]);
+    // 没有领先的提交时不推送
+    let output = gerrit("commits", "", &["--branch", "develop"]);
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("没有要推送的提交"), "{}", stderr);
+    for subject in ["Fix UART", "Add SPI"] {
+        let message = format!("{}\n\nChange-Id: I{:040x}", subject, subject.len());
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", &message]);
+    }
+    // 列出要推送的提交之后，标准输入不是终端，不能确认，因此没有执行推送
+    let output = gerrit("commits", "", &["--branch", "develop"]);
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("领先 origin/develop 2 个"), "{}", stderr);
+    assert!(stderr.contains("Fix UART"), "{}", stderr);
+    assert!(stderr.contains("Add SPI"), "{}", stderr);
+    assert!(stderr.contains("会在 Gerrit 上创建 2 个评审"), "{}", stderr);
+    assert!(stderr.contains("标准输入不是终端"), "{}", stderr);
+}
+
+#[test]
+fn refspec() {
+    let (command, _) = dry_run(&["--refspec", "refs/for/main%topic=x", "--reviewer", "alice"]);
+    assert_eq!(
+        command,
+        "git push origin HEAD:refs/for/main%topic=x,r=alice"
+    );
+    assert_eq!(rejected(&["--refspec", "HEAD:refs/for/main"]), 2);
+}
+
+#[test]
+fn push_options() {
+    let (command, _) = dry_run(&[
+        "--branch",
+        "main",
+        "--wip",
+        "-o",
+        "notify=NONE",
+        "-o",
+        "m=take two",
+    ]);
+    assert_eq!(
+        command,
+        "git push origin HEAD:refs/for/main%wip,notify=NONE,m=take%20two"
+    );
+    assert_eq!(rejected(&["-o", "notify=NO NE"]), 2);
+}
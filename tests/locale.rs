@@ -0,0 +1,77 @@
+//! 输出语言：--lang 优先，其次按 LC_ALL、LC_MESSAGES、LANG 选择，C 为英文，默认为中文
+
+use std::process::{Command, Output};
+
+/// 运行 enjoy，不读取用户的配置文件，只设置给出的语言环境变量
+fn enjoy(env: &[(&str, &str)], args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_enjoy"))
+        .arg("--no-config")
+        .args(args)
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES")
+        .env_remove("LANG")
+        .envs(env.iter().copied())
+        .output()
+        .expect("无法运行 enjoy")
+}
+
+fn stdout(env: &[(&str, &str)], args: &[&str]) -> String {
+    let output = enjoy(env, args);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn stderr(env: &[(&str, &str)], args: &[&str]) -> String {
+    String::from_utf8(enjoy(env, args).stderr).unwrap()
+}
+
+const ENGLISH_RESULT: &str = "Decimal: 31\nHex: 0x1F\nOctal: 0o37\nBinary: \n\
+                              0001 1111\n   4    0\n   1    F\n";
+const CHINESE_RESULT: &str = "十进制: 31\n十六进制: 0x1F\n八进制: 0o37\n二进制: \n\
+                              0001 1111\n   4    0\n   1    F\n";
+const ENGLISH_ERROR: &str = "error: token 2 ('/'): division by zero\n  1 / 0\n    ^\n";
+const CHINESE_ERROR: &str = "错误: 第 2 个元素 ('/'): 除零错误\n  1 / 0\n    ^\n";
+
+#[test]
+fn english_for_c_locale() {
+    let env = [("LANG", "C")];
+    assert_eq!(stdout(&env, &["calc", "0x1F"]), ENGLISH_RESULT);
+    assert_eq!(stderr(&env, &["calc", "1 / 0"]), ENGLISH_ERROR);
+    assert_eq!(
+        stdout(&[("LANG", "en_US.UTF-8")], &["calc", "0x1F"]),
+        ENGLISH_RESULT
+    );
+}
+
+#[test]
+fn chinese_for_chinese_locale() {
+    let env = [("LANG", "zh_CN.UTF-8")];
+    assert_eq!(stdout(&env, &["calc", "0x1F"]), CHINESE_RESULT);
+    assert_eq!(stderr(&env, &["calc", "1 / 0"]), CHINESE_ERROR);
+}
+
+#[test]
+fn default_and_precedence() {
+    // 没有设置或无法识别时为中文
+    assert_eq!(stdout(&[], &["calc", "0x1F"]), CHINESE_RESULT);
+    assert_eq!(
+        stdout(&[("LANG", "fr_FR.UTF-8")], &["calc", "0x1F"]),
+        CHINESE_RESULT
+    );
+    // LC_ALL 优先于 LANG，--lang 优先于环境变量
+    assert_eq!(
+        stdout(
+            &[("LC_ALL", "C"), ("LANG", "zh_CN.UTF-8")],
+            &["calc", "0x1F"]
+        ),
+        ENGLISH_RESULT
+    );
+    assert_eq!(
+        stdout(&[("LANG", "C")], &["--lang", "zh", "calc", "0x1F"]),
+        CHINESE_RESULT
+    );
+    assert_eq!(
+        stderr(&[("LANG", "zh_CN.UTF-8")], &["--lang=en", "calc", "1 / 0"]),
+        ENGLISH_ERROR
+    );
+}